@@ -0,0 +1,255 @@
+use gtk4::{
+    prelude::*,
+    Window, Box as GtkBox, Orientation, Align, Label, Entry, CheckButton, ComboBoxText
+};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::ui::components;
+use crate::downloader::{CookieSource, SiteLogin, COOKIE_BROWSERS};
+use super::credentials;
+use super::Settings;
+
+// Configured logins, with the password if it was entered in this dialog
+type LoginList = Rc<RefCell<Vec<(SiteLogin, Option<String>)>>>;
+
+// Authentication section of the preferences: cookies, ~/.netrc and per-site logins
+#[derive(Clone)]
+pub struct AuthSection {
+    pub container: GtkBox,
+    cookie_combo: ComboBoxText,
+    cookie_file_entry: Entry,
+    profile_entry: Entry,
+    netrc_check: CheckButton,
+    logins: LoginList,  // New passwords are stored on save
+}
+
+impl AuthSection {
+    fn cookie_source(&self) -> CookieSource {
+        match self.cookie_combo.active_id().as_deref() {
+            Some("file") if !self.cookie_file_entry.text().trim().is_empty() => CookieSource::File {
+                path: self.cookie_file_entry.text().trim().to_string(),
+            },
+            Some(browser) if COOKIE_BROWSERS.contains(&browser) => CookieSource::Browser {
+                browser: browser.to_string(),
+                profile: self.profile_entry.text().trim().to_string(),
+            },
+            _ => CookieSource::None,
+        }
+    }
+
+    // Write the choices into the settings and update the credential store
+    pub fn apply(&self, settings: &mut Settings) {
+        let logins = self.logins.borrow();
+
+        for removed in settings.site_logins.iter().filter(|login| !logins.iter().any(|(kept, _)| kept == *login)) {
+            credentials::remove_password(removed);
+        }
+
+        for (login, password) in logins.iter() {
+            if let Some(password) = password {
+                if let Err(e) = credentials::store_password(login, password) {
+                    eprintln!("Failed to store password for {}: {:#}", login.site, e);
+                }
+            }
+        }
+
+        settings.cookies = self.cookie_source();
+        settings.use_netrc = self.netrc_check.is_active();
+        settings.site_logins = logins.iter().map(|(login, _)| login.clone()).collect();
+    }
+
+    fn update_visibility(&self, file_row: &GtkBox, profile_box: &GtkBox) {
+        let id = self.cookie_combo.active_id();
+        file_row.set_visible(id.as_deref() == Some("file"));
+        profile_box.set_visible(id.as_deref().is_some_and(|id| COOKIE_BROWSERS.contains(&id)));
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+// One configured login with a Remove button
+fn append_login_row(list: &GtkBox, logins: &LoginList, login: SiteLogin) {
+    let row = GtkBox::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(8)
+        .build();
+
+    let label = Label::builder()
+        .label(format!("{} on {}", login.username, login.site))
+        .halign(Align::Start)
+        .hexpand(true)
+        .build();
+
+    let remove_button = components::create_button("Remove", "browse-button");
+
+    row.append(&label);
+    row.append(&remove_button);
+    list.append(&row);
+
+    let list = list.clone();
+    let logins = Rc::clone(logins);
+    remove_button.connect_clicked(move |_| {
+        logins.borrow_mut().retain(|(existing, _)| *existing != login);
+        list.remove(&row);
+    });
+}
+
+pub fn create_auth_section(parent: &Window, settings: &Settings) -> AuthSection {
+    let container = GtkBox::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(6)
+        .build();
+
+    container.add_css_class("options-box");
+    container.append(&components::create_section_title("Authentication"));
+
+    // Cookies for members-only, age-restricted and private media
+    let (cookie_box, cookie_combo) = components::create_dropdown("Cookies from:");
+    cookie_combo.append(Some("none"), "No cookies");
+    cookie_combo.append(Some("file"), "cookies.txt file");
+    for browser in COOKIE_BROWSERS {
+        cookie_combo.append(Some(browser), &capitalize(browser));
+    }
+    cookie_combo.set_sensitive(true);
+
+    let (file_box, cookie_file_entry) = components::create_labeled_entry("Cookies file:", "Netscape cookies.txt", None);
+    file_box.set_hexpand(true);
+
+    let file_row = GtkBox::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(6)
+        .build();
+
+    let browse_button = components::create_button("Browse", "browse-button");
+    browse_button.set_valign(Align::End);
+
+    file_row.append(&file_box);
+    file_row.append(&browse_button);
+
+    let (profile_box, profile_entry) = components::create_labeled_entry("Browser profile:", "Default profile", None);
+
+    match &settings.cookies {
+        CookieSource::None => {
+            cookie_combo.set_active_id(Some("none"));
+        },
+        CookieSource::File { path } => {
+            cookie_combo.set_active_id(Some("file"));
+            cookie_file_entry.set_text(path);
+        },
+        CookieSource::Browser { browser, profile } => {
+            cookie_combo.set_active_id(Some(browser));
+            profile_entry.set_text(profile);
+        },
+    }
+
+    let netrc_check = CheckButton::with_label("Use logins from ~/.netrc");
+    netrc_check.set_active(settings.use_netrc);
+
+    // Site logins; passwords are kept in the Secret Service
+    let logins_label = Label::builder()
+        .label("Site logins:")
+        .halign(Align::Start)
+        .build();
+
+    logins_label.add_css_class("input-label");
+
+    let login_list = GtkBox::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(2)
+        .build();
+
+    let logins = Rc::new(RefCell::new(Vec::new()));
+    for login in &settings.site_logins {
+        logins.borrow_mut().push((login.clone(), None));
+        append_login_row(&login_list, &logins, login.clone());
+    }
+
+    let add_row = GtkBox::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(6)
+        .build();
+
+    let site_entry = Entry::builder().placeholder_text("Site, e.g. vimeo.com").hexpand(true).build();
+    let username_entry = Entry::builder().placeholder_text("Username").hexpand(true).build();
+    let password_entry = Entry::builder().placeholder_text("Password").visibility(false).hexpand(true).build();
+    let add_button = components::create_button("Add", "browse-button");
+
+    add_row.append(&site_entry);
+    add_row.append(&username_entry);
+    add_row.append(&password_entry);
+    add_row.append(&add_button);
+
+    container.append(&cookie_box);
+    container.append(&file_row);
+    container.append(&profile_box);
+    container.append(&netrc_check);
+    container.append(&logins_label);
+    container.append(&login_list);
+    container.append(&add_row);
+
+    let section = AuthSection {
+        container,
+        cookie_combo: cookie_combo.clone(),
+        cookie_file_entry: cookie_file_entry.clone(),
+        profile_entry,
+        netrc_check,
+        logins: Rc::clone(&logins),
+    };
+
+    section.update_visibility(&file_row, &profile_box);
+    let section_clone = section.clone();
+    cookie_combo.connect_changed(move |_| {
+        section_clone.update_visibility(&file_row, &profile_box);
+    });
+
+    let parent = parent.clone();
+    browse_button.connect_clicked(move |_| {
+        let dialog = components::create_file_chooser_dialog(&parent, "Select Cookies File");
+
+        let entry_clone = cookie_file_entry.clone();
+        dialog.connect_response(move |dialog, response| {
+            if response == gtk4::ResponseType::Accept {
+                if let Some(path) = dialog.file().and_then(|file| file.path()) {
+                    entry_clone.set_text(&path.to_string_lossy());
+                }
+            }
+            dialog.destroy();
+        });
+
+        dialog.present();
+    });
+
+    add_button.connect_clicked(move |_| {
+        let site = site_entry.text().trim().trim_start_matches("https://").trim_start_matches("http://")
+            .trim_end_matches('/').to_lowercase();
+        let username = username_entry.text().trim().to_string();
+        let password = password_entry.text().to_string();
+        if site.is_empty() || username.is_empty() || password.is_empty() {
+            return;
+        }
+
+        // Adding a login again replaces its password
+        let login = SiteLogin { site, username };
+        let position = logins.borrow().iter().position(|(existing, _)| *existing == login);
+        match position {
+            Some(index) => logins.borrow_mut()[index].1 = Some(password),
+            None => {
+                logins.borrow_mut().push((login.clone(), Some(password)));
+                append_login_row(&login_list, &logins, login);
+            },
+        }
+
+        for entry in [&site_entry, &username_entry, &password_entry] {
+            entry.set_text("");
+        }
+    });
+
+    section
+}
@@ -0,0 +1,271 @@
+use gtk4::{
+    prelude::*,
+    ApplicationWindow, Box as GtkBox, Orientation, Label, Entry
+};
+use serde::{Serialize, Deserialize};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::thread;
+
+use crate::ui::components;
+use crate::downloader::{SupportedSites, QualityPreset, QueueJob, JobKind, AudioTags};
+use super::Settings;
+
+// What happens when a supported link is copied while watching the clipboard
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClipboardAction {
+    #[default]
+    Offer,     // Show the banner above the URL entry
+    Fetch,     // Fill in the URL and fetch its formats
+    Enqueue,   // Queue it right away with the default quality
+}
+
+impl ClipboardAction {
+    pub const ALL: [ClipboardAction; 3] = [ClipboardAction::Offer, ClipboardAction::Fetch, ClipboardAction::Enqueue];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            ClipboardAction::Offer => "offer",
+            ClipboardAction::Fetch => "fetch",
+            ClipboardAction::Enqueue => "enqueue",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ClipboardAction::Offer => "Offer the link",
+            ClipboardAction::Fetch => "Fetch formats",
+            ClipboardAction::Enqueue => "Add to queue with default quality",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<ClipboardAction> {
+        ClipboardAction::ALL.into_iter().find(|action| action.id() == id)
+    }
+}
+
+// Preset matching the preferences of the last used tab
+fn default_preset(settings: &Settings) -> QualityPreset {
+    if settings.last_tab == "audio" {
+        QualityPreset::ALL.into_iter()
+            .find(|preset| matches!(preset, QualityPreset::Audio(format) if *format == settings.audio_format))
+            .unwrap_or(QualityPreset::Audio("mp3"))
+    } else {
+        settings.preferred_height
+            .map(QualityPreset::MaxHeight)
+            .unwrap_or(QualityPreset::BestVideo)
+    }
+}
+
+// Queue job for a copied link, downloaded without fetching its formats first
+pub fn default_job(url: &str, settings: &Settings) -> QueueJob {
+    let preset = default_preset(settings);
+    let kind = preset.job_kind();
+    let tab = if matches!(kind, JobKind::Audio { .. }) { "audio" } else { "video" };
+
+    let tags = match kind {
+        JobKind::Audio { .. } if settings.write_audio_tags || settings.embed_cover => Some(AudioTags {
+            embed_metadata: settings.write_audio_tags,
+            embed_cover: settings.embed_cover,
+            ..AudioTags::default()
+        }),
+        _ => None,
+    };
+
+    QueueJob {
+        url: url.to_string(),
+        output_path: settings.output_dir_for(tab).to_string(),
+        label: format!("{} - {}", preset.label(), url),
+        kind,
+        title: None,
+        duration: None,
+        subtitles: None,
+        tags,
+        output_template: Some(settings.template_for(tab)),
+        rate_limit: None,
+    }
+}
+
+// State shared by the clipboard and focus handlers
+struct Watcher {
+    settings: Rc<RefCell<Settings>>,
+    url_entry: Entry,
+    banner: GtkBox,
+    banner_label: Label,
+    pending_url: RefCell<String>,
+    last_seen: RefCell<String>,
+    sites: Rc<RefCell<SupportedSites>>,
+    sites_requested: Cell<bool>,
+    on_fetch: Box<dyn Fn(String)>,
+    on_enqueue: Box<dyn Fn(QueueJob)>,
+}
+
+impl Watcher {
+    fn check(&self, text: &str) {
+        if !self.settings.borrow().watch_clipboard {
+            return;
+        }
+
+        let url = text.trim();
+        if url.is_empty() || url.contains(char::is_whitespace) {
+            return;
+        }
+
+        // Each copied link is handled once, not on every focus change
+        if *self.last_seen.borrow() == url {
+            return;
+        }
+        *self.last_seen.borrow_mut() = url.to_string();
+
+        if self.url_entry.text() == url {
+            return;
+        }
+
+        self.load_sites();
+        if !self.sites.borrow().matches(url) {
+            return;
+        }
+
+        eprintln!("Supported link in clipboard: {}", url);
+        let action = self.settings.borrow().clipboard_action;
+        match action {
+            ClipboardAction::Offer => {
+                *self.pending_url.borrow_mut() = url.to_string();
+                self.banner_label.set_text(&format!("Copied link: {}", url));
+                self.banner_label.set_tooltip_text(Some(url));
+                self.banner.set_visible(true);
+            },
+            ClipboardAction::Fetch => (self.on_fetch)(url.to_string()),
+            ClipboardAction::Enqueue => {
+                let job = default_job(url, &self.settings.borrow());
+                (self.on_enqueue)(job);
+            },
+        }
+    }
+
+    // The extractor list of yt-dlp replaces the built-in sites once it has been read
+    fn load_sites(&self) {
+        if self.sites_requested.replace(true) {
+            return;
+        }
+
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+        thread::spawn(move || {
+            match SupportedSites::load() {
+                Ok(sites) => {
+                    let _ = sender.send(sites);
+                },
+                Err(e) => eprintln!("Error loading supported sites: {:?}", e),
+            }
+        });
+
+        let sites = Rc::clone(&self.sites);
+        receiver.attach(None, move |loaded: SupportedSites| {
+            *sites.borrow_mut() = loaded;
+            glib::Continue(false)
+        });
+    }
+
+    fn take_pending(&self) -> String {
+        self.banner.set_visible(false);
+        std::mem::take(&mut *self.pending_url.borrow_mut())
+    }
+}
+
+// Watch the clipboard for links of supported sites and return the banner offering them
+pub fn create_clipboard_watcher<F, G>(window: &ApplicationWindow, url_entry: &Entry, settings: Rc<RefCell<Settings>>,
+                                      on_fetch: F, on_enqueue: G) -> GtkBox
+where
+    F: Fn(String) + 'static,
+    G: Fn(QueueJob) + 'static,
+{
+    let banner = GtkBox::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(8)
+        .visible(false)
+        .build();
+
+    banner.add_css_class("clipboard-banner");
+
+    let banner_label = Label::builder()
+        .hexpand(true)
+        .xalign(0.0)
+        .ellipsize(gtk4::pango::EllipsizeMode::Middle)
+        .build();
+
+    let fetch_button = components::create_button("Fetch", "browse-button");
+    let queue_button = components::create_button("Add to Queue", "browse-button");
+    let dismiss_button = components::create_button("Dismiss", "browse-button");
+
+    banner.append(&banner_label);
+    banner.append(&fetch_button);
+    banner.append(&queue_button);
+    banner.append(&dismiss_button);
+
+    let watcher = Rc::new(Watcher {
+        settings,
+        url_entry: url_entry.clone(),
+        banner: banner.clone(),
+        banner_label,
+        pending_url: RefCell::new(String::new()),
+        last_seen: RefCell::new(String::new()),
+        sites: Rc::new(RefCell::new(SupportedSites::default())),
+        sites_requested: Cell::new(false),
+        on_fetch: Box::new(on_fetch),
+        on_enqueue: Box::new(on_enqueue),
+    });
+
+    let watcher_clone = Rc::clone(&watcher);
+    fetch_button.connect_clicked(move |_| {
+        let url = watcher_clone.take_pending();
+        (watcher_clone.on_fetch)(url);
+    });
+
+    let watcher_clone = Rc::clone(&watcher);
+    queue_button.connect_clicked(move |_| {
+        let url = watcher_clone.take_pending();
+        let job = default_job(&url, &watcher_clone.settings.borrow());
+        (watcher_clone.on_enqueue)(job);
+    });
+
+    let watcher_clone = Rc::clone(&watcher);
+    dismiss_button.connect_clicked(move |_| {
+        watcher_clone.take_pending();
+    });
+
+    let Some(display) = gtk4::gdk::Display::default() else {
+        return banner;
+    };
+    let clipboard = display.clipboard();
+
+    let read_clipboard = {
+        let watcher = Rc::clone(&watcher);
+        let clipboard = clipboard.clone();
+        move || {
+            if !watcher.settings.borrow().watch_clipboard {
+                return;
+            }
+            let watcher = Rc::clone(&watcher);
+            clipboard.read_text_async(None::<&gtk4::gio::Cancellable>, move |result| {
+                if let Ok(Some(text)) = result {
+                    watcher.check(&text);
+                }
+            });
+        }
+    };
+
+    // Wayland only reports clipboard changes to focused windows, so check on focus as well
+    let read_on_change = read_clipboard.clone();
+    clipboard.connect_changed(move |_| {
+        read_on_change();
+    });
+
+    window.connect_is_active_notify(move |window| {
+        if window.is_active() {
+            read_clipboard();
+        }
+    });
+
+    banner
+}
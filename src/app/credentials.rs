@@ -0,0 +1,147 @@
+use anyhow::{Result, Context};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use crate::downloader::{AuthConfig, SiteLogin};
+use super::Settings;
+
+// Attribute marking our items in the Secret Service
+const SECRET_APPLICATION: &str = "HyprDownloader";
+
+// Passwords of site logins go into the Secret Service through secret-tool (libsecret),
+// or into a file only the user can read if no keyring is available
+pub fn store_password(login: &SiteLogin, password: &str) -> Result<()> {
+    match secret_tool_store(login, password) {
+        Ok(()) => remove_file_password(login),
+        Err(e) => {
+            eprintln!("Secret Service unavailable, storing the password in a file: {:#}", e);
+            let mut passwords = file_passwords()?;
+            passwords.insert(file_key(login), password.to_string());
+            write_file_passwords(&passwords)
+        }
+    }
+}
+
+pub fn lookup_password(login: &SiteLogin) -> Option<String> {
+    secret_tool_lookup(login)
+        .or_else(|| file_passwords().ok()?.remove(&file_key(login)))
+}
+
+pub fn remove_password(login: &SiteLogin) {
+    let _ = secret_tool(&["clear"], login).output();
+    if let Err(e) = remove_file_password(login) {
+        eprintln!("Failed to remove stored password: {:#}", e);
+    }
+}
+
+// Auth settings together with the stored passwords, as passed to every yt-dlp call
+pub fn load_auth_config(settings: &Settings) -> AuthConfig {
+    let logins = settings.site_logins.iter()
+        .filter_map(|login| match lookup_password(login) {
+            Some(password) => Some((login.clone(), password)),
+            None => {
+                eprintln!("No password stored for {} on {}", login.username, login.site);
+                None
+            }
+        })
+        .collect();
+
+    AuthConfig {
+        cookies: settings.cookies.clone(),
+        use_netrc: settings.use_netrc,
+        logins,
+    }
+}
+
+// secret-tool ACTION application HyprDownloader site SITE username USER
+fn secret_tool(args: &[&str], login: &SiteLogin) -> Command {
+    let mut command = Command::new("secret-tool");
+    command.args(args)
+        .arg("application").arg(SECRET_APPLICATION)
+        .arg("site").arg(&login.site)
+        .arg("username").arg(&login.username);
+    command
+}
+
+fn secret_tool_store(login: &SiteLogin, password: &str) -> Result<()> {
+    let label = format!("--label=HyprDownloader: {} on {}", login.username, login.site);
+    let mut child = secret_tool(&["store", &label], login)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run secret-tool")?;
+
+    // The password is read from stdin so it never shows up in the process list
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(password.as_bytes())?;
+    }
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("secret-tool store failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
+
+fn secret_tool_lookup(login: &SiteLogin) -> Option<String> {
+    let output = secret_tool(&["lookup"], login).output().ok()?;
+    if !output.status.success() || output.stdout.is_empty() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+        .map(|password| password.trim_end_matches('\n').to_string())
+}
+
+fn credentials_path() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
+
+    Ok(config_dir.join("HyprDownloader").join("credentials.json"))
+}
+
+fn file_key(login: &SiteLogin) -> String {
+    format!("{}\t{}", login.site, login.username)
+}
+
+fn file_passwords() -> Result<HashMap<String, String>> {
+    let path = credentials_path()?;
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+
+    let contents = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&contents)
+        .with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+fn write_file_passwords(passwords: &HashMap<String, String>) -> Result<()> {
+    let path = credentials_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // Readable by the user only
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&path)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    file.write_all(serde_json::to_string_pretty(passwords)?.as_bytes())?;
+    Ok(())
+}
+
+fn remove_file_password(login: &SiteLogin) -> Result<()> {
+    let mut passwords = file_passwords()?;
+    if passwords.remove(&file_key(login)).is_some() {
+        write_file_passwords(&passwords)?;
+    }
+    Ok(())
+}
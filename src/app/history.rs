@@ -0,0 +1,138 @@
+use anyhow::{Result, Context};
+use serde::{Serialize, Deserialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::downloader::{QueueJob, JobKind};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum HistoryOutcome {
+    Completed,
+    Failed(String),
+    Cancelled,
+}
+
+// One finished download; the job is kept so it can be started again with the same settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub timestamp: u64,               // Unix seconds
+    pub url: String,
+    pub title: String,
+    pub format_id: String,
+    pub output_file: Option<String>,
+    pub size: Option<u64>,            // Bytes
+    pub duration: Option<u64>,        // Seconds
+    pub outcome: HistoryOutcome,
+    pub job: QueueJob,
+}
+
+impl HistoryEntry {
+    pub fn new(job: &QueueJob, output_file: Option<PathBuf>, outcome: HistoryOutcome) -> Self {
+        let format_id = match &job.kind {
+            JobKind::Video { format_id, .. } | JobKind::Audio { format_id, .. } => format_id.clone(),
+            JobKind::Direct { .. } => "direct".to_string(),
+        };
+
+        // Without fetched metadata the file name is the best available title
+        let title = job.title.clone()
+            .or_else(|| output_file.as_ref()
+                .and_then(|path| path.file_stem())
+                .map(|stem| stem.to_string_lossy().to_string()))
+            .unwrap_or_else(|| job.url.clone());
+
+        let size = output_file.as_ref()
+            .and_then(|path| fs::metadata(path).ok())
+            .map(|metadata| metadata.len());
+
+        HistoryEntry {
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+            url: job.url.clone(),
+            title,
+            format_id,
+            output_file: output_file.map(|path| path.to_string_lossy().to_string()),
+            size,
+            duration: job.duration,
+            outcome,
+            job: job.clone(),
+        }
+    }
+
+    // Case-insensitive match against title, URL and file path
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return true;
+        }
+
+        self.title.to_lowercase().contains(&query) ||
+            self.url.to_lowercase().contains(&query) ||
+            self.output_file.as_ref().is_some_and(|path| path.to_lowercase().contains(&query))
+    }
+}
+
+// Download history persisted as JSON in the XDG data dir, oldest entry first
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct History {
+    entries: Vec<HistoryEntry>,
+}
+
+impl History {
+    pub fn history_path() -> Result<PathBuf> {
+        let data_dir = dirs::data_dir()
+            .ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?;
+
+        Ok(data_dir.join("HyprDownloader").join("history.json"))
+    }
+
+    // Load the history, starting empty if the file is missing or invalid
+    pub fn load() -> History {
+        match Self::try_load() {
+            Ok(history) => history,
+            Err(e) => {
+                eprintln!("Starting with empty history: {:#}", e);
+                History::default()
+            }
+        }
+    }
+
+    fn try_load() -> Result<History> {
+        let path = Self::history_path()?;
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::history_path()?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(&path, contents)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    // Append an entry and write the history right away
+    pub fn record(&mut self, entry: HistoryEntry) {
+        self.entries.push(entry);
+        if let Err(e) = self.save() {
+            eprintln!("Failed to save history: {}", e);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        if let Err(e) = self.save() {
+            eprintln!("Failed to save history: {}", e);
+        }
+    }
+}
@@ -0,0 +1,263 @@
+use gtk4::{
+    prelude::*,
+    Window, Box as GtkBox, Orientation, Align, Label, Button, SearchEntry, ScrolledWindow
+};
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::ui::components;
+use crate::downloader::QueueJob;
+use super::history::{History, HistoryEntry, HistoryOutcome};
+use super::utils::{format_duration, format_size};
+
+// History page in the main stack: searchable list of past downloads
+#[derive(Clone)]
+pub struct HistoryPage {
+    pub container: GtkBox,
+    history: Rc<RefCell<History>>,
+    list: GtkBox,
+    search_entry: SearchEntry,
+    empty_label: Label,
+    window: Window,
+    on_redownload: Rc<dyn Fn(QueueJob)>,
+}
+
+impl HistoryPage {
+    // Add a finished download and show it at the top of the list
+    pub fn record(&self, entry: HistoryEntry) {
+        self.history.borrow_mut().record(entry);
+        self.refresh();
+    }
+
+    // Rebuild the rows for the current search text, newest first
+    pub fn refresh(&self) {
+        while let Some(child) = self.list.first_child() {
+            self.list.remove(&child);
+        }
+
+        let query = self.search_entry.text().to_string();
+        let history = self.history.borrow();
+        let mut shown = 0;
+
+        for entry in history.entries().iter().rev().filter(|entry| entry.matches(&query)) {
+            self.list.append(&self.create_row(entry));
+            shown += 1;
+        }
+
+        self.empty_label.set_text(if history.entries().is_empty() {
+            "No downloads yet"
+        } else {
+            "No downloads match the search"
+        });
+        self.empty_label.set_visible(shown == 0);
+    }
+
+    fn create_row(&self, entry: &HistoryEntry) -> GtkBox {
+        let row = GtkBox::builder()
+            .orientation(Orientation::Vertical)
+            .spacing(4)
+            .build();
+
+        row.add_css_class("queue-row");
+
+        let title_label = Label::builder()
+            .label(&entry.title)
+            .halign(Align::Start)
+            .ellipsize(gtk4::pango::EllipsizeMode::End)
+            .build();
+
+        title_label.add_css_class("info-title");
+
+        let details_label = Label::builder()
+            .label(entry_details(entry))
+            .halign(Align::Start)
+            .ellipsize(gtk4::pango::EllipsizeMode::End)
+            .build();
+
+        details_label.add_css_class("status-label");
+        details_label.set_tooltip_text(Some(&entry.url));
+
+        let button_row = GtkBox::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(4)
+            .halign(Align::End)
+            .build();
+
+        let open_file_button = create_row_button("Open File");
+        let open_folder_button = create_row_button("Open Folder");
+        let copy_url_button = create_row_button("Copy URL");
+        let again_button = create_row_button("Download Again");
+
+        // Only offer the file actions when the file is still there
+        let output_file = entry.output_file.clone()
+            .filter(|path| Path::new(path).exists());
+        open_file_button.set_sensitive(output_file.is_some());
+        open_folder_button.set_sensitive(output_file.is_some());
+
+        button_row.append(&open_file_button);
+        button_row.append(&open_folder_button);
+        button_row.append(&copy_url_button);
+        button_row.append(&again_button);
+
+        row.append(&title_label);
+        row.append(&details_label);
+        row.append(&button_row);
+
+        if let Some(path) = output_file {
+            let window = self.window.clone();
+            let file_path = path.clone();
+            open_file_button.connect_clicked(move |_| {
+                open_with_default_app(&window, &file_path);
+            });
+
+            let window = self.window.clone();
+            open_folder_button.connect_clicked(move |_| {
+                let folder = Path::new(&path).parent()
+                    .map(|folder| folder.to_string_lossy().to_string())
+                    .unwrap_or_else(|| ".".to_string());
+                open_with_default_app(&window, &folder);
+            });
+        }
+
+        let url = entry.url.clone();
+        copy_url_button.connect_clicked(move |button| {
+            button.clipboard().set_text(&url);
+        });
+
+        let job = entry.job.clone();
+        let on_redownload = Rc::clone(&self.on_redownload);
+        again_button.connect_clicked(move |_| {
+            on_redownload(job.clone());
+        });
+
+        row
+    }
+}
+
+fn create_row_button(label: &str) -> Button {
+    let button = Button::with_label(label);
+    button.add_css_class("queue-button");
+    button
+}
+
+// One line summary: date, outcome, format, size and duration
+fn entry_details(entry: &HistoryEntry) -> String {
+    let mut details = Vec::new();
+
+    if let Ok(date) = gtk4::glib::DateTime::from_unix_local(entry.timestamp as i64)
+        .and_then(|date| date.format("%Y-%m-%d %H:%M")) {
+        details.push(date.to_string());
+    }
+
+    details.push(match &entry.outcome {
+        HistoryOutcome::Completed => "Completed".to_string(),
+        HistoryOutcome::Failed(reason) => format!("Failed: {}", reason),
+        HistoryOutcome::Cancelled => "Cancelled".to_string(),
+    });
+
+    details.push(format!("Format {}", entry.format_id));
+
+    if let Some(size) = entry.size {
+        details.push(format_size(size));
+    }
+
+    if let Some(duration) = entry.duration {
+        details.push(format_duration(duration));
+    }
+
+    details.join(" · ")
+}
+
+// Use xdg-open to open a file or folder (Linux)
+fn open_with_default_app(window: &Window, path: &str) {
+    if let Err(e) = std::process::Command::new("xdg-open").arg(path).spawn() {
+        components::show_error_dialog(window, "Open Error", &format!("Failed to open {}: {}", path, e));
+    }
+}
+
+// Build the history page; on_redownload receives the job of an entry to start it again
+pub fn create_history_page<F>(window: &impl IsA<Window>, on_redownload: F) -> HistoryPage
+where
+    F: Fn(QueueJob) + 'static,
+{
+    let container = GtkBox::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(8)
+        .margin_start(16)
+        .margin_end(16)
+        .margin_bottom(16)
+        .build();
+
+    container.add_css_class("content-box");
+
+    let card = GtkBox::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(6)
+        .vexpand(true)
+        .build();
+
+    card.add_css_class("card");
+
+    let header_row = GtkBox::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(8)
+        .build();
+
+    let title = components::create_section_title("Download History");
+    title.set_hexpand(true);
+
+    let search_entry = SearchEntry::builder()
+        .placeholder_text("Search title, URL or file")
+        .width_request(260)
+        .build();
+
+    let clear_button = components::create_button("Clear History", "browse-button");
+
+    header_row.append(&title);
+    header_row.append(&search_entry);
+    header_row.append(&clear_button);
+
+    let list = GtkBox::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(2)
+        .build();
+
+    let empty_label = components::create_status_label("No downloads yet");
+
+    let scroller = ScrolledWindow::builder()
+        .hscrollbar_policy(gtk4::PolicyType::Never)
+        .vexpand(true)
+        .child(&list)
+        .build();
+
+    // The empty label sits outside the list so refresh can clear all rows
+    card.append(&header_row);
+    card.append(&empty_label);
+    card.append(&scroller);
+    container.append(&card);
+
+    let page = HistoryPage {
+        container,
+        history: Rc::new(RefCell::new(History::load())),
+        list,
+        search_entry: search_entry.clone(),
+        empty_label,
+        window: window.clone().upcast(),
+        on_redownload: Rc::new(on_redownload),
+    };
+
+    let page_clone = page.clone();
+    search_entry.connect_search_changed(move |_| {
+        page_clone.refresh();
+    });
+
+    let page_clone = page.clone();
+    clear_button.connect_clicked(move |_| {
+        page_clone.history.borrow_mut().clear();
+        page_clone.refresh();
+    });
+
+    page.refresh();
+    page
+}
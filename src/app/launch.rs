@@ -0,0 +1,109 @@
+use gtk4::{
+    prelude::*,
+    gdk, gio, glib, Application, ApplicationWindow, DropTarget
+};
+
+use super::build_ui;
+
+// Present the existing window, building it on the first activation
+pub fn activate(app: &Application) {
+    match app.windows().into_iter().next() {
+        Some(window) => window.present(),
+        None => build_ui(app),
+    }
+}
+
+// `media_downloader --enqueue URL...` queues the URLs instead of fetching the first one
+pub fn add_main_options(app: &Application) {
+    app.add_main_option("enqueue", glib::Char::from(b'q'), glib::OptionFlags::NONE, glib::OptionArg::None,
+        "Add the URLs to the download queue", None);
+}
+
+// Also called in the running instance when the app is launched a second time
+pub fn handle_command_line(app: &Application, command_line: &gio::ApplicationCommandLine) -> i32 {
+    let enqueue = command_line.options_dict().contains("enqueue");
+    let urls: Vec<String> = command_line.arguments().iter()
+        .skip(1)
+        .map(|arg| arg.to_string_lossy().to_string())
+        .collect();
+
+    open_urls(app, &urls, enqueue);
+    0
+}
+
+// "Open With" from the desktop and D-Bus Open calls
+pub fn open_files(app: &Application, files: &[gio::File], _hint: &str) {
+    let urls: Vec<String> = files.iter().map(|file| file.uri().to_string()).collect();
+    open_urls(app, &urls, false);
+}
+
+// A single URL goes into the URL entry and is fetched, several are queued with the default quality
+pub fn open_urls(app: &Application, urls: &[String], enqueue: bool) {
+    activate(app);
+
+    let urls: Vec<&String> = urls.iter()
+        .filter(|url| {
+            let supported = url.starts_with("http://") || url.starts_with("https://");
+            if !supported {
+                println!("Ignoring '{}': not an http(s) URL", url);
+            }
+            supported
+        })
+        .collect();
+
+    match urls.as_slice() {
+        [] => {},
+        [url] if !enqueue => app.activate_action("open-url", Some(&url.to_variant())),
+        urls => {
+            for url in urls {
+                app.activate_action("enqueue-url", Some(&url.to_variant()));
+            }
+        },
+    }
+}
+
+// Register the actions open_urls triggers; on_open fills in and fetches, on_enqueue queues
+pub fn install_url_actions<F, G>(app: &Application, on_open: F, on_enqueue: G)
+where
+    F: Fn(String) + 'static,
+    G: Fn(String) + 'static,
+{
+    for (name, handler) in [("open-url", Box::new(on_open) as Box<dyn Fn(String)>), ("enqueue-url", Box::new(on_enqueue))] {
+        let action = gio::SimpleAction::new(name, Some(glib::VariantTy::STRING));
+        action.connect_activate(move |_, parameter| {
+            if let Some(url) = parameter.and_then(|parameter| parameter.get::<String>()) {
+                handler(url);
+            }
+        });
+        app.add_action(&action);
+    }
+}
+
+// Links arrive as a file list (text/uri-list from file managers and browsers) or as plain text
+// with one URL per line
+pub fn accept_dropped_urls(window: &ApplicationWindow) {
+    let drop_target = DropTarget::new(glib::Type::INVALID, gdk::DragAction::COPY);
+    drop_target.set_types(&[gdk::FileList::static_type(), glib::Type::STRING]);
+
+    let window_clone = window.clone();
+    drop_target.connect_drop(move |_, value, _, _| {
+        let urls: Vec<String> = if let Ok(files) = value.get::<gdk::FileList>() {
+            files.files().iter().map(|file| file.uri().to_string()).collect()
+        } else if let Ok(text) = value.get::<String>() {
+            text.lines()
+                .map(|line| line.trim().to_string())
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .collect()
+        } else {
+            return false;
+        };
+
+        let Some(app) = window_clone.application() else {
+            return false;
+        };
+        open_urls(&app, &urls, false);
+        true
+    });
+
+    window.add_controller(drop_target);
+}
@@ -1,6 +1,25 @@
 mod utils;
+mod settings;
+mod history;
 
-pub use utils::get_default_download_path;
+pub use utils::{get_default_download_path, format_duration, format_size};
+pub use settings::Settings;
+pub use history::{History, HistoryEntry, HistoryOutcome};
 pub use ui_builder::build_ui;
+pub use launch::{activate, add_main_options, handle_command_line, open_files};
+pub use credentials::load_auth_config;
 
-mod ui_builder; 
\ No newline at end of file
+mod ui_builder;
+mod playlist_dialog;
+mod preferences_dialog;
+mod history_page;
+mod subtitle_panel;
+mod tag_editor;
+mod template_row;
+mod clipboard_watcher;
+mod notifications;
+mod launch;
+mod credentials;
+mod auth_section;
+mod network_section;
+mod ytdlp_setup; 
\ No newline at end of file
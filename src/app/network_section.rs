@@ -0,0 +1,124 @@
+use gtk4::{
+    prelude::*,
+    Box as GtkBox, Orientation, Align, Label, Entry, ComboBoxText, SpinButton
+};
+
+use crate::ui::components;
+use crate::downloader::{NetworkOptions, IpVersion, parse_rate_limit};
+
+// Network section of the preferences: proxy, speed limit, retries and connection settings
+pub struct NetworkSection {
+    pub container: GtkBox,
+    proxy_entry: Entry,
+    rate_limit_entry: Entry,
+    retries_spin: SpinButton,
+    fragment_retries_spin: SpinButton,
+    timeout_spin: SpinButton,
+    ip_combo: ComboBoxText,
+    source_entry: Entry,
+}
+
+impl NetworkSection {
+    // The entered options, or a message for the first invalid field
+    pub fn options(&self) -> Result<NetworkOptions, String> {
+        let proxy = self.proxy_entry.text().trim().to_string();
+        let valid_scheme = ["http://", "https://", "socks4://", "socks4a://", "socks5://", "socks5h://"]
+            .iter()
+            .any(|scheme| proxy.to_lowercase().starts_with(scheme));
+        if !proxy.is_empty() && !valid_scheme {
+            return Err(format!("Invalid proxy '{}', use a URL like socks5://127.0.0.1:1080", proxy));
+        }
+
+        let source_address = self.source_entry.text().trim().to_string();
+        if !source_address.is_empty() && source_address.parse::<std::net::IpAddr>().is_err() {
+            return Err(format!("Invalid source address '{}', use an IP address of this machine", source_address));
+        }
+
+        let timeout = self.timeout_spin.value() as u32;
+
+        Ok(NetworkOptions {
+            proxy,
+            rate_limit: parse_rate_limit(&self.rate_limit_entry.text())?,
+            retries: self.retries_spin.value() as u32,
+            fragment_retries: self.fragment_retries_spin.value() as u32,
+            socket_timeout: Some(timeout).filter(|timeout| *timeout > 0),
+            ip_version: self.ip_combo.active_id()
+                .and_then(|id| IpVersion::from_id(&id))
+                .unwrap_or_default(),
+            source_address,
+        })
+    }
+}
+
+// Label on the left, spin button on the right
+fn create_spin_row(label: &str, max: f64, value: u32) -> (GtkBox, SpinButton) {
+    let row = GtkBox::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(8)
+        .build();
+
+    let label = Label::builder()
+        .label(label)
+        .halign(Align::Start)
+        .hexpand(true)
+        .build();
+
+    label.add_css_class("input-label");
+
+    let spin = SpinButton::with_range(0.0, max, 1.0);
+    spin.set_value(value as f64);
+
+    row.append(&label);
+    row.append(&spin);
+    (row, spin)
+}
+
+pub fn create_network_section(options: &NetworkOptions) -> NetworkSection {
+    let container = GtkBox::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(6)
+        .build();
+
+    container.add_css_class("options-box");
+    container.append(&components::create_section_title("Network"));
+
+    let (proxy_box, proxy_entry) = components::create_labeled_entry("Proxy:", "socks5://127.0.0.1:1080", Some(&options.proxy));
+    proxy_entry.set_tooltip_text(Some("HTTP, HTTPS or SOCKS proxy URL; empty connects directly"));
+
+    let (rate_box, rate_limit_entry) = components::create_labeled_entry("Speed limit:", "Unlimited, e.g. 2M or 500K",
+        options.rate_limit.as_deref());
+
+    let (retries_row, retries_spin) = create_spin_row("Retries:", 100.0, options.retries);
+    let (fragment_row, fragment_retries_spin) = create_spin_row("Fragment retries:", 100.0, options.fragment_retries);
+    let (timeout_row, timeout_spin) = create_spin_row("Socket timeout (seconds, 0 = default):", 600.0,
+        options.socket_timeout.unwrap_or(0));
+
+    let (ip_box, ip_combo) = components::create_dropdown("Connect over:");
+    for version in IpVersion::ALL {
+        ip_combo.append(Some(version.id()), version.label());
+    }
+    ip_combo.set_active_id(Some(options.ip_version.id()));
+    ip_combo.set_sensitive(true);
+
+    let (source_box, source_entry) = components::create_labeled_entry("Source address:", "Any local address",
+        Some(&options.source_address));
+
+    container.append(&proxy_box);
+    container.append(&rate_box);
+    container.append(&retries_row);
+    container.append(&fragment_row);
+    container.append(&timeout_row);
+    container.append(&ip_box);
+    container.append(&source_box);
+
+    NetworkSection {
+        container,
+        proxy_entry,
+        rate_limit_entry,
+        retries_spin,
+        fragment_retries_spin,
+        timeout_spin,
+        ip_combo,
+        source_entry,
+    }
+}
@@ -0,0 +1,154 @@
+use gtk4::{
+    prelude::*,
+    gio, glib, Application, ApplicationWindow
+};
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::downloader::QueueJob;
+use super::Settings;
+
+// Desktop notifications for downloads that end while the window is in the background
+#[derive(Clone)]
+pub struct Notifier {
+    app: Application,
+    window: ApplicationWindow,
+    settings: Rc<RefCell<Settings>>,
+}
+
+impl Notifier {
+    pub fn new(app: &Application, window: &ApplicationWindow, settings: Rc<RefCell<Settings>>) -> Self {
+        Notifier { app: app.clone(), window: window.clone(), settings }
+    }
+
+    // The window shows progress itself, so only notify when it isn't focused
+    fn enabled(&self) -> bool {
+        self.settings.borrow().notify_downloads && !self.window.is_active()
+    }
+
+    fn send(&self, id: &str, notification: &gio::Notification) {
+        // Clicking the notification brings the window back instead of activating the app again
+        notification.set_default_action("app.show-window");
+        self.app.send_notification(Some(id), notification);
+    }
+
+    // id identifies the download, a later notification for it replaces this one
+    pub fn download_finished(&self, id: &str, job: &QueueJob, output_file: Option<&Path>) {
+        if !self.enabled() {
+            return;
+        }
+
+        let notification = gio::Notification::new("Download complete");
+        notification.set_body(Some(&job_name(job, output_file)));
+
+        if let Some(file) = output_file {
+            notification.add_button_with_target_value("Open file", "app.open-file", Some(&file.to_string_lossy().to_variant()));
+        }
+        let folder = output_file
+            .and_then(|file| file.parent())
+            .map(|folder| folder.to_string_lossy().to_string())
+            .unwrap_or_else(|| job.output_path.clone());
+        notification.add_button_with_target_value("Open folder", "app.open-folder", Some(&folder.to_variant()));
+
+        self.send(id, &notification);
+    }
+
+    // Retry restarts the queue entry, or queues the job again if it didn't run in the queue
+    pub fn download_failed(&self, id: &str, job: &QueueJob, reason: &str, queue_id: Option<u64>) {
+        if !self.enabled() {
+            return;
+        }
+
+        let notification = gio::Notification::new("Download failed");
+        notification.set_body(Some(&format!("{}\n{}", job_name(job, None), reason)));
+        notification.set_priority(gio::NotificationPriority::High);
+
+        match queue_id {
+            Some(queue_id) => notification.add_button_with_target_value("Retry", "app.retry-download", Some(&queue_id.to_variant())),
+            None => match serde_json::to_string(job) {
+                Ok(json) => notification.add_button_with_target_value("Retry", "app.enqueue-job", Some(&json.to_variant())),
+                Err(e) => eprintln!("Failed to serialize job for retry: {}", e),
+            },
+        }
+
+        self.send(id, &notification);
+    }
+
+    // Summary once every queued job has ended
+    pub fn queue_drained(&self, completed: usize, failed: usize) {
+        if !self.enabled() {
+            return;
+        }
+
+        let notification = gio::Notification::new("Download queue finished");
+        let body = match failed {
+            0 => format!("{} downloads complete", completed),
+            _ => format!("{} downloads complete, {} failed", completed, failed),
+        };
+        notification.set_body(Some(&body));
+
+        self.send("queue-drained", &notification);
+    }
+}
+
+// Media title if known, otherwise the file name or the queue label
+fn job_name(job: &QueueJob, output_file: Option<&Path>) -> String {
+    job.title.clone()
+        .or_else(|| output_file
+            .and_then(|file| file.file_name())
+            .map(|name| name.to_string_lossy().to_string()))
+        .unwrap_or_else(|| job.label.clone())
+}
+
+// Use xdg-open to open a file or folder (Linux)
+fn open_path(path: &str) {
+    if let Err(e) = std::process::Command::new("xdg-open").arg(path).spawn() {
+        eprintln!("Failed to open {}: {}", path, e);
+    }
+}
+
+fn add_string_action<F>(app: &Application, name: &str, handler: F)
+where
+    F: Fn(String) + 'static,
+{
+    let action = gio::SimpleAction::new(name, Some(glib::VariantTy::STRING));
+    action.connect_activate(move |_, parameter| {
+        if let Some(value) = parameter.and_then(|parameter| parameter.get::<String>()) {
+            handler(value);
+        }
+    });
+    app.add_action(&action);
+}
+
+// Register the actions the notification buttons trigger
+pub fn install_actions<F, G>(app: &Application, window: &ApplicationWindow, on_retry: F, on_enqueue: G)
+where
+    F: Fn(u64) + 'static,
+    G: Fn(QueueJob) + 'static,
+{
+    let show_window = gio::SimpleAction::new("show-window", None);
+    let window_clone = window.clone();
+    show_window.connect_activate(move |_, _| {
+        window_clone.present();
+    });
+    app.add_action(&show_window);
+
+    add_string_action(app, "open-file", |path| open_path(&path));
+    add_string_action(app, "open-folder", |path| open_path(&path));
+
+    let retry = gio::SimpleAction::new("retry-download", Some(glib::VariantTy::UINT64));
+    retry.connect_activate(move |_, parameter| {
+        if let Some(id) = parameter.and_then(|parameter| parameter.get::<u64>()) {
+            on_retry(id);
+        }
+    });
+    app.add_action(&retry);
+
+    add_string_action(app, "enqueue-job", move |json| {
+        match serde_json::from_str::<QueueJob>(&json) {
+            Ok(job) => on_enqueue(job),
+            Err(e) => eprintln!("Invalid job in notification action: {}", e),
+        }
+    });
+}
@@ -0,0 +1,249 @@
+use gtk4::{
+    prelude::*,
+    Window, Box as GtkBox, Orientation, Align, Label, Entry, CheckButton,
+    ScrolledWindow, SpinButton
+};
+use std::rc::Rc;
+
+use crate::ui::components;
+use super::utils::format_duration;
+use crate::downloader::{Playlist, PlaylistEntry, PlaylistFilter, QualityPreset,
+                        parse_index_ranges, parse_filter_date};
+
+// Show the entries of a playlist or channel and let the user enqueue a selection of them
+pub fn show_playlist_dialog<F>(parent: &impl IsA<Window>, playlist: Playlist, on_enqueue: F)
+where
+    F: Fn(Vec<PlaylistEntry>, QualityPreset) + 'static,
+{
+    let dialog = Window::builder()
+        .title("Playlist")
+        .transient_for(parent)
+        .modal(true)
+        .default_width(560)
+        .default_height(520)
+        .build();
+
+    let container = GtkBox::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(8)
+        .margin_top(12)
+        .margin_bottom(12)
+        .margin_start(12)
+        .margin_end(12)
+        .build();
+
+    container.add_css_class("content-box");
+
+    let subtitle = if playlist.uploader.is_empty() {
+        format!("{} entries", playlist.entries.len())
+    } else {
+        format!("{} · {} entries", playlist.uploader, playlist.entries.len())
+    };
+    let header = components::create_title_with_subtitle(&playlist.title, &subtitle);
+    container.append(&header);
+
+    // Range selection: first N, index ranges and upload date window
+    let range_card = GtkBox::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(6)
+        .build();
+
+    range_card.add_css_class("options-box");
+
+    let range_row = GtkBox::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(8)
+        .build();
+
+    let first_label = Label::builder()
+        .label("First:")
+        .valign(Align::Center)
+        .build();
+    first_label.add_css_class("input-label");
+
+    // 0 means no limit
+    let first_spin = SpinButton::with_range(0.0, playlist.entries.len().max(1) as f64, 1.0);
+    first_spin.set_valign(Align::Center);
+    first_spin.set_tooltip_text(Some("Only the first N entries (0 = all)"));
+
+    let (ranges_box, ranges_entry) = components::create_labeled_entry("Items:", "e.g. 1-5,8,10-12", None);
+    ranges_box.set_hexpand(true);
+
+    range_row.append(&first_label);
+    range_row.append(&first_spin);
+    range_row.append(&ranges_box);
+
+    let date_row = GtkBox::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(8)
+        .build();
+
+    let (after_box, after_entry) = components::create_labeled_entry("Uploaded after:", "YYYYMMDD", None);
+    let (before_box, before_entry) = components::create_labeled_entry("Uploaded before:", "YYYYMMDD", None);
+    after_box.set_hexpand(true);
+    before_box.set_hexpand(true);
+
+    date_row.append(&after_box);
+    date_row.append(&before_box);
+
+    let selection_row = GtkBox::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(6)
+        .build();
+
+    let apply_button = components::create_button("Apply Range", "browse-button");
+    let all_button = components::create_button("Select All", "browse-button");
+    let none_button = components::create_button("Select None", "browse-button");
+
+    selection_row.append(&apply_button);
+    selection_row.append(&all_button);
+    selection_row.append(&none_button);
+
+    range_card.append(&range_row);
+    range_card.append(&date_row);
+    range_card.append(&selection_row);
+    container.append(&range_card);
+
+    // Entry list with a checkbox per item
+    let entry_list = GtkBox::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(2)
+        .build();
+
+    let mut checks = Vec::new();
+    for entry in &playlist.entries {
+        let mut label = format!("{}. {}", entry.index, entry.title);
+        if let Some(duration) = entry.duration {
+            label.push_str(&format!(" ({})", format_duration(duration)));
+        }
+
+        let check = CheckButton::builder()
+            .label(&label)
+            .active(true)
+            .build();
+
+        entry_list.append(&check);
+        checks.push(check);
+    }
+
+    let scroller = ScrolledWindow::builder()
+        .hscrollbar_policy(gtk4::PolicyType::Never)
+        .vexpand(true)
+        .child(&entry_list)
+        .build();
+
+    container.append(&scroller);
+
+    // Quality preset applied to every selected entry
+    let (preset_box, preset_combo) = components::create_dropdown("Quality preset:");
+    for (i, preset) in QualityPreset::ALL.iter().enumerate() {
+        preset_combo.append(Some(&i.to_string()), &preset.label());
+    }
+    preset_combo.set_active(Some(0));
+    preset_combo.set_sensitive(true);
+    container.append(&preset_box);
+
+    let status_label = components::create_status_label("");
+    container.append(&status_label);
+
+    let button_row = GtkBox::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(8)
+        .halign(Align::End)
+        .build();
+
+    let cancel_button = components::create_button("Cancel", "browse-button");
+    let enqueue_button = components::create_button("Add to Queue", "fetch-button");
+
+    button_row.append(&cancel_button);
+    button_row.append(&enqueue_button);
+    container.append(&button_row);
+
+    dialog.set_child(Some(&container));
+
+    let playlist = Rc::new(playlist);
+    let checks = Rc::new(checks);
+
+    let update_count = {
+        let checks = Rc::clone(&checks);
+        let status_label = status_label.clone();
+        Rc::new(move || {
+            let selected = checks.iter().filter(|c| c.is_active()).count();
+            status_label.set_text(&format!("{} of {} entries selected", selected, checks.len()));
+        })
+    };
+
+    for check in checks.iter() {
+        let update_count = Rc::clone(&update_count);
+        check.connect_toggled(move |_| update_count());
+    }
+    update_count();
+
+    let checks_clone = Rc::clone(&checks);
+    all_button.connect_clicked(move |_| {
+        checks_clone.iter().for_each(|c| c.set_active(true));
+    });
+
+    let checks_clone = Rc::clone(&checks);
+    none_button.connect_clicked(move |_| {
+        checks_clone.iter().for_each(|c| c.set_active(false));
+    });
+
+    // Select exactly the entries matching the range fields
+    let checks_clone = Rc::clone(&checks);
+    let playlist_clone = Rc::clone(&playlist);
+    let dialog_clone = dialog.clone();
+    apply_button.connect_clicked(move |_| {
+        let filter = match build_filter(&first_spin, &ranges_entry, &after_entry, &before_entry) {
+            Ok(filter) => filter,
+            Err(e) => {
+                components::show_error_dialog(&dialog_clone, "Invalid Range", &e.to_string());
+                return;
+            }
+        };
+
+        for (check, entry) in checks_clone.iter().zip(playlist_clone.entries.iter()) {
+            check.set_active(filter.matches(entry));
+        }
+    });
+
+    let dialog_clone = dialog.clone();
+    cancel_button.connect_clicked(move |_| {
+        dialog_clone.close();
+    });
+
+    let dialog_clone = dialog.clone();
+    enqueue_button.connect_clicked(move |_| {
+        let selected: Vec<PlaylistEntry> = checks.iter()
+            .zip(playlist.entries.iter())
+            .filter(|(check, _)| check.is_active())
+            .map(|(_, entry)| entry.clone())
+            .collect();
+
+        if selected.is_empty() {
+            components::show_error_dialog(&dialog_clone, "Nothing Selected", "Select at least one entry to download.");
+            return;
+        }
+
+        let preset = preset_combo.active_id()
+            .and_then(|id| id.parse::<usize>().ok())
+            .and_then(|i| QualityPreset::ALL.get(i).copied())
+            .unwrap_or(QualityPreset::BestVideo);
+
+        on_enqueue(selected, preset);
+        dialog_clone.close();
+    });
+
+    dialog.present();
+}
+
+fn build_filter(first_spin: &SpinButton, ranges_entry: &Entry, after_entry: &Entry, before_entry: &Entry) -> anyhow::Result<PlaylistFilter> {
+    let first = first_spin.value() as usize;
+
+    Ok(PlaylistFilter {
+        first: if first > 0 { Some(first) } else { None },
+        ranges: parse_index_ranges(&ranges_entry.text())?,
+        date_after: parse_filter_date(&after_entry.text())?,
+        date_before: parse_filter_date(&before_entry.text())?,
+    })
+}
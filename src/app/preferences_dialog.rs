@@ -0,0 +1,410 @@
+use gtk4::{
+    prelude::*,
+    Window, Box as GtkBox, Orientation, Align, Label, Entry, ComboBoxText, SpinButton, CheckButton, Notebook
+};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::ui::components;
+use crate::app::Settings;
+use super::clipboard_watcher::ClipboardAction;
+use super::{auth_section, network_section, ytdlp_setup};
+use crate::downloader::{VIDEO_CODECS, DEFAULT_TEMPLATE, OutputTemplate, BackendKind, refresh_ffmpeg_tools,
+                        parse_backend_rules, format_backend_rules};
+
+const HEIGHT_CHOICES: [u32; 7] = [0, 2160, 1440, 1080, 720, 480, 360];
+const FPS_CHOICES: [u32; 3] = [0, 60, 30];
+const CONTAINER_CHOICES: [&str; 3] = ["mp4", "mkv", "webm"];
+const AUDIO_FORMAT_CHOICES: [&str; 6] = ["mp3", "m4a", "opus", "aac", "flac", "wav"];
+const ABR_CHOICES: [u32; 6] = [0, 320, 256, 192, 160, 128];
+
+// Dropdown whose ids are the values; 0 stands for "no preference"
+fn create_number_dropdown(label: &str, values: &[u32], any_label: &str, format: fn(u32) -> String, selected: Option<u32>) -> (GtkBox, ComboBoxText) {
+    let (container, combo) = components::create_dropdown(label);
+    for value in values {
+        let text = if *value == 0 { any_label.to_string() } else { format(*value) };
+        combo.append(Some(&value.to_string()), &text);
+    }
+    combo.set_active_id(Some(&selected.unwrap_or(0).to_string()));
+    combo.set_sensitive(true);
+    (container, combo)
+}
+
+fn create_text_dropdown(label: &str, values: &[&str], selected: &str) -> (GtkBox, ComboBoxText) {
+    let (container, combo) = components::create_dropdown(label);
+    for value in values {
+        combo.append(Some(value), &value.to_uppercase());
+    }
+    if !combo.set_active_id(Some(selected)) {
+        combo.set_active(Some(0));
+    }
+    combo.set_sensitive(true);
+    (container, combo)
+}
+
+fn combo_number(combo: &ComboBoxText) -> Option<u32> {
+    combo.active_id()
+        .and_then(|id| id.parse::<u32>().ok())
+        .filter(|value| *value > 0)
+}
+
+// "vp9, av1" -> ["VP9", "AV1"], keeping the spelling of known codecs; empty means the default order
+fn parse_codec_order(text: &str) -> Vec<String> {
+    let order: Vec<String> = text.split(',')
+        .map(|codec| codec.trim())
+        .filter(|codec| !codec.is_empty())
+        .map(|codec| VIDEO_CODECS.iter()
+            .find(|known| known.eq_ignore_ascii_case(codec))
+            .map(|known| known.to_string())
+            .unwrap_or_else(|| codec.to_uppercase()))
+        .collect();
+
+    if order.is_empty() {
+        VIDEO_CODECS.iter().map(|codec| codec.to_string()).collect()
+    } else {
+        order
+    }
+}
+
+// Entry with a Browse button that opens a folder chooser
+fn create_folder_row(parent: &Window, label: &str, value: &str) -> (GtkBox, Entry) {
+    let (container, entry) = components::create_labeled_entry(label, "Folder path", Some(value));
+    entry.set_hexpand(true);
+
+    let row = GtkBox::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(6)
+        .build();
+
+    let browse_button = components::create_button("Browse", "browse-button");
+    browse_button.set_valign(Align::End);
+
+    container.set_hexpand(true);
+    row.append(&container);
+    row.append(&browse_button);
+
+    let parent = parent.clone();
+    let entry_clone = entry.clone();
+    browse_button.connect_clicked(move |_| {
+        let dialog = components::create_folder_chooser_dialog(&parent, "Select Folder");
+
+        let entry_clone = entry_clone.clone();
+        dialog.connect_response(move |dialog, response| {
+            if response == gtk4::ResponseType::Accept {
+                if let Some(path) = dialog.file().and_then(|file| file.path()) {
+                    entry_clone.set_text(&path.to_string_lossy());
+                }
+            }
+            dialog.destroy();
+        });
+
+        dialog.present();
+    });
+
+    (row, entry)
+}
+
+// Edit the persistent settings; on_saved is called after they have been written
+pub fn show_preferences_dialog<F>(parent: &impl IsA<Window>, settings: Rc<RefCell<Settings>>, on_saved: F)
+where
+    F: Fn(&Settings) + 'static,
+{
+    let current = settings.borrow().clone();
+
+    let dialog = Window::builder()
+        .title("Preferences")
+        .transient_for(parent)
+        .modal(true)
+        .default_width(480)
+        .build();
+
+    let container = GtkBox::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(10)
+        .margin_top(12)
+        .margin_bottom(12)
+        .margin_start(12)
+        .margin_end(12)
+        .build();
+
+    container.add_css_class("content-box");
+
+    // General, Authentication and Network pages above the buttons
+    let general_page = GtkBox::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(10)
+        .margin_top(8)
+        .build();
+
+    // Output folders
+    let folders_box = GtkBox::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(6)
+        .build();
+
+    folders_box.add_css_class("options-box");
+    folders_box.append(&components::create_section_title("Output Folders"));
+
+    let (video_row, video_entry) = create_folder_row(&dialog, "Video folder:", &current.video_output_dir);
+    let (audio_row, audio_entry) = create_folder_row(&dialog, "Audio folder:", &current.audio_output_dir);
+    folders_box.append(&video_row);
+    folders_box.append(&audio_row);
+    general_page.append(&folders_box);
+
+    // Preferred quality, applied after every fetch
+    let quality_box = GtkBox::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(6)
+        .build();
+
+    quality_box.add_css_class("options-box");
+    quality_box.append(&components::create_section_title("Preferred Quality"));
+
+    let video_row = GtkBox::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(8)
+        .build();
+
+    let (height_box, height_combo) = create_number_dropdown("Resolution:", &HEIGHT_CHOICES, "Best",
+        |h| format!("{}p", h), current.preferred_height);
+    let (fps_box, fps_combo) = create_number_dropdown("FPS:", &FPS_CHOICES, "Highest",
+        |fps| format!("{} fps", fps), current.preferred_fps);
+    let (container_box, container_combo) = create_text_dropdown("Container:", &CONTAINER_CHOICES, &current.preferred_container);
+
+    video_row.append(&height_box);
+    video_row.append(&fps_box);
+    video_row.append(&container_box);
+
+    let (codec_box, codec_entry) = components::create_labeled_entry("Codec order:", &VIDEO_CODECS.join(", "),
+        Some(&current.codec_preference.join(", ")));
+    codec_entry.set_tooltip_text(Some("Comma separated; decides which stream wins when several match"));
+
+    let audio_row = GtkBox::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(8)
+        .build();
+
+    let (audio_format_box, audio_format_combo) = create_text_dropdown("Audio format:", &AUDIO_FORMAT_CHOICES, &current.audio_format);
+    let (abr_box, abr_combo) = create_number_dropdown("Audio bitrate:", &ABR_CHOICES, "Best",
+        |abr| format!("{} kbps", abr), current.preferred_abr);
+
+    audio_row.append(&audio_format_box);
+    audio_row.append(&abr_box);
+
+    quality_box.append(&video_row);
+    quality_box.append(&codec_box);
+    quality_box.append(&audio_row);
+    general_page.append(&quality_box);
+
+    // Downloads and backend
+    let advanced_box = GtkBox::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(6)
+        .build();
+
+    advanced_box.add_css_class("options-box");
+    advanced_box.append(&components::create_section_title("Downloads"));
+
+    let parallel_row = GtkBox::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(8)
+        .build();
+
+    let parallel_label = Label::builder()
+        .label("Parallel downloads:")
+        .halign(Align::Start)
+        .hexpand(true)
+        .build();
+
+    parallel_label.add_css_class("input-label");
+
+    let parallel_spin = SpinButton::with_range(1.0, 8.0, 1.0);
+    parallel_spin.set_value(current.max_concurrent as f64);
+
+    parallel_row.append(&parallel_label);
+    parallel_row.append(&parallel_spin);
+
+    let (backend_box, backend_combo) = components::create_dropdown("Downloader:");
+    for kind in BackendKind::ALL {
+        backend_combo.append(Some(kind.id()), kind.label());
+    }
+    backend_combo.set_active_id(Some(current.backend.id()));
+    backend_combo.set_sensitive(true);
+
+    let (rules_box, rules_entry) = components::create_labeled_entry("Downloader rules:", "*.example.com/files/* = http",
+        Some(&format_backend_rules(&current.backend_rules)));
+    rules_entry.set_tooltip_text(Some("PATTERN = DOWNLOADER, separated by ';'. '*' matches anything, \
+        a pattern without '*' matches anywhere in the URL. The first matching rule wins."));
+
+    let direct_check = CheckButton::with_label("Download direct file links without yt-dlp");
+    direct_check.set_active(current.detect_direct_links);
+    direct_check.set_tooltip_text(Some("Links to a plain file like .mp4 or .zip are recognized by their content type"));
+
+    let connections_row = GtkBox::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(8)
+        .build();
+
+    let connections_label = Label::builder()
+        .label("Connections per file:")
+        .halign(Align::Start)
+        .hexpand(true)
+        .build();
+
+    connections_label.add_css_class("input-label");
+
+    let connections_spin = SpinButton::with_range(1.0, 16.0, 1.0);
+    connections_spin.set_value(current.http_connections as f64);
+
+    connections_row.append(&connections_label);
+    connections_row.append(&connections_spin);
+
+    let (ytdlp_box, ytdlp_entry) = components::create_labeled_entry("yt-dlp executable:", "yt-dlp", Some(&current.ytdlp_path));
+
+    // yt-dlp output templates, see the token picker next to the download folder
+    let (video_template_box, video_template_entry) = components::create_labeled_entry("Video file name:", DEFAULT_TEMPLATE, Some(&current.video_template));
+    let (audio_template_box, audio_template_entry) = components::create_labeled_entry("Audio file name:", DEFAULT_TEMPLATE, Some(&current.audio_template));
+
+    let restrict_check = CheckButton::with_label("Restrict file names (ASCII, no spaces)");
+    restrict_check.set_active(current.restrict_filenames);
+
+    let notify_check = CheckButton::with_label("Notify when downloads end in the background");
+    notify_check.set_active(current.notify_downloads);
+
+    advanced_box.append(&parallel_row);
+    advanced_box.append(&notify_check);
+    advanced_box.append(&backend_box);
+    advanced_box.append(&rules_box);
+    advanced_box.append(&direct_check);
+    advanced_box.append(&connections_row);
+    advanced_box.append(&ytdlp_box);
+    advanced_box.append(&ytdlp_setup::create_ytdlp_row(&dialog, &ytdlp_entry));
+    advanced_box.append(&components::create_status_label(&refresh_ffmpeg_tools().description()));
+    advanced_box.append(&video_template_box);
+    advanced_box.append(&audio_template_box);
+    advanced_box.append(&restrict_check);
+    general_page.append(&advanced_box);
+
+    // Clipboard watcher
+    let clipboard_box = GtkBox::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(6)
+        .build();
+
+    clipboard_box.add_css_class("options-box");
+    clipboard_box.append(&components::create_section_title("Clipboard"));
+
+    let watch_check = CheckButton::with_label("Watch the clipboard for media links");
+    watch_check.set_active(current.watch_clipboard);
+
+    let (action_box, action_combo) = components::create_dropdown("On copied link:");
+    for action in ClipboardAction::ALL {
+        action_combo.append(Some(action.id()), action.label());
+    }
+    action_combo.set_active_id(Some(current.clipboard_action.id()));
+    action_combo.set_sensitive(current.watch_clipboard);
+
+    let action_combo_clone = action_combo.clone();
+    watch_check.connect_toggled(move |check| {
+        action_combo_clone.set_sensitive(check.is_active());
+    });
+
+    clipboard_box.append(&watch_check);
+    clipboard_box.append(&action_box);
+    general_page.append(&clipboard_box);
+
+    let auth_section = auth_section::create_auth_section(&dialog, &current);
+    auth_section.container.set_margin_top(8);
+    auth_section.container.set_valign(Align::Start);
+
+    let network_section = network_section::create_network_section(&current.network);
+    network_section.container.set_margin_top(8);
+    network_section.container.set_valign(Align::Start);
+
+    let notebook = Notebook::new();
+    notebook.append_page(&general_page, Some(&Label::new(Some("General"))));
+    notebook.append_page(&auth_section.container, Some(&Label::new(Some("Authentication"))));
+    notebook.append_page(&network_section.container, Some(&Label::new(Some("Network"))));
+    container.append(&notebook);
+
+    let button_row = GtkBox::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(8)
+        .halign(Align::End)
+        .build();
+
+    let cancel_button = components::create_button("Cancel", "browse-button");
+    let save_button = components::create_button("Save", "fetch-button");
+
+    button_row.append(&cancel_button);
+    button_row.append(&save_button);
+    container.append(&button_row);
+
+    dialog.set_child(Some(&container));
+
+    let dialog_clone = dialog.clone();
+    cancel_button.connect_clicked(move |_| {
+        dialog_clone.close();
+    });
+
+    let dialog_clone = dialog.clone();
+    save_button.connect_clicked(move |_| {
+        let network = match network_section.options() {
+            Ok(network) => network,
+            Err(e) => {
+                components::show_error_dialog(&dialog_clone, "Preferences Error", &e);
+                return;
+            }
+        };
+
+        let backend_rules = match parse_backend_rules(&rules_entry.text()) {
+            Ok(rules) => rules,
+            Err(e) => {
+                components::show_error_dialog(&dialog_clone, "Preferences Error", &e);
+                return;
+            }
+        };
+
+        let updated = {
+            let mut settings = settings.borrow_mut();
+            settings.video_output_dir = video_entry.text().trim().to_string();
+            settings.audio_output_dir = audio_entry.text().trim().to_string();
+            settings.preferred_height = combo_number(&height_combo);
+            settings.preferred_fps = combo_number(&fps_combo);
+            settings.codec_preference = parse_codec_order(&codec_entry.text());
+            settings.preferred_container = container_combo.active_id().map(|id| id.to_string()).unwrap_or_else(|| "mp4".to_string());
+            settings.audio_format = audio_format_combo.active_id().map(|id| id.to_string()).unwrap_or_else(|| "mp3".to_string());
+            settings.preferred_abr = combo_number(&abr_combo);
+            settings.max_concurrent = parallel_spin.value() as usize;
+            settings.backend = backend_combo.active_id()
+                .and_then(|id| BackendKind::from_id(&id))
+                .unwrap_or_default();
+            settings.backend_rules = backend_rules;
+            settings.detect_direct_links = direct_check.is_active();
+            settings.http_connections = connections_spin.value() as usize;
+            settings.ytdlp_path = ytdlp_entry.text().trim().to_string();
+            settings.video_template = OutputTemplate::new(&video_template_entry.text(), false).template;
+            settings.audio_template = OutputTemplate::new(&audio_template_entry.text(), false).template;
+            settings.restrict_filenames = restrict_check.is_active();
+            settings.notify_downloads = notify_check.is_active();
+            auth_section.apply(&mut settings);
+            settings.network = network;
+            settings.watch_clipboard = watch_check.is_active();
+            settings.clipboard_action = action_combo.active_id()
+                .and_then(|id| ClipboardAction::from_id(&id))
+                .unwrap_or_default();
+            settings.clone()
+        };
+
+        if let Err(e) = updated.save() {
+            components::show_error_dialog(&dialog_clone, "Preferences Error",
+                &format!("Failed to save preferences: {}", e));
+            return;
+        }
+
+        on_saved(&updated);
+        dialog_clone.close();
+    });
+
+    dialog.present();
+}
@@ -0,0 +1,147 @@
+use anyhow::{Result, Context};
+use serde::{Serialize, Deserialize};
+use std::fs;
+use std::path::PathBuf;
+
+use super::get_default_download_path;
+use super::clipboard_watcher::ClipboardAction;
+use crate::downloader::{SubtitleMode, OutputTemplate, CookieSource, SiteLogin, NetworkOptions, BackendKind, BackendRule, BackendConfig,
+                        DEFAULT_TEMPLATE, VIDEO_CODECS};
+
+// User preferences persisted as JSON in the XDG config dir
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub video_output_dir: String,
+    pub audio_output_dir: String,
+    pub preferred_height: Option<u32>,   // None = best available
+    pub preferred_fps: Option<u32>,      // None = highest available
+    pub preferred_container: String,     // Output container for video (mp4, mkv, webm)
+    pub codec_preference: Vec<String>,   // Video codecs, first wins when several streams match
+    pub audio_format: String,            // Target audio format (mp3, m4a, opus, ...)
+    pub subtitle_languages: Vec<String>, // Checked after every fetch when available, e.g. ["en", "de"]
+    pub subtitle_mode: SubtitleMode,
+    pub video_template: String,          // yt-dlp output templates per media type
+    pub audio_template: String,
+    pub restrict_filenames: bool,        // ASCII-only file names without spaces
+    pub preferred_abr: Option<u32>,      // Audio bitrate in kbps, None = best available
+    pub write_audio_tags: bool,          // Title/artist/album/date tags in extracted audio
+    pub embed_cover: bool,               // Thumbnail as cover art in extracted audio
+    pub notify_downloads: bool,          // Desktop notifications while the window is in the background
+    pub last_tab: String,                // "video" or "audio"
+    pub watch_clipboard: bool,           // Look for supported links in the clipboard
+    pub clipboard_action: ClipboardAction,
+    pub max_concurrent: usize,           // Parallel downloads in the queue
+    pub ytdlp_path: String,              // yt-dlp executable, looked up in PATH if not absolute
+    pub cookies: CookieSource,           // Cookies passed to every fetch and download
+    pub use_netrc: bool,                 // Logins from ~/.netrc
+    pub site_logins: Vec<SiteLogin>,     // Passwords are kept in the Secret Service
+    pub network: NetworkOptions,         // Proxy, speed limit and retries for every yt-dlp call
+    pub backend: BackendKind,            // Downloader for URLs no rule matches
+    pub backend_rules: Vec<BackendRule>, // URL patterns sent to a specific downloader, first match wins
+    pub detect_direct_links: bool,       // Download plain file links without yt-dlp
+    pub http_connections: usize,         // Parallel connections per direct file
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        let base_dir = get_default_download_path().unwrap_or_else(|_| String::from("."));
+
+        Settings {
+            video_output_dir: format!("{}/video", base_dir),
+            audio_output_dir: format!("{}/audio", base_dir),
+            preferred_height: None,
+            preferred_fps: None,
+            preferred_container: "mp4".to_string(),
+            codec_preference: VIDEO_CODECS.iter().map(|codec| codec.to_string()).collect(),
+            audio_format: "mp3".to_string(),
+            subtitle_languages: Vec::new(),
+            subtitle_mode: SubtitleMode::default(),
+            video_template: DEFAULT_TEMPLATE.to_string(),
+            audio_template: DEFAULT_TEMPLATE.to_string(),
+            restrict_filenames: false,
+            preferred_abr: None,
+            write_audio_tags: true,
+            embed_cover: true,
+            notify_downloads: true,
+            last_tab: "video".to_string(),
+            watch_clipboard: false,
+            clipboard_action: ClipboardAction::default(),
+            max_concurrent: 2,
+            ytdlp_path: "yt-dlp".to_string(),
+            cookies: CookieSource::default(),
+            use_netrc: false,
+            site_logins: Vec::new(),
+            network: NetworkOptions::default(),
+            backend: BackendKind::default(),
+            backend_rules: Vec::new(),
+            detect_direct_links: true,
+            http_connections: 4,
+        }
+    }
+}
+
+impl Settings {
+    pub fn config_path() -> Result<PathBuf> {
+        let config_dir = dirs::config_dir()
+            .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
+
+        Ok(config_dir.join("HyprDownloader").join("settings.json"))
+    }
+
+    // Load settings, falling back to defaults if the file is missing or invalid
+    pub fn load() -> Settings {
+        match Self::try_load() {
+            Ok(settings) => settings,
+            Err(e) => {
+                eprintln!("Using default settings: {:#}", e);
+                Settings::default()
+            }
+        }
+    }
+
+    fn try_load() -> Result<Settings> {
+        let path = Self::config_path()?;
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::config_path()?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(&path, contents)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    // Output folder for the given media tab
+    pub fn output_dir_for(&self, tab: &str) -> &str {
+        if tab == "audio" {
+            &self.audio_output_dir
+        } else {
+            &self.video_output_dir
+        }
+    }
+
+    pub fn backend_config(&self) -> BackendConfig {
+        BackendConfig {
+            default: self.backend,
+            rules: self.backend_rules.clone(),
+            detect_direct_links: self.detect_direct_links,
+            http_connections: self.http_connections,
+        }
+    }
+
+    // Output template for the "video" or "audio" tab
+    pub fn template_for(&self, tab: &str) -> OutputTemplate {
+        let template = if tab == "audio" { &self.audio_template } else { &self.video_template };
+        OutputTemplate::new(template, self.restrict_filenames)
+    }
+}
@@ -0,0 +1,191 @@
+use gtk4::{
+    prelude::*,
+    Box as GtkBox, Orientation, Label, CheckButton, ComboBoxText, ScrolledWindow
+};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::ui::components;
+use crate::downloader::{SubtitleTrack, SubtitleMode, SubtitleOptions};
+use super::Settings;
+
+// Subtitles section of the video options: one check button per language
+#[derive(Clone)]
+pub struct SubtitlePanel {
+    pub container: GtkBox,
+    list: GtkBox,
+    empty_label: Label,
+    show_automatic: CheckButton,
+    mode_combo: ComboBoxText,
+    tracks: Rc<RefCell<Vec<(SubtitleTrack, CheckButton)>>>,
+}
+
+impl SubtitlePanel {
+    // Show the languages of a fetched video, checking the preferred ones
+    pub fn set_tracks(&self, tracks: Vec<SubtitleTrack>, preferred: &[String]) {
+        self.clear();
+
+        let matches = |track: &SubtitleTrack, language: &str| {
+            track.language == language || track.language.starts_with(&format!("{}-", language))
+        };
+
+        // Auto-generated captions only stand in for languages without uploaded subtitles
+        let is_checked = |track: &SubtitleTrack| preferred.iter().any(|language| {
+            matches(track, language) && (!track.automatic
+                || !tracks.iter().any(|other| !other.automatic && matches(other, language)))
+        });
+
+        let mut rows = self.tracks.borrow_mut();
+        for track in tracks.iter() {
+            let check = CheckButton::with_label(&track.label());
+            check.set_active(is_checked(track));
+            if !track.formats.is_empty() {
+                check.set_tooltip_text(Some(&format!("Available as {}", track.formats.join(", "))));
+            }
+
+            self.list.append(&check);
+            rows.push((track.clone(), check));
+        }
+        drop(rows);
+
+        self.empty_label.set_text(if tracks.is_empty() {
+            "No subtitles available"
+        } else {
+            "Select the languages to download"
+        });
+        self.update_visibility();
+    }
+
+    pub fn clear(&self) {
+        for (_, check) in self.tracks.borrow_mut().drain(..) {
+            self.list.remove(&check);
+        }
+        self.empty_label.set_text("Fetch a URL to list subtitles");
+    }
+
+    // Selected languages and mode, None if nothing is checked
+    pub fn options(&self) -> Option<SubtitleOptions> {
+        let tracks = self.tracks.borrow();
+        let selected: Vec<&SubtitleTrack> = tracks.iter()
+            .filter(|(_, check)| check.is_active())
+            .map(|(track, _)| track)
+            .collect();
+
+        if selected.is_empty() {
+            return None;
+        }
+
+        let mut languages: Vec<String> = Vec::new();
+        for track in &selected {
+            if !languages.contains(&track.language) {
+                languages.push(track.language.clone());
+            }
+        }
+
+        Some(SubtitleOptions {
+            languages,
+            automatic: selected.iter().any(|track| track.automatic),
+            mode: self.mode(),
+        })
+    }
+
+    // Store the checked languages and the mode for the next fetch
+    pub fn remember(&self, settings: &mut Settings) {
+        settings.subtitle_mode = self.mode();
+
+        // Keep the old list when the video had no subtitles to choose from
+        if !self.tracks.borrow().is_empty() {
+            settings.subtitle_languages = self.options()
+                .map(|options| options.languages)
+                .unwrap_or_default();
+        }
+    }
+
+    // Without ffmpeg subtitles are saved as the site offers them and can't be embedded
+    pub fn set_ffmpeg_available(&self, available: bool) {
+        let tooltip = if available { None } else { Some("Converting and embedding need ffmpeg, which was not found") };
+        self.mode_combo.set_tooltip_text(tooltip);
+    }
+
+    fn mode(&self) -> SubtitleMode {
+        self.mode_combo.active_id()
+            .and_then(|id| SubtitleMode::from_id(&id))
+            .unwrap_or_default()
+    }
+
+    // Auto-generated captions are hidden unless asked for or already checked
+    fn update_visibility(&self) {
+        let show_automatic = self.show_automatic.is_active();
+        for (track, check) in self.tracks.borrow().iter() {
+            check.set_visible(!track.automatic || show_automatic || check.is_active());
+        }
+    }
+}
+
+pub fn create_subtitle_panel(settings: &Settings) -> SubtitlePanel {
+    let container = GtkBox::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(6)
+        .margin_top(4)
+        .build();
+
+    let header = GtkBox::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(8)
+        .build();
+
+    let title = Label::builder()
+        .label("Subtitles")
+        .halign(gtk4::Align::Start)
+        .hexpand(true)
+        .build();
+
+    title.add_css_class("input-label");
+
+    let show_automatic = CheckButton::with_label("Show auto-generated");
+
+    header.append(&title);
+    header.append(&show_automatic);
+
+    let empty_label = components::create_status_label("Fetch a URL to list subtitles");
+
+    let list = GtkBox::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(2)
+        .build();
+
+    let scroller = ScrolledWindow::builder()
+        .hscrollbar_policy(gtk4::PolicyType::Never)
+        .max_content_height(120)
+        .propagate_natural_height(true)
+        .child(&list)
+        .build();
+
+    let (mode_box, mode_combo) = components::create_dropdown("Save as:");
+    for mode in SubtitleMode::ALL {
+        mode_combo.append(Some(mode.id()), mode.label());
+    }
+    mode_combo.set_active_id(Some(settings.subtitle_mode.id()));
+    mode_combo.set_sensitive(true);
+
+    container.append(&header);
+    container.append(&empty_label);
+    container.append(&scroller);
+    container.append(&mode_box);
+
+    let panel = SubtitlePanel {
+        container,
+        list,
+        empty_label,
+        show_automatic: show_automatic.clone(),
+        mode_combo,
+        tracks: Rc::new(RefCell::new(Vec::new())),
+    };
+
+    let panel_clone = panel.clone();
+    show_automatic.connect_toggled(move |_| {
+        panel_clone.update_visibility();
+    });
+
+    panel
+}
@@ -0,0 +1,148 @@
+use gtk4::{
+    prelude::*,
+    Box as GtkBox, Orientation, Label, CheckButton, Entry
+};
+
+use crate::ui::components;
+use crate::downloader::{AudioTags, MediaInfo};
+use super::Settings;
+
+// Tags section of the audio options, pre-filled from the fetched metadata
+#[derive(Clone)]
+pub struct TagEditor {
+    pub container: GtkBox,
+    write_tags: CheckButton,
+    embed_cover: CheckButton,
+    title_entry: Entry,
+    artist_entry: Entry,
+    album_entry: Entry,
+    date_entry: Entry,
+}
+
+impl TagEditor {
+    pub fn fill(&self, info: &MediaInfo) {
+        let tags = AudioTags::from_media_info(info);
+        self.title_entry.set_text(&tags.title);
+        self.artist_entry.set_text(&tags.artist);
+        self.album_entry.set_text(&tags.album);
+        self.date_entry.set_text(&tags.date);
+    }
+
+    pub fn clear(&self) {
+        for entry in [&self.title_entry, &self.artist_entry, &self.album_entry, &self.date_entry] {
+            entry.set_text("");
+        }
+    }
+
+    // Tags for the audio job, None if neither tags nor cover art are wanted
+    pub fn tags(&self) -> Option<AudioTags> {
+        let embed_metadata = self.write_tags.is_active() && self.write_tags.is_sensitive();
+        let embed_cover = self.embed_cover.is_active() && self.embed_cover.is_sensitive();
+        if !embed_metadata && !embed_cover {
+            return None;
+        }
+
+        let text = |entry: &Entry| entry.text().trim().to_string();
+        Some(AudioTags {
+            title: text(&self.title_entry),
+            artist: text(&self.artist_entry),
+            album: text(&self.album_entry),
+            date: text(&self.date_entry),
+            embed_metadata,
+            embed_cover,
+        })
+    }
+
+    // Tags and cover art are written by ffmpeg, without it both are skipped
+    pub fn set_ffmpeg_available(&self, available: bool) {
+        let tooltip = if available { None } else { Some("Needs ffmpeg, which was not found") };
+        for check in [&self.write_tags, &self.embed_cover] {
+            check.set_sensitive(available);
+            check.set_tooltip_text(tooltip);
+        }
+    }
+
+    pub fn remember(&self, settings: &mut Settings) {
+        settings.write_audio_tags = self.write_tags.is_active();
+        settings.embed_cover = self.embed_cover.is_active();
+    }
+}
+
+fn create_tag_row(label: &str, placeholder: &str) -> (GtkBox, Entry) {
+    let (container, entry) = components::create_labeled_entry(label, placeholder, None);
+    container.set_hexpand(true);
+    entry.set_hexpand(true);
+    (container, entry)
+}
+
+pub fn create_tag_editor(settings: &Settings) -> TagEditor {
+    let container = GtkBox::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(6)
+        .margin_top(4)
+        .build();
+
+    let header = GtkBox::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(8)
+        .build();
+
+    let title = Label::builder()
+        .label("Tags")
+        .halign(gtk4::Align::Start)
+        .hexpand(true)
+        .build();
+
+    title.add_css_class("input-label");
+
+    let write_tags = CheckButton::with_label("Write tags");
+    write_tags.set_active(settings.write_audio_tags);
+
+    let embed_cover = CheckButton::with_label("Embed cover art");
+    embed_cover.set_active(settings.embed_cover);
+
+    header.append(&title);
+    header.append(&write_tags);
+    header.append(&embed_cover);
+
+    let fields = GtkBox::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(4)
+        .sensitive(settings.write_audio_tags)
+        .build();
+
+    let (title_box, title_entry) = create_tag_row("Title:", "From the video title");
+    let (artist_box, artist_entry) = create_tag_row("Artist:", "From the video title or channel");
+    let (album_box, album_entry) = create_tag_row("Album:", "None");
+    let (date_box, date_entry) = create_tag_row("Date:", "Year or YYYY-MM-DD");
+
+    let album_row = GtkBox::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(8)
+        .build();
+
+    album_row.append(&album_box);
+    album_row.append(&date_box);
+
+    fields.append(&title_box);
+    fields.append(&artist_box);
+    fields.append(&album_row);
+
+    container.append(&header);
+    container.append(&fields);
+
+    let fields_clone = fields.clone();
+    write_tags.connect_toggled(move |check| {
+        fields_clone.set_sensitive(check.is_active());
+    });
+
+    TagEditor {
+        container,
+        write_tags,
+        embed_cover,
+        title_entry,
+        artist_entry,
+        album_entry,
+        date_entry,
+    }
+}
@@ -0,0 +1,146 @@
+use gtk4::{
+    prelude::*,
+    Box as GtkBox, Orientation, Align, Label, Entry, Button, MenuButton, Popover
+};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::ui::components;
+use crate::downloader::{OutputTemplate, TEMPLATE_TOKENS};
+
+// File name template entry with a token picker and a live preview
+#[derive(Clone)]
+pub struct TemplateRow {
+    pub container: GtkBox,
+    entry: Entry,
+    preview_label: Label,
+    values: Rc<RefCell<HashMap<&'static str, String>>>,
+    restrict_filenames: Rc<Cell<bool>>,
+}
+
+impl TemplateRow {
+    pub fn template(&self) -> OutputTemplate {
+        OutputTemplate::new(&self.entry.text(), self.restrict_filenames.get())
+    }
+
+    pub fn set_template(&self, template: &OutputTemplate) {
+        self.restrict_filenames.set(template.restrict_filenames);
+        self.entry.set_text(&template.template);
+        self.refresh();
+    }
+
+    // Field values of the fetched media, shown in the preview
+    pub fn set_values(&self, values: HashMap<&'static str, String>) {
+        *self.values.borrow_mut() = values;
+        self.refresh();
+    }
+
+    fn refresh(&self) {
+        let preview = self.template().preview(&self.values.borrow());
+        self.preview_label.set_text(&format!("Preview: {}", preview));
+        self.preview_label.set_tooltip_text(Some(&preview));
+    }
+}
+
+// Placeholder values until a URL has been fetched
+pub fn sample_values() -> HashMap<&'static str, String> {
+    HashMap::from([
+        ("title", "Video Title".to_string()),
+        ("id", "dQw4w9WgXcQ".to_string()),
+        ("uploader", "Channel".to_string()),
+        ("channel", "Channel".to_string()),
+        ("upload_date", "20240101".to_string()),
+        ("resolution", "1920x1080".to_string()),
+        ("ext", "mp4".to_string()),
+    ])
+}
+
+pub fn create_template_row(template: &OutputTemplate) -> TemplateRow {
+    let container = GtkBox::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(3)
+        .build();
+
+    let label = Label::builder()
+        .label("File name template:")
+        .halign(Align::Start)
+        .build();
+
+    label.add_css_class("input-label");
+
+    let entry = Entry::builder()
+        .placeholder_text("%(title)s [%(id)s].%(ext)s")
+        .hexpand(true)
+        .build();
+
+    entry.add_css_class("path-entry");
+
+    // Token picker: each button inserts its token at the cursor
+    let token_box = GtkBox::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(2)
+        .build();
+
+    let popover = Popover::builder()
+        .child(&token_box)
+        .build();
+
+    for (name, token) in TEMPLATE_TOKENS {
+        let button = Button::builder()
+            .label(format!("{}  {}", name, token))
+            .build();
+
+        button.add_css_class("flat");
+
+        let entry_clone = entry.clone();
+        let popover_clone = popover.clone();
+        button.connect_clicked(move |_| {
+            let mut position = entry_clone.position();
+            entry_clone.insert_text(token, &mut position);
+            entry_clone.set_position(position);
+            popover_clone.popdown();
+        });
+        token_box.append(&button);
+    }
+
+    let token_button = MenuButton::builder()
+        .label("Insert")
+        .popover(&popover)
+        .build();
+
+    token_button.add_css_class("browse-button");
+
+    let entry_row = GtkBox::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(6)
+        .build();
+
+    entry_row.append(&entry);
+    entry_row.append(&token_button);
+
+    let preview_label = components::create_status_label("");
+    preview_label.set_ellipsize(gtk4::pango::EllipsizeMode::Middle);
+    preview_label.set_xalign(0.0);
+
+    container.append(&label);
+    container.append(&entry_row);
+    container.append(&preview_label);
+
+    let row = TemplateRow {
+        container,
+        entry: entry.clone(),
+        preview_label,
+        values: Rc::new(RefCell::new(sample_values())),
+        restrict_filenames: Rc::new(Cell::new(template.restrict_filenames)),
+    };
+
+    row.set_template(template);
+
+    let row_clone = row.clone();
+    entry.connect_changed(move |_| {
+        row_clone.refresh();
+    });
+
+    row
+}
@@ -1,20 +1,26 @@
 use gtk4::{
     prelude::*,
     Application, ApplicationWindow, Box as GtkBox, 
-    Orientation, Align, Stack, StackSwitcher, Label, Entry, Button
+    Orientation, Align, Stack, StackSwitcher, Label, Entry, Button, ComboBoxText, Spinner
 };
 use std::thread;
 use std::rc::Rc;
-use std::cell::RefCell;
-use std::io::BufRead;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use glib;
 use std::collections::HashSet;
 use rand;
 
 use crate::ui::style;
 use crate::ui::components;
-use crate::downloader::{fetch_available_formats, FormatMessage};
-use crate::app::get_default_download_path;
+use crate::downloader::{probe_media, MediaProbe, FormatMessage, FormatOption, MediaInfo, DirectFile, Playlist, fetch_thumbnail,
+                        merge_containers_for, build_format_selector, estimate_selector_size, codec_rank, codec_compatibility_hint, MERGE_CONTAINERS, DownloadControl,
+                        DownloadQueue, QueueJob, JobKind, JobStatus, QueueEvent, DownloadEvent,
+                        DownloadJob, AudioTags, template_values, set_auth_config,
+                        FfmpegTools, FFMPEG_HELP, ffmpeg_tools, check_ffmpeg,
+                        set_network_options, parse_rate_limit, set_backend_config, parse_checksum};
+use crate::app::{Settings, HistoryEntry, load_auth_config, HistoryOutcome, format_duration, format_size, playlist_dialog, preferences_dialog, history_page, subtitle_panel, tag_editor, template_row, clipboard_watcher, notifications, launch, ytdlp_setup};
 
 // Map resolution to (FPS, format_id, ext) list for video
 type ResolutionMap = Vec<(String, Vec<(u32, String, String)>)>;
@@ -22,9 +28,930 @@ type ResolutionMap = Vec<(String, Vec<(u32, String, String)>)>;
 // Map bitrate to (format_id, ext) list for audio
 type AudioBitrateMap = Vec<(u32, Vec<(String, String)>)>;
 
+// Widgets and state that describe the current Video/Audio selection
+#[derive(Clone)]
+struct MediaSelection {
+    media_stack: Stack,
+    quality_combo: ComboBoxText,
+    fps_combo: ComboBoxText,
+    format_combo: ComboBoxText,
+    codec_combo: ComboBoxText,
+    subtitle_panel: subtitle_panel::SubtitlePanel,
+    tag_editor: tag_editor::TagEditor,
+    template_row: template_row::TemplateRow,
+    rate_limit_entry: Entry,
+    checksum_entry: Entry,
+    audio_quality_combo: ComboBoxText,
+    audio_format_combo: ComboBoxText,
+    resolution_map: Rc<RefCell<ResolutionMap>>,
+    audio_bitrate_map: Rc<RefCell<AudioBitrateMap>>,
+    fetched_formats: Rc<RefCell<Vec<FormatOption>>>,
+    media_info: Rc<RefCell<Option<MediaInfo>>>,
+    direct_file: Rc<RefCell<Option<DirectFile>>>,
+}
+
+impl MediaSelection {
+    // Turn the active dropdown choices into a download job
+    fn to_queue_job(&self, url: &str, output_path: &str) -> Result<QueueJob, String> {
+        // A direct file link has nothing to choose, the file is saved as the server sends it
+        if let Some(file) = self.direct_file.borrow().as_ref() {
+            let checksum = self.checksum_entry.text();
+            let checksum = Some(checksum.trim())
+                .filter(|checksum| !checksum.is_empty())
+                .map(parse_checksum)
+                .transpose()?;
+            
+            return Ok(QueueJob {
+                url: url.to_string(),
+                output_path: output_path.to_string(),
+                label: format!("{} ({}) - {}", file.file_name, file.mime_type(), url),
+                kind: JobKind::Direct { checksum },
+                title: Some(file.file_name.clone()),
+                duration: None,
+                subtitles: None,
+                tags: None,
+                output_template: None,
+                rate_limit: parse_rate_limit(&self.rate_limit_entry.text())?,
+            });
+        }
+        
+        let active_tab = self.media_stack.visible_child_name().unwrap_or_else(|| "video".into());
+        
+        if active_tab == "video" {
+            let quality_idx = self.quality_combo.active_id()
+                .and_then(|id| id.parse::<usize>().ok())
+                .ok_or("Please select a video quality")?;
+            
+            // Extract the numeric part from "XX fps"
+            let selected_fps = self.fps_combo.active_text()
+                .and_then(|text| text.split_whitespace().next().and_then(|s| s.parse::<u32>().ok()))
+                .unwrap_or(0);
+            
+            // The id is "ext" or "ext:container" for merged streams
+            let format_choice = self.format_combo.active_id()
+                .ok_or("Please select a video format")?;
+            let (selected_format, merge_format) = match format_choice.split_once(':') {
+                Some((ext, container)) => (ext.to_lowercase(), Some(container.to_string())),
+                None => (format_choice.to_lowercase(), None),
+            };
+            
+            let map = self.resolution_map.borrow();
+            let (resolution, fps_values) = map.get(quality_idx)
+                .ok_or("Please select a video quality")?;
+            
+            // Streams are ordered by codec preference, so without a codec choice the preferred one wins
+            let selected_codec = self.codec_combo.active_id();
+            let codec_matches = |format_id: &str| selected_codec.as_deref()
+                .is_none_or(|codec| stream_codec(&self.fetched_formats.borrow(), format_id).as_deref() == Some(codec));
+            
+            // Without a merge container the stream itself has to carry the audio
+            let audio_matches = |format_id: &str| merge_format.is_some()
+                || stream_is_video_only(&self.fetched_formats.borrow(), format_id) == Some(false);
+            
+            let mut format_id = fps_values.iter()
+                .find(|(fps, format_id, ext)| (*fps == selected_fps || selected_fps == 0)
+                    && ext.to_lowercase() == selected_format
+                    && codec_matches(format_id)
+                    && audio_matches(format_id))
+                .map(|(_, format_id, _)| format_id.clone())
+                .ok_or("No matching format found for the selected quality, FPS and format type.")?;
+            
+            // Pair video-only streams with the best compatible audio stream
+            if let Some(container) = &merge_format {
+                let all_formats = self.fetched_formats.borrow();
+                if let Some(video) = all_formats.iter().find(|f| f.format_id == format_id) {
+                    format_id = build_format_selector(video, &all_formats, container);
+                }
+            }
+            
+            let output_ext = merge_format.clone().unwrap_or(selected_format);
+            
+            Ok(QueueJob {
+                url: url.to_string(),
+                output_path: output_path.to_string(),
+                label: format!("{} @ {} fps ({}) - {}", resolution, selected_fps, output_ext.to_uppercase(), url),
+                kind: JobKind::Video { format_id, merge_format },
+                title: self.media_title(),
+                duration: self.media_info.borrow().as_ref().and_then(|info| info.duration),
+                subtitles: self.subtitle_panel.options(),
+                tags: None,
+                output_template: Some(self.template_row.template()),
+                rate_limit: parse_rate_limit(&self.rate_limit_entry.text())?,
+            })
+        } else if active_tab == "audio" {
+            let quality_idx = self.audio_quality_combo.active_id()
+                .and_then(|id| id.parse::<usize>().ok())
+                .ok_or("Please select an audio quality")?;
+            
+            let selected_format = self.audio_format_combo.active_text()
+                .map(|text| text.to_lowercase())
+                .ok_or("Please select an audio format")?;
+            
+            let audio_map = self.audio_bitrate_map.borrow();
+            let (bitrate, format_values) = audio_map.get(quality_idx)
+                .ok_or("Please select an audio quality")?;
+            
+            let format_id = format_values.iter()
+                .find(|(_, ext)| ext.to_lowercase() == selected_format)
+                .map(|(format_id, _)| format_id.clone())
+                .ok_or("No matching format found for the selected audio quality and format type.")?;
+            
+            Ok(QueueJob {
+                url: url.to_string(),
+                output_path: output_path.to_string(),
+                label: format!("Audio @ {} kbps ({}) - {}", bitrate, selected_format.to_uppercase(), url),
+                kind: JobKind::Audio { format_id, audio_format: audio_target_format(&selected_format).to_string() },
+                title: self.media_title(),
+                duration: self.media_info.borrow().as_ref().and_then(|info| info.duration),
+                subtitles: None,
+                tags: self.tag_editor.tags(),
+                output_template: Some(self.template_row.template()),
+                rate_limit: parse_rate_limit(&self.rate_limit_entry.text())?,
+            })
+        } else {
+            Err("Please select video or audio options before downloading.".to_string())
+        }
+    }
+}
+
+impl MediaSelection {
+    // Title of the fetched media, used for the history instead of the file name
+    fn media_title(&self) -> Option<String> {
+        self.media_info.borrow().as_ref()
+            .map(|info| info.title.clone())
+            .filter(|title| !title.is_empty())
+    }
+    
+    // Store the current dropdown choices as the preferred quality for the next fetch
+    fn remember_preferences(&self, settings: &mut Settings) {
+        if self.direct_file.borrow().is_some() {
+            return;
+        }
+        
+        let active_tab = self.media_stack.visible_child_name().unwrap_or_else(|| "video".into());
+        
+        if active_tab == "video" {
+            let map = self.resolution_map.borrow();
+            if let Some((resolution, _)) = self.quality_combo.active_id()
+                .and_then(|id| id.parse::<usize>().ok())
+                .and_then(|idx| map.get(idx)) {
+                settings.preferred_height = resolution.split('x').nth(1).and_then(|h| h.parse().ok());
+            }
+            
+            settings.preferred_fps = self.fps_combo.active_id()
+                .and_then(|id| id.parse::<u32>().ok())
+                .filter(|fps| *fps > 0);
+            
+            // The output container is the merge target for merged streams
+            if let Some(format_choice) = self.format_combo.active_id() {
+                if let Some(container) = format_choice.rsplit(':').next() {
+                    settings.preferred_container = container.to_lowercase();
+                }
+            }
+            
+            self.subtitle_panel.remember(settings);
+        } else if active_tab == "audio" {
+            let audio_map = self.audio_bitrate_map.borrow();
+            if let Some((bitrate, _)) = self.audio_quality_combo.active_id()
+                .and_then(|id| id.parse::<usize>().ok())
+                .and_then(|idx| audio_map.get(idx)) {
+                settings.preferred_abr = Some(*bitrate);
+            }
+            
+            if let Some(format) = self.audio_format_combo.active_text() {
+                settings.audio_format = format.to_lowercase();
+            }
+            
+            self.tag_editor.remember(settings);
+        }
+        
+        // Each media type keeps its own file name template
+        let template = self.template_row.template().template;
+        if active_tab == "audio" {
+            settings.audio_template = template;
+        } else {
+            settings.video_template = template;
+        }
+    }
+    
+    // Preview the file name with the fetched metadata and the selected output format
+    fn refresh_template_preview(&self) {
+        let info = self.media_info.borrow();
+        let Some(info) = info.as_ref() else {
+            self.template_row.set_values(template_row::sample_values());
+            return;
+        };
+        
+        let active_tab = self.media_stack.visible_child_name().unwrap_or_else(|| "video".into());
+        let values = if active_tab == "audio" {
+            let ext = self.audio_format_combo.active_text()
+                .map(|text| text.to_lowercase())
+                .unwrap_or_default();
+            template_values(info, &ext, "audio only")
+        } else {
+            // The id is "ext" or "ext:container" for merged streams
+            let ext = self.format_combo.active_id()
+                .and_then(|id| id.rsplit(':').next().map(|ext| ext.to_lowercase()))
+                .unwrap_or_default();
+            let map = self.resolution_map.borrow();
+            let resolution = self.quality_combo.active_id()
+                .and_then(|id| id.parse::<usize>().ok())
+                .and_then(|idx| map.get(idx))
+                .map(|(resolution, _)| resolution.clone())
+                .unwrap_or_default();
+            template_values(info, &ext, &resolution)
+        };
+        self.template_row.set_values(values);
+    }
+}
+
+// Index of the preferred resolution: the exact height or the tallest one below it
+fn preferred_resolution_index(map: &ResolutionMap, height: Option<u32>) -> u32 {
+    let Some(height) = height else {
+        return 0;
+    };
+    
+    map.iter()
+        .position(|(resolution, _)| {
+            resolution.split('x').nth(1)
+                .and_then(|h| h.parse::<u32>().ok())
+                .is_some_and(|h| h <= height)
+        })
+        .unwrap_or(0) as u32
+}
+
+// Index of the preferred audio bitrate: the highest one not above it
+fn preferred_bitrate_index(map: &AudioBitrateMap, abr: Option<u32>) -> u32 {
+    let Some(abr) = abr else {
+        return 0;
+    };
+    
+    map.iter()
+        .position(|(bitrate, _)| *bitrate <= abr)
+        .unwrap_or(0) as u32
+}
+
+// Queue list widgets together with the queue they display
+#[derive(Clone)]
+struct QueueView {
+    queue: DownloadQueue,
+    rows: Rc<RefCell<HashMap<u64, components::QueueRow>>>,
+    list: GtkBox,
+    empty_label: Label,
+}
+
+impl QueueView {
+    // Enqueue a job and add its row with retry/remove actions to the list
+    fn enqueue(&self, job: QueueJob) {
+        let row = components::create_queue_row(&job.label);
+        let id = self.queue.add(job);
+        
+        // Retry puts a failed or cancelled job back into the queue
+        let queue = self.queue.clone();
+        row.retry_button.connect_clicked(move |_| {
+            queue.retry(id);
+        });
+        
+        // Remove stops the job if needed and drops its row
+        let view = self.clone();
+        row.remove_button.connect_clicked(move |_| {
+            view.queue.remove(id);
+            
+            let mut rows = view.rows.borrow_mut();
+            if let Some(row) = rows.remove(&id) {
+                view.list.remove(&row.container);
+            }
+            view.empty_label.set_visible(rows.is_empty());
+        });
+        
+        self.empty_label.set_visible(false);
+        self.list.append(&row.container);
+        self.rows.borrow_mut().insert(id, row);
+    }
+    
+    // Show the progress or new status of a job in its row
+    fn update_row(&self, event: &QueueEvent) {
+        let rows = self.rows.borrow();
+        
+        match event {
+            QueueEvent::Progress(id, progress) => {
+                if let Some(row) = rows.get(id) {
+                    row.progress_bar.set_fraction(progress.overall_percent);
+                    row.progress_bar.set_text(Some(&format!("{:.1}%", progress.overall_percent * 100.0)));
+                    row.status_label.set_text(&progress.status_message);
+                }
+            },
+            QueueEvent::Status(id, status) => {
+                if let Some(row) = rows.get(id) {
+                    let can_retry = matches!(status, JobStatus::Failed(_) | JobStatus::Cancelled);
+                    row.retry_button.set_visible(can_retry);
+                    
+                    match status {
+                        JobStatus::Queued => {
+                            row.progress_bar.set_fraction(0.0);
+                            row.progress_bar.set_text(Some("0%"));
+                            row.status_label.set_text("Waiting in queue");
+                        },
+                        JobStatus::Running => {
+                            row.status_label.set_text("Starting download...");
+                        },
+                        JobStatus::Completed => {
+                            row.progress_bar.set_fraction(1.0);
+                            row.progress_bar.set_text(Some("100.0%"));
+                            row.status_label.set_text("Download complete!");
+                        },
+                        JobStatus::Failed(reason) => {
+                            row.status_label.set_text(&format!("Download failed: {}", reason));
+                        },
+                        JobStatus::Cancelled => {
+                            row.status_label.set_text("Download cancelled");
+                        },
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Records finished queue jobs in the history and notifies about them
+struct QueueReporter {
+    queue: DownloadQueue,
+    history_page: history_page::HistoryPage,
+    notifier: notifications::Notifier,
+    finished_counts: Cell<(usize, usize)>, // Completed and failed since the queue was last idle
+}
+
+impl QueueReporter {
+    fn report(&self, event: &QueueEvent) {
+        let QueueEvent::Status(id, status) = event else {
+            return;
+        };
+        let outcome = match status {
+            JobStatus::Completed => HistoryOutcome::Completed,
+            JobStatus::Failed(reason) => HistoryOutcome::Failed(reason.clone()),
+            JobStatus::Cancelled => HistoryOutcome::Cancelled,
+            _ => return,
+        };
+        let Some((job, output_file)) = self.queue.finished_job(*id) else {
+            return;
+        };
+        
+        let (completed, failed) = self.finished_counts.get();
+        let notification_id = format!("download-{}", id);
+        match status {
+            JobStatus::Completed => {
+                self.notifier.download_finished(&notification_id, &job, output_file.as_deref());
+                self.finished_counts.set((completed + 1, failed));
+            },
+            JobStatus::Failed(reason) => {
+                self.notifier.download_failed(&notification_id, &job, reason, Some(*id));
+                self.finished_counts.set((completed, failed + 1));
+            },
+            _ => {},
+        }
+        self.history_page.record(HistoryEntry::new(&job, output_file, outcome));
+        
+        // A single job already got its own notification
+        if self.queue.is_idle() {
+            let (completed, failed) = self.finished_counts.replace((0, 0));
+            if completed + failed > 1 {
+                self.notifier.queue_drained(completed, failed);
+            }
+        }
+    }
+}
+
+// Codec name of a fetched stream, e.g. "VP9"
+fn stream_codec(formats: &[FormatOption], format_id: &str) -> Option<String> {
+    formats.iter()
+        .find(|f| f.format_id == format_id)
+        .map(|f| f.codec_label())
+}
+
+fn stream_is_video_only(formats: &[FormatOption], format_id: &str) -> Option<bool> {
+    formats.iter()
+        .find(|f| f.format_id == format_id)
+        .map(|f| f.is_video_only())
+}
+
+// " · ~412.0 MB" for a known size, empty otherwise
+fn size_suffix(size: Option<u64>) -> String {
+    size.map(|size| format!(" · ~{}", format_size(size))).unwrap_or_default()
+}
+
+// Estimated size of a video stream, including the audio stream merged into it
+fn merged_size(video: &FormatOption, formats: &[FormatOption], container: &str) -> Option<u64> {
+    if video.is_video_only() {
+        estimate_selector_size(&build_format_selector(video, formats, container), formats)
+    } else {
+        video.estimated_size()
+    }
+}
+
+// Quality dropdown label from the highest FPS stream of a resolution, e.g. "1080p60 · VP9 · ~412.0 MB"
+fn video_quality_label(resolution: &str, fps_list: &[(u32, String, String)], formats: &[FormatOption]) -> String {
+    let Some(video) = fps_list.first()
+        .and_then(|(_, format_id, _)| formats.iter().find(|f| &f.format_id == format_id)) else {
+        return resolution.to_string();
+    };
+    
+    let mut label = match video.height() {
+        0 => resolution.to_string(),
+        height => format!("{}p", height),
+    };
+    if video.fps > 0 {
+        label.push_str(&video.fps.to_string());
+    }
+    
+    let container = if MERGE_CONTAINERS.contains(&video.ext.as_str()) { video.ext.as_str() } else { "mkv" };
+    format!("{} · {}{}", label, video.codec_label(), size_suffix(merged_size(video, formats, container)))
+}
+
+// Fill the preview card and load the thumbnail in the background
+fn show_media_preview(preview: &components::MediaPreview, info: &MediaInfo) {
+    let title = if info.title.is_empty() { "Untitled" } else { info.title.as_str() };
+    
+    let mut details = Vec::new();
+    if !info.uploader.is_empty() {
+        details.push(info.uploader.clone());
+    }
+    if let Some(duration) = info.duration {
+        details.push(format_duration(duration));
+    }
+    if let Some(date) = info.formatted_upload_date() {
+        details.push(date);
+    }
+    
+    preview.show(title, &details.join(" · "));
+    preview.container.set_tooltip_text(Some(&info.description).filter(|d| !d.is_empty()).map(|d| d.as_str()));
+    
+    let Some(thumbnail_url) = info.thumbnail.clone() else {
+        return;
+    };
+    
+    let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+    thread::spawn(move || {
+        match fetch_thumbnail(&thumbnail_url) {
+            Ok(path) => {
+                let _ = sender.send(path);
+            },
+            Err(e) => println!("Error fetching thumbnail: {:?}", e),
+        }
+    });
+    
+    // The preview is shown again for each fetch, so drop thumbnails of an earlier one
+    let thumbnail = preview.thumbnail.clone();
+    let title_label = preview.title_label.clone();
+    let expected_title = title.to_string();
+    receiver.attach(None, move |path: std::path::PathBuf| {
+        if title_label.text() == expected_title {
+            thumbnail.set_filename(Some(&path));
+        }
+        glib::Continue(false)
+    });
+}
+
+// Map a selected audio extension to the --audio-format value for yt-dlp
+fn audio_target_format(ext: &str) -> &'static str {
+    match ext {
+        "m4a" => "m4a",
+        "mp3" => "mp3",
+        "opus" => "opus",
+        "webm" => "webm",
+        _ => "best" // Default to best
+    }
+}
+
+// URL form widgets that a fetch fills in, together with the selection it prepares
+#[derive(Clone)]
+struct FetchView {
+    window: ApplicationWindow,
+    url_entry: Entry,
+    path_entry: Entry,
+    spinner: Spinner,
+    status_label: Label,
+    format_info: Label,
+    download_button: Button,
+    media_preview: components::MediaPreview,
+    selection: MediaSelection,
+    queue_view: QueueView,
+    settings: Rc<RefCell<Settings>>,
+}
+
+impl FetchView {
+    // Fetch the formats of the entered URL, or the entries if it is a playlist
+    fn fetch(&self) {
+        let url = self.url_entry.text().to_string();
+        
+        if url.is_empty() {
+            components::show_error_dialog(&self.window, "Invalid URL", "Please enter a URL");
+            self.status_label.set_text("Please enter a URL");
+            self.status_label.set_visible(true);
+            return;
+        }
+        
+        // Show processing UI
+        self.status_label.set_text("Fetching available formats...");
+        self.status_label.set_visible(true);
+        self.spinner.start();
+        self.reset();
+        
+        // Create a channel to communicate between threads
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+        
+        // Spawn a thread to fetch formats, or the entries if the URL is a playlist
+        thread::spawn(move || {
+            match probe_media(&url) {
+                Ok(MediaProbe::Single(info, fetched_formats)) => {
+                    let _ = sender.send(FormatMessage::Formats(info, fetched_formats));
+                },
+                Ok(MediaProbe::Playlist(playlist)) => {
+                    let _ = sender.send(FormatMessage::Playlist(playlist));
+                },
+                Ok(MediaProbe::Direct(file)) => {
+                    let _ = sender.send(FormatMessage::Direct(file));
+                },
+                Err(e) => {
+                    println!("Error fetching formats: {:?}", e);
+                    let _ = sender.send(FormatMessage::Error);
+                }
+            }
+        });
+        
+        // Handle messages from the thread
+        let view = self.clone();
+        receiver.attach(None, move |message| {
+            match message {
+                FormatMessage::Formats(info, formats) => view.show_formats(*info, formats),
+                FormatMessage::Playlist(playlist) => view.show_playlist(playlist),
+                FormatMessage::Direct(file) => view.show_direct_file(*file),
+                FormatMessage::Error => {
+                    view.status_label.set_text("Error fetching formats");
+                    view.format_info.set_text("Check your URL or internet connection");
+                    components::show_error_dialog(&view.window, "URL Error", 
+                        "Failed to fetch video formats. Please check if the URL is valid and your internet connection is working.");
+                }
+            }
+            
+            // Hide processing UI once done
+            view.spinner.stop();
+            glib::Continue(true)
+        });
+    }
+    
+    // Forget the previous fetch and disable the form until the new one arrives
+    fn reset(&self) {
+        let selection = &self.selection;
+        selection.resolution_map.borrow_mut().clear();
+        selection.audio_bitrate_map.borrow_mut().clear();
+        selection.fetched_formats.borrow_mut().clear();
+        *selection.media_info.borrow_mut() = None;
+        *selection.direct_file.borrow_mut() = None;
+        
+        self.media_preview.hide();
+        selection.subtitle_panel.clear();
+        selection.tag_editor.clear();
+        
+        for combo in [&selection.quality_combo, &selection.fps_combo, &selection.format_combo,
+                      &selection.audio_quality_combo, &selection.audio_format_combo] {
+            combo.remove_all();
+            combo.set_sensitive(false);
+        }
+        self.download_button.set_sensitive(false);
+        self.format_info.set_text("Analyzing media source...");
+    }
+    
+    fn show_formats(&self, info: MediaInfo, formats: Vec<FormatOption>) {
+        let selection = &self.selection;
+        *selection.fetched_formats.borrow_mut() = formats.clone();
+        show_media_preview(&self.media_preview, &info);
+        selection.subtitle_panel.set_tracks(info.subtitles.clone(), &self.settings.borrow().subtitle_languages);
+        selection.tag_editor.fill(&info);
+        *selection.media_info.borrow_mut() = Some(info);
+        
+        // Separate video and audio formats
+        let video_formats: Vec<&FormatOption> = formats.iter()
+            .filter(|format| !format.is_audio_only)
+            .collect();
+            
+        let audio_formats: Vec<&FormatOption> = formats.iter()
+            .filter(|format| format.is_audio_only)
+            .collect();
+            
+        println!("Found {} video formats and {} audio formats", 
+            video_formats.len(), audio_formats.len());
+        
+        *selection.resolution_map.borrow_mut() =
+            build_resolution_map(&video_formats, &formats, &self.settings.borrow().codec_preference);
+        *selection.audio_bitrate_map.borrow_mut() = build_audio_bitrate_map(&audio_formats, &video_formats);
+        
+        self.fill_video_options(&formats);
+        self.fill_audio_options(&formats);
+        
+        self.status_label.set_text("Formats fetched successfully");
+        self.format_info.set_text("Select your preferred quality, FPS and file format");
+        self.update_output_path();
+    }
+    
+    // Populate the resolution combo box
+    fn fill_video_options(&self, formats: &[FormatOption]) {
+        let map = self.selection.resolution_map.borrow();
+        let quality_combo = &self.selection.quality_combo;
+        
+        if map.is_empty() {
+            self.status_label.set_text("No suitable video formats found");
+            self.format_info.set_text("Try a different URL or check if the media source is valid");
+            return;
+        }
+        
+        for (i, (resolution, fps_list)) in map.iter().enumerate() {
+            quality_combo.append(Some(&i.to_string()), &video_quality_label(resolution, fps_list, formats));
+        }
+        
+        // If we have resolutions, activate the preferred one, which populates the FPS options
+        quality_combo.set_sensitive(true);
+        quality_combo.set_active(Some(preferred_resolution_index(&map, self.settings.borrow().preferred_height)));
+    }
+    
+    // Populate the audio bitrate and audio format combo boxes
+    fn fill_audio_options(&self, formats: &[FormatOption]) {
+        let audio_map = self.selection.audio_bitrate_map.borrow();
+        let audio_quality_combo = &self.selection.audio_quality_combo;
+        let audio_format_combo = &self.selection.audio_format_combo;
+        
+        if audio_map.is_empty() {
+            println!("No suitable audio formats found");
+            return;
+        }
+        
+        for (i, (bitrate, format_values)) in audio_map.iter().enumerate() {
+            // Largest stream of this bitrate, so the estimate doesn't undersell
+            let size = format_values.iter()
+                .filter_map(|(format_id, _)| formats.iter().find(|f| &f.format_id == format_id))
+                .filter_map(|f| f.estimated_size())
+                .max();
+            audio_quality_combo.append(Some(&i.to_string()), &format!("{} kbps{}", bitrate, size_suffix(size)));
+        }
+        
+        // If we have bitrates, activate the preferred one
+        audio_quality_combo.set_sensitive(true);
+        audio_quality_combo.set_active(Some(preferred_bitrate_index(&audio_map, self.settings.borrow().preferred_abr)));
+        
+        // Populate audio format options (m4a, mp3, etc.)
+        audio_format_combo.remove_all();
+        
+        // First collect the available extensions from the formats
+        let mut available_extensions = HashSet::new();
+        for format_list in audio_map.iter() {
+            for (_, ext) in &format_list.1 {
+                available_extensions.insert(ext.clone().to_lowercase());
+            }
+        }
+        
+        println!("Available audio extensions: {:?}", available_extensions);
+        
+        // Common audio formats to check for
+        let preferred_audio_extensions = ["m4a", "mp3", "opus", "aac", "wav", "ogg"];
+        
+        // Add only the available formats in the preferred order
+        let mut found_formats = 0;
+        for ext in preferred_audio_extensions.iter() {
+            if available_extensions.contains(&ext.to_string()) {
+                audio_format_combo.append(Some(ext), &ext.to_uppercase());
+                found_formats += 1;
+            }
+        }
+        
+        // If we didn't find any of the preferred formats, add all available formats
+        if found_formats == 0 {
+            for ext_str in available_extensions {
+                audio_format_combo.append(Some(&ext_str), &ext_str.to_uppercase());
+            }
+        }
+    }
+    
+    fn show_playlist(&self, playlist: Playlist) {
+        self.status_label.set_text(&format!("Playlist found: {} entries", playlist.entries.len()));
+        self.format_info.set_text("Choose the playlist entries to add to the download queue");
+        
+        // Every selected entry becomes a queue job with the chosen preset
+        let queue_view = self.queue_view.clone();
+        let path_entry = self.path_entry.clone();
+        let (playlist_title, playlist_uploader) = (playlist.title.clone(), playlist.uploader.clone());
+        let (write_tags, embed_cover, video_template, audio_template) = {
+            let settings = self.settings.borrow();
+            (settings.write_audio_tags, settings.embed_cover, settings.template_for("video"), settings.template_for("audio"))
+        };
+        playlist_dialog::show_playlist_dialog(&self.window, playlist, move |entries, preset| {
+            let output_path = path_entry.text().to_string();
+            for entry in entries {
+                // Audio entries are tagged from their titles, with the playlist as album
+                let tags = match preset.job_kind() {
+                    JobKind::Audio { .. } if write_tags || embed_cover => Some(AudioTags {
+                        embed_metadata: write_tags,
+                        embed_cover,
+                        ..AudioTags::from_title(&entry.title, &playlist_uploader, &playlist_title)
+                    }),
+                    _ => None,
+                };
+                let template = match preset.job_kind() {
+                    JobKind::Audio { .. } => &audio_template,
+                    _ => &video_template,
+                };
+                queue_view.enqueue(QueueJob {
+                    url: entry.url.clone(),
+                    output_path: output_path.clone(),
+                    kind: preset.job_kind(),
+                    label: format!("{}. {} ({})", entry.index, entry.title, preset.label()),
+                    title: Some(entry.title.clone()),
+                    duration: entry.duration,
+                    subtitles: None,
+                    tags,
+                    output_template: Some(template.for_playlist_entry(entry.index, &playlist_title)),
+                    rate_limit: None,
+                });
+            }
+        });
+    }
+    
+    fn show_direct_file(&self, file: DirectFile) {
+        *self.selection.media_info.borrow_mut() = Some(file.media_info());
+        
+        // No thumbnail or duration, the preview describes the file
+        let mut details = vec![file.mime_type().to_string()];
+        if let Some(size) = file.size {
+            details.push(format_size(size));
+        }
+        self.media_preview.show_file(&file.file_name, &details.join(" · "), file.digest.as_deref());
+        
+        self.status_label.set_text("Direct file link found");
+        self.format_info.set_text("The file is downloaded as it is, without yt-dlp");
+        self.download_button.set_sensitive(true);
+        *self.selection.direct_file.borrow_mut() = Some(file);
+        self.update_output_path();
+    }
+    
+    // Switch to the folder of the active tab, unless the user picked another one by hand
+    fn update_output_path(&self) {
+        let settings = self.settings.borrow();
+        let current_path = self.path_entry.text().to_string();
+        let should_update_path = current_path.is_empty() || 
+                                 current_path == "." || 
+                                 current_path == settings.video_output_dir || 
+                                 current_path == settings.audio_output_dir;
+        
+        if should_update_path {
+            let active_tab = self.selection.media_stack.visible_child_name().unwrap_or_else(|| "video".into());
+            self.path_entry.set_text(settings.output_dir_for(&active_tab));
+        }
+    }
+}
+
+// Widgets that follow the settings when they are changed in the Preferences dialog
+#[derive(Clone)]
+struct PreferencesView {
+    window: ApplicationWindow,
+    settings: Rc<RefCell<Settings>>,
+    path_entry: Entry,
+    status_label: Label,
+    parallel_spin: gtk4::SpinButton,
+    selection: MediaSelection,
+}
+
+impl PreferencesView {
+    fn show(&self) {
+        let previous = self.settings.borrow().clone();
+        let view = self.clone();
+        preferences_dialog::show_preferences_dialog(&self.window, Rc::clone(&self.settings), move |updated| {
+            view.apply(&previous, updated);
+        });
+    }
+    
+    fn apply(&self, previous: &Settings, updated: &Settings) {
+        ytdlp_setup::check_ytdlp(&self.window, Rc::clone(&self.settings), &self.status_label);
+        set_auth_config(load_auth_config(updated));
+        set_network_options(updated.network.clone());
+        set_backend_config(updated.backend_config());
+        
+        // The spin button handler passes the new limit on to the queue
+        self.parallel_spin.set_value(updated.max_concurrent as f64);
+        
+        // Follow a changed output folder unless the user picked another one by hand
+        let active_tab = self.selection.media_stack.visible_child_name().unwrap_or_else(|| "video".into());
+        let current_path = self.path_entry.text().to_string();
+        if current_path == previous.video_output_dir || current_path == previous.audio_output_dir {
+            self.path_entry.set_text(updated.output_dir_for(&active_tab));
+        }
+        
+        self.selection.template_row.set_template(&updated.template_for(&active_tab));
+    }
+}
+
+// Group video streams by resolution, with one entry per FPS, extension, codec and audio
+fn build_resolution_map(video_formats: &[&FormatOption], formats: &[FormatOption], codec_preference: &[String]) -> ResolutionMap {
+    let mut map = ResolutionMap::new();
+    
+    for format in video_formats {
+        let resolution = &format.resolution;
+        let fps = format.fps;
+        let format_id = &format.format_id;
+        let ext = &format.ext;
+        
+        // Find if this resolution is already in our map
+        match map.iter().position(|(res, _)| res == resolution) {
+            Some(idx) => {
+                // Check if this FPS, extension and codec combination is already in the list;
+                // a muxed and a video-only stream are kept apart so the audio isn't lost
+                let fps_list = &mut map[idx].1;
+                let codec = format.codec_label();
+                if !fps_list.iter().any(|(existing_fps, existing_id, existing_ext)| 
+                    *existing_fps == fps && existing_ext == ext
+                        && stream_codec(formats, existing_id).as_deref() == Some(codec.as_str())
+                        && stream_is_video_only(formats, existing_id) == Some(format.is_video_only())) {
+                    fps_list.push((fps, format_id.clone(), ext.clone()));
+                }
+            },
+            None => {
+                // Add this resolution with its first FPS
+                map.push((resolution.clone(), vec![(fps, format_id.clone(), ext.clone())]));
+            }
+        }
+    }
+    
+    // Sort resolutions by height (descending)
+    map.sort_by_key(|(resolution, _)| {
+        let height: u32 = resolution.split('x').nth(1)
+            .and_then(|h| h.parse().ok())
+            .unwrap_or(0);
+        std::cmp::Reverse(height)
+    });
+    
+    // Sort FPS values for each resolution (descending), then streams by codec preference
+    for (_, fps_list) in map.iter_mut() {
+        fps_list.sort_by_key(|(fps, format_id, _)| {
+            let codec = stream_codec(formats, format_id).unwrap_or_default();
+            (std::cmp::Reverse(*fps), codec_rank(&codec, codec_preference))
+        });
+    }
+    map
+}
+
+// Group audio streams by bitrate; without audio-only streams the audio of muxed ones is used
+fn build_audio_bitrate_map(audio_formats: &[&FormatOption], video_formats: &[&FormatOption]) -> AudioBitrateMap {
+    let mut map = AudioBitrateMap::new();
+    
+    if !audio_formats.is_empty() {
+        println!("Processing {} dedicated audio formats", audio_formats.len());
+        for format in audio_formats {
+            let bitrate = format.abr;
+            println!("Audio format: id={}, codec={}, bitrate={}kbps, ext={}", 
+                format.format_id, format.acodec, bitrate, format.ext);
+            
+            // Skip formats with 0 bitrate after our processing
+            if bitrate == 0 {
+                println!("Skipping audio format with 0 bitrate: {}", format.format_id);
+                continue;
+            }
+            
+            match map.iter().position(|(br, _)| *br == bitrate) {
+                Some(idx) => map[idx].1.push((format.format_id.clone(), format.ext.clone())),
+                None => map.push((bitrate, vec![(format.format_id.clone(), format.ext.clone())])),
+            }
+        }
+    } else {
+        println!("No audio-only formats found, looking for audio in video formats");
+        
+        // Collect all non-zero audio bitrates from video formats
+        let audio_bitrates: HashSet<u32> = video_formats.iter()
+            .filter(|format| format.acodec != "none" && format.abr > 0)
+            .map(|format| format.abr)
+            .collect();
+        
+        for bitrate in audio_bitrates {
+            // Find all format_ids that provide this audio bitrate
+            let format_pairs: Vec<(String, String)> = video_formats.iter()
+                .filter(|f| f.abr == bitrate && f.acodec != "none")
+                .map(|f| (f.format_id.clone(), f.ext.clone()))
+                .collect();
+            
+            map.push((bitrate, format_pairs));
+        }
+    }
+    
+    // Sort audio bitrates (descending)
+    map.sort_by(|(a, _), (b, _)| b.cmp(a));
+    map
+}
+
 pub fn build_ui(app: &Application) {
     // Load CSS
     style::load_css();
+    
+    // Persistent preferences shared by all handlers
+    let settings = Rc::new(RefCell::new(Settings::load()));
+    set_auth_config(load_auth_config(&settings.borrow()));
+    set_network_options(settings.borrow().network.clone());
+    set_backend_config(settings.borrow().backend_config());
 
     // Create a main container
     let main_container = GtkBox::builder()
@@ -68,8 +995,30 @@ pub fn build_ui(app: &Application) {
     
     about_button.add_css_class("about-button");
     
+    // Preferences button next to About
+    let preferences_button = Button::builder()
+        .label("Preferences")
+        .valign(Align::Center)
+        .margin_start(8)
+        .build();
+    
+    preferences_button.add_css_class("about-button");
+    
+    // Main stack with the download form and the history page
+    let main_stack = Stack::builder()
+        .transition_type(gtk4::StackTransitionType::Crossfade)
+        .vexpand(true)
+        .build();
+    
+    let main_stack_switcher = StackSwitcher::builder()
+        .stack(&main_stack)
+        .valign(Align::Center)
+        .build();
+    
     // Add sides to header container
     header_container.append(&header_left);
+    header_container.append(&main_stack_switcher);
+    header_container.append(&preferences_button);
     header_container.append(&about_button);
     
     // Create a content container with padding
@@ -137,6 +1086,10 @@ pub fn build_ui(app: &Application) {
     let (spinner_box, spinner, status_label) = components::create_spinner_with_label();
     url_card.append(&spinner_box);
     
+    // Preview of the fetched media, shown once formats are loaded
+    let media_preview = components::create_media_preview();
+    url_card.append(&media_preview.container);
+    
     // Add URL card to the main container
     container.append(&url_card);
     
@@ -232,7 +1185,12 @@ pub fn build_ui(app: &Application) {
     let (format_box, format_combo) = components::create_dropdown("Format:");
     format_box.set_hexpand(true);
     
+    // Codec selection, for formats that come in several codecs
+    let (codec_box, codec_combo) = components::create_dropdown("Codec:");
+    codec_box.set_hexpand(true);
+    
     format_row.append(&format_box);
+    format_row.append(&codec_box);
     
     // Add rows to the grid
     video_grid.append(&quality_row);
@@ -241,6 +1199,10 @@ pub fn build_ui(app: &Application) {
     // Add grid to video options
     video_options_container.append(&video_grid);
     
+    // Subtitle languages of the fetched video
+    let subtitle_panel = subtitle_panel::create_subtitle_panel(&settings.borrow());
+    video_options_container.append(&subtitle_panel.container);
+    
     // Audio options container
     let audio_options_container = GtkBox::builder()
         .orientation(Orientation::Vertical)
@@ -291,10 +1253,17 @@ pub fn build_ui(app: &Application) {
     // Add grid to audio options container
     audio_options_container.append(&audio_grid);
     
+    // Tags and cover art for the extracted audio
+    let tag_editor = tag_editor::create_tag_editor(&settings.borrow());
+    audio_options_container.append(&tag_editor.container);
+    
     // Add pages to the media stack
     media_stack.add_titled(&video_options_container, Some("video"), "Video");
     media_stack.add_titled(&audio_options_container, Some("audio"), "Audio");
     
+    // Reopen the tab that was used last
+    media_stack.set_visible_child_name(&settings.borrow().last_tab);
+    
     // Add stack to media options container
     media_options_container.append(&media_stack);
     
@@ -307,9 +1276,71 @@ pub fn build_ui(app: &Application) {
     quality_card.append(&media_options_container);
     quality_card.append(&format_info);
     
+    // Shown when ffmpeg is missing, see the probe at the end of build_ui
+    let ffmpeg_warning = components::create_status_label(&format!(
+        "ffmpeg not found: video-only qualities can't be merged with audio, and audio downloads, \
+        cover art and embedded subtitles won't work. {}", FFMPEG_HELP));
+    ffmpeg_warning.set_wrap(true);
+    ffmpeg_warning.set_visible(false);
+    quality_card.append(&ffmpeg_warning);
+    
     // Add quality card to left panel
     left_panel.append(&quality_card);
     
+    // Download queue card
+    let queue_card = GtkBox::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(8)
+        .build();
+    
+    queue_card.add_css_class("card");
+    
+    let queue_header = GtkBox::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(8)
+        .build();
+    
+    let queue_title = components::create_section_title("Download Queue");
+    queue_title.set_hexpand(true);
+    
+    let parallel_label = Label::builder()
+        .label("Parallel:")
+        .valign(Align::Center)
+        .build();
+    
+    parallel_label.add_css_class("input-label");
+    
+    // Number of yt-dlp processes the queue runs at once
+    let parallel_spin = gtk4::SpinButton::with_range(1.0, 8.0, 1.0);
+    parallel_spin.set_value(settings.borrow().max_concurrent as f64);
+    parallel_spin.set_valign(Align::Center);
+    
+    queue_header.append(&queue_title);
+    queue_header.append(&parallel_label);
+    queue_header.append(&parallel_spin);
+    
+    // List of queued jobs
+    let queue_list = GtkBox::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(0)
+        .build();
+    
+    let queue_empty_label = components::create_status_label("No queued downloads");
+    queue_list.append(&queue_empty_label);
+    
+    let queue_scroller = gtk4::ScrolledWindow::builder()
+        .hscrollbar_policy(gtk4::PolicyType::Never)
+        .min_content_height(80)
+        .max_content_height(240)
+        .propagate_natural_height(true)
+        .child(&queue_list)
+        .build();
+    
+    queue_card.append(&queue_header);
+    queue_card.append(&queue_scroller);
+    
+    left_panel.append(&queue_card);
+    
     // Right side - info boxes and download controls
     let right_panel = GtkBox::builder()
         .orientation(Orientation::Vertical)
@@ -411,8 +1442,11 @@ pub fn build_ui(app: &Application) {
     let download_title = components::create_section_title("Download Options");
     download_card.append(&download_title);
     
-    // Output path selection row
-    let default_path = get_default_download_path().unwrap_or_else(|_| String::from("."));
+    // Output path selection row, starting in the folder configured for the active tab
+    let default_path = {
+        let settings = settings.borrow();
+        settings.output_dir_for(&settings.last_tab).to_string()
+    };
     
     // Create the label
     let path_label = Label::builder()
@@ -458,11 +1492,22 @@ pub fn build_ui(app: &Application) {
     // Add path selection to download card
     download_card.append(&label_container);
     
+    // File name template for the active tab
+    let template_row = {
+        let settings = settings.borrow();
+        template_row::create_template_row(&settings.template_for(&settings.last_tab))
+    };
+    download_card.append(&template_row.container);
+    
+    // Bandwidth limit for this download only, empty uses the network settings
+    let (rate_limit_box, rate_limit_entry) = components::create_labeled_entry("Speed limit:", "e.g. 2M or 500K", None);
+    rate_limit_entry.set_tooltip_text(Some("Bytes per second for this download, overrides the limit from the preferences"));
+    download_card.append(&rate_limit_box);
+    
     // Path entry clones for various handlers
     let path_entry_for_download = path_entry.clone();
     let path_entry_for_open = path_entry.clone();
     let path_entry_for_browse = path_entry.clone();
-    let path_entry_for_fetch = path_entry.clone();
     let path_entry_for_tab_change = path_entry.clone();
     
     // Download button with action area
@@ -477,6 +1522,10 @@ pub fn build_ui(app: &Application) {
     download_button.set_sensitive(false);
     download_button.set_hexpand(true);
     
+    // Add to Queue button for batching several downloads
+    let queue_button = components::create_button("Add to Queue", "open-folder-button");
+    queue_button.set_hexpand(true);
+    
     // Download status
     let download_status = components::create_status_label("Ready to download");
     
@@ -490,6 +1539,20 @@ pub fn build_ui(app: &Application) {
         
     progress_bar.add_css_class("download-progress");
     
+    // Pause/Cancel controls for the running download (initially hidden)
+    let download_controls = GtkBox::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(6)
+        .homogeneous(true)
+        .visible(false)
+        .build();
+    
+    let pause_button = components::create_button("Pause", "browse-button");
+    let cancel_button = components::create_button("Cancel", "cancel-button");
+    
+    download_controls.append(&pause_button);
+    download_controls.append(&cancel_button);
+    
     // Create Open Folder button (initially hidden)
     let open_folder_button = components::create_button("Open Folder", "open-folder-button");
     open_folder_button.set_visible(false);
@@ -497,8 +1560,10 @@ pub fn build_ui(app: &Application) {
     
     // Add download components to container
     download_button_container.append(&download_button);
+    download_button_container.append(&queue_button);
     download_button_container.append(&download_status);
     download_button_container.append(&progress_bar);
+    download_button_container.append(&download_controls);
     download_button_container.append(&open_folder_button);
     
     // Add download components to download card
@@ -538,356 +1603,118 @@ pub fn build_ui(app: &Application) {
     
     // Create a shared audio bitrate map
     let audio_bitrate_map = Rc::new(RefCell::new(AudioBitrateMap::new()));
-
-    // Connect fetch button
+    
+    // Keep the full list of fetched formats for pairing video-only streams with audio
+    let fetched_formats = Rc::new(RefCell::new(Vec::<FormatOption>::new()));
+    let media_info: Rc<RefCell<Option<MediaInfo>>> = Rc::new(RefCell::new(None));
+    let direct_file: Rc<RefCell<Option<DirectFile>>> = Rc::new(RefCell::new(None));
+    
+    // Control handle of the running download, used by the Pause and Cancel buttons
+    let current_download: Rc<RefCell<Option<DownloadControl>>> = Rc::new(RefCell::new(None));
+    
+    // Download queue, worker threads report back through a channel
+    let (queue_sender, queue_receiver) = glib::MainContext::channel::<QueueEvent>(glib::PRIORITY_DEFAULT);
+    let queue_sender = Mutex::new(queue_sender);
+    let download_queue = DownloadQueue::new(settings.borrow().max_concurrent, Arc::new(move |event| {
+        let _ = queue_sender.lock().unwrap().send(event);
+    }));
+    
+    // Widgets of each queued job, keyed by job id
+    let queue_rows: Rc<RefCell<HashMap<u64, components::QueueRow>>> = Rc::new(RefCell::new(HashMap::new()));
+    
+    let queue_view = QueueView {
+        queue: download_queue.clone(),
+        rows: Rc::clone(&queue_rows),
+        list: queue_list.clone(),
+        empty_label: queue_empty_label.clone(),
+    };
+    
+    let media_selection = MediaSelection {
+        media_stack: media_stack.clone(),
+        quality_combo: quality_combo.clone(),
+        fps_combo: fps_combo.clone(),
+        format_combo: format_combo.clone(),
+        codec_combo: codec_combo.clone(),
+        subtitle_panel: subtitle_panel.clone(),
+        tag_editor: tag_editor.clone(),
+        template_row: template_row.clone(),
+        rate_limit_entry: rate_limit_entry.clone(),
+        checksum_entry: media_preview.checksum_entry.clone(),
+        audio_quality_combo: audio_quality_combo.clone(),
+        audio_format_combo: audio_format_combo.clone(),
+        resolution_map: Rc::clone(&resolution_map),
+        audio_bitrate_map: Rc::clone(&audio_bitrate_map),
+        fetched_formats: Rc::clone(&fetched_formats),
+        media_info: Rc::clone(&media_info),
+        direct_file: Rc::clone(&direct_file),
+    };
+    let media_selection_for_preview = media_selection.clone();
+    
+    // History of finished downloads, "Download Again" puts the same job into the queue
+    let queue_view_clone = queue_view.clone();
+    let history_page = history_page::create_history_page(&window, move |job| {
+        queue_view_clone.enqueue(job);
+    });
+    
+    // Links copied in the browser are offered above the URL entry, or fetched/queued right away
     let url_entry_clone = url_entry.clone();
-    let spinner_clone = spinner.clone();
+    let fetch_button_clone = fetch_button.clone();
+    let queue_view_clone = queue_view.clone();
     let status_label_clone = status_label.clone();
-    let quality_combo_clone = quality_combo.clone();
-    let fps_combo_clone = fps_combo.clone();
-    let format_combo_clone = format_combo.clone();
-    let audio_quality_combo_clone = audio_quality_combo.clone();
-    let audio_format_combo_clone = audio_format_combo.clone();
-    let download_button_clone = download_button.clone();
-    let format_info_clone = format_info.clone();
-    let resolution_map_clone = Rc::clone(&resolution_map);
-    let audio_bitrate_map_clone = Rc::clone(&audio_bitrate_map);
-    let media_stack_for_fetch = media_stack.clone();
-    let window_clone = window.clone();
-    
-    fetch_button.connect_clicked(move |_| {
-        let url = url_entry_clone.text().to_string();
-        
-        if url.is_empty() {
-            components::show_error_dialog(&window_clone, "Invalid URL", "Please enter a URL");
-            status_label_clone.set_text("Please enter a URL");
+    let clipboard_banner = clipboard_watcher::create_clipboard_watcher(&window, &url_entry, Rc::clone(&settings),
+        move |url| {
+            url_entry_clone.set_text(&url);
+            fetch_button_clone.emit_clicked();
+        },
+        move |job| {
+            status_label_clone.set_text(&format!("Added to queue: {}", job.url));
             status_label_clone.set_visible(true);
-            return;
-        }
-        
-        // Show processing UI
-        status_label_clone.set_text("Fetching available formats...");
-        status_label_clone.set_visible(true);
-        spinner_clone.start();
-        
-        // Reset UI and state
-        {
-            let mut map = resolution_map_clone.borrow_mut();
-            map.clear();
-            
-            let mut audio_map = audio_bitrate_map_clone.borrow_mut();
-            audio_map.clear();
-        }
-        
-        quality_combo_clone.remove_all();
-        fps_combo_clone.remove_all();
-        format_combo_clone.remove_all();
-        audio_quality_combo_clone.remove_all();
-        audio_format_combo_clone.remove_all();
-        
-        quality_combo_clone.set_sensitive(false);
-        fps_combo_clone.set_sensitive(false);
-        format_combo_clone.set_sensitive(false);
-        audio_quality_combo_clone.set_sensitive(false);
-        audio_format_combo_clone.set_sensitive(false);
-        download_button_clone.set_sensitive(false);
-        format_info_clone.set_text("Analyzing media source...");
-        
-        // Create a channel to communicate between threads
-        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
-        
-        // Spawn a thread to fetch formats
-        let url_clone = url.clone();
-        thread::spawn(move || {
-            match fetch_available_formats(&url_clone) {
-                Ok(fetched_formats) => {
-                    let _ = sender.send(FormatMessage::Formats(fetched_formats));
-                },
-                Err(e) => {
-                    println!("Error fetching formats: {:?}", e);
-                    let _ = sender.send(FormatMessage::Error);
-                }
-            }
+            queue_view_clone.enqueue(job);
         });
-        
-        // Prepare for the receiver
-        let quality_combo = quality_combo_clone.clone();
-        let fps_combo = fps_combo_clone.clone();
-        let format_combo = format_combo_clone.clone();
-        let audio_quality_combo = audio_quality_combo_clone.clone();
-        let audio_format_combo = audio_format_combo_clone.clone();
-        let status_label = status_label_clone.clone();
-        let format_info = format_info_clone.clone();
-        let download_button = download_button_clone.clone();
-        let spinner = spinner_clone.clone();
-        let resolution_map = Rc::clone(&resolution_map_clone);
-        let audio_bitrate_map = Rc::clone(&audio_bitrate_map_clone);
-        let window_clone = window_clone.clone();
-        let path_entry_clone = path_entry_for_fetch.clone();
-        let media_stack_for_fetch = media_stack_for_fetch.clone();
-        
-        // Handle messages from the thread
-        receiver.attach(None, move |message| {
-            match message {
-                FormatMessage::Formats(formats) => {
-                    // Separate video and audio formats
-                    let video_formats: Vec<_> = formats.iter()
-                        .filter(|format| !format.is_audio_only)
-                        .collect();
-                        
-                    let audio_formats: Vec<_> = formats.iter()
-                        .filter(|format| format.is_audio_only)
-                        .collect();
-                        
-                    println!("Found {} video formats and {} audio formats", 
-                        video_formats.len(), audio_formats.len());
-                    
-                    // Process video formats
-                    {
-                        let mut map = resolution_map.borrow_mut();
-                        map.clear();
-                        
-                        // Organize formats by resolution and FPS
-                        for format in &video_formats {
-                            let resolution = &format.resolution;
-                            let fps = format.fps;
-                            let format_id = &format.format_id;
-                            let ext = &format.ext;
-                            
-                            // Don't skip zero FPS formats - they might be valid
-                            // if fps == 0 {
-                            //     continue;  // Skip formats without FPS information
-                            // }
-                            
-                            // Find if this resolution is already in our map
-                            let pos = map.iter().position(|(res, _)| res == resolution);
-                            
-                            match pos {
-                                Some(idx) => {
-                                    // Check if this FPS and extension combination is already in the list
-                                    let fps_list = &mut map[idx].1;
-                                    if !fps_list.iter().any(|(existing_fps, _, existing_ext)| 
-                                        *existing_fps == fps && existing_ext == ext) {
-                                        fps_list.push((fps, format_id.clone(), ext.clone()));
-                                    }
-                                },
-                                None => {
-                                    // Add this resolution with its first FPS
-                                    map.push((resolution.clone(), vec![(fps, format_id.clone(), ext.clone())]));
-                                }
-                            }
-                        }
-                        
-                        // Sort resolutions by height (descending)
-                        map.sort_by(|(a, _), (b, _)| {
-                            let a_height: u32 = a.split('x').nth(1)
-                                .and_then(|h| h.parse().ok())
-                                .unwrap_or(0);
-                            let b_height: u32 = b.split('x').nth(1)
-                                .and_then(|h| h.parse().ok())
-                                .unwrap_or(0);
-                            
-                            b_height.cmp(&a_height)
-                        });
-                        
-                        // Sort FPS values for each resolution (descending)
-                        for (_, fps_list) in map.iter_mut() {
-                            fps_list.sort_by(|(a, _, _), (b, _, _)| b.cmp(a));
-                        }
-                    }
-                    
-                    // Process audio formats
-                    {
-                        let mut map = audio_bitrate_map.borrow_mut();
-                        map.clear();
-                        
-                        // First try to use dedicated audio formats
-                        if !audio_formats.is_empty() {
-                            println!("Processing {} dedicated audio formats", audio_formats.len());
-                        for format in &audio_formats {
-                            let bitrate = format.abr;
-                            let format_id = &format.format_id;
-                            let ext = &format.ext;
-                            let acodec = &format.acodec;
-                            
-                                println!("Audio format: id={}, codec={}, bitrate={}kbps, ext={}", 
-                                format_id, acodec, bitrate, ext);
-                            
-                                // Skip formats with 0 bitrate after our processing
-                                if bitrate == 0 {
-                                    println!("Skipping audio format with 0 bitrate: {}", format_id);
-                                    continue;
-                                }
-                            
-                            // Find if this bitrate is already in our map
-                                let pos = map.iter().position(|(br, _)| *br == bitrate);
-                            
-                            match pos {
-                                Some(idx) => {
-                                        // Add this format ID to the existing bitrate
-                                        map[idx].1.push((format_id.clone(), ext.clone()));
-                                        println!("Added to existing bitrate {}kbps", bitrate);
-                                },
-                                None => {
-                                    // Add this bitrate with its first format
-                                        map.push((bitrate, vec![(format_id.clone(), ext.clone())]));
-                                        println!("Added new bitrate {}kbps", bitrate);
-                                }
-                            }
-                        }
-                        } else {
-                            // No audio-only formats found, extract audio info from video formats
-                            println!("No audio-only formats found, looking for audio in video formats");
-                            
-                            // Collect all non-zero audio bitrates from video formats
-                            let mut audio_bitrates = HashSet::new();
-                            for format in &video_formats {
-                                if format.acodec != "none" {
-                                    // Only use non-zero bitrates
-                                    if format.abr > 0 {
-                                        audio_bitrates.insert(format.abr);
-                                        println!("Found audio in video format: id={}, bitrate={}kbps, codec={}", 
-                                            format.format_id, format.abr, format.acodec);
-                                        } else {
-                                        println!("Skipping zero bitrate audio in video format: id={}, codec={}", 
-                                            format.format_id, format.acodec);
-                                    }
-                                }
-                            }
-                            
-                            println!("Audio bitrates found:");
-                            for bitrate in &audio_bitrates {
-                                println!("  - {} kbps", bitrate);
-                                
-                                // Find all format_ids that provide this audio bitrate
-                                let format_pairs: Vec<(String, String)> = video_formats.iter()
-                                    .filter(|f| f.abr == *bitrate && f.acodec != "none")
-                                    .map(|f| (f.format_id.clone(), f.ext.clone()))
-                                    .collect();
-                                
-                                map.push((*bitrate, format_pairs));
-                            }
-                        }
-                        
-                        // Sort audio bitrates (descending)
-                        map.sort_by(|(a, _), (b, _)| b.cmp(a));
-                    }
-                    
-                    // Populate the resolution combo box
-                    {
-                        let map = resolution_map.borrow();
-                        
-                        if map.is_empty() {
-                            status_label.set_text("No suitable video formats found");
-                            format_info.set_text("Try a different URL or check if the media source is valid");
-                        } else {
-                            for (i, (resolution, _)) in map.iter().enumerate() {
-                                quality_combo.append(Some(&i.to_string()), resolution);
-                            }
-                            
-                            // If we have resolutions, activate the first one and populate FPS
-                            quality_combo.set_sensitive(true);
-                            
-                            // Set the first resolution as active
-                            quality_combo.set_active(Some(0));
-                        }
-                    }
-                    
-                    // Populate the audio bitrate combo box
-                    {
-                        let audio_map = audio_bitrate_map.borrow();
-                        
-                        if audio_map.is_empty() {
-                            println!("No suitable audio formats found");
-                        } else {
-                            for (i, (bitrate, _)) in audio_map.iter().enumerate() {
-                                audio_quality_combo.append(Some(&i.to_string()), &format!("{} kbps", bitrate));
-                            }
-                            
-                            // If we have bitrates, activate the first one and populate formats
-                            audio_quality_combo.set_sensitive(true);
-                            
-                            // Set the first bitrate as active
-                            audio_quality_combo.set_active(Some(0));
-                            
-                            // Populate audio format options (m4a, mp3, etc.)
-                            audio_format_combo.remove_all();
-                            
-                            // First collect the available extensions from the formats
-                            let mut available_extensions = HashSet::new();
-                            for format_list in audio_map.iter() {
-                                for (_, ext) in &format_list.1 {
-                                    available_extensions.insert(ext.clone().to_lowercase());
-                                }
-                            }
-                            
-                            println!("Available audio extensions: {:?}", available_extensions);
-                            
-                            // Common audio formats to check for
-                            let preferred_audio_extensions = ["m4a", "mp3", "opus", "aac", "wav", "ogg"];
-                            
-                            // Add only the available formats in the preferred order
-                            let mut found_formats = 0;
-                            for ext in preferred_audio_extensions.iter() {
-                                if available_extensions.contains(&ext.to_string()) {
-                                    audio_format_combo.append(Some(ext), &ext.to_uppercase());
-                                    found_formats += 1;
-                                }
-                            }
-                            
-                            // If we didn't find any of the preferred formats, add all available formats
-                            if found_formats == 0 {
-                                // Convert HashSet to Vec for predictable iteration
-                                let exts: Vec<String> = available_extensions.into_iter().collect();
-                                for ext_str in exts {
-                                    audio_format_combo.append(Some(&ext_str), &ext_str.to_uppercase());
-                                }
-                            }
-                        }
-                    }
-                    
-                    status_label.set_text("Formats fetched successfully");
-                    format_info.set_text("Select your preferred quality, FPS and file format");
-                    
-                    // Update the download path based on the active tab
-                    let default_path = get_default_download_path().unwrap_or_else(|_| String::from("."));
-                    let active_tab = media_stack_for_fetch.visible_child_name().unwrap_or_else(|| "video".into());
-                    
-                    // Get current path
-                    let current_path = path_entry_clone.text().to_string();
-                    
-                    // Only change the path if it contains "HyprDownloader" (meaning it's using our default path structure)
-                    // or if it's empty/default
-                    let should_update_path = current_path.contains("HyprDownloader") || 
-                                             current_path.is_empty() || 
-                                             current_path == "." || 
-                                             current_path == default_path;
-                    
-                    if should_update_path {
-                        if active_tab == "video" {
-                            // Set path to video subfolder
-                            let video_path = format!("{}/video", default_path);
-                            path_entry_clone.set_text(&video_path);
-                        } else if active_tab == "audio" {
-                            // Set path to audio subfolder
-                            let audio_path = format!("{}/audio", default_path);
-                            path_entry_clone.set_text(&audio_path);
-                        }
-                    }
-                },
-                FormatMessage::Error => {
-                    status_label.set_text("Error fetching formats");
-                    format_info.set_text("Check your URL or internet connection");
-                    components::show_error_dialog(&window_clone, "URL Error", 
-                        "Failed to fetch video formats. Please check if the URL is valid and your internet connection is working.");
-                }
-            }
-            
-            // Hide processing UI once done
-            spinner.stop();
-            glib::Continue(true)
+    url_card.insert_child_after(&clipboard_banner, Some(&url_section_title));
+    
+    // Notification buttons act on the queue through application actions
+    let notifier = notifications::Notifier::new(app, &window, Rc::clone(&settings));
+    let download_queue_clone = download_queue.clone();
+    let queue_view_clone = queue_view.clone();
+    notifications::install_actions(app, &window,
+        move |id| download_queue_clone.retry(id),
+        move |job| queue_view_clone.enqueue(job));
+    
+    // URLs from the command line, "Open With", a second launch or drag-and-drop
+    let url_entry_clone = url_entry.clone();
+    let fetch_button_clone = fetch_button.clone();
+    let queue_view_clone = queue_view.clone();
+    let status_label_clone = status_label.clone();
+    let settings_clone = Rc::clone(&settings);
+    launch::install_url_actions(app,
+        move |url| {
+            url_entry_clone.set_text(&url);
+            fetch_button_clone.emit_clicked();
+        },
+        move |url| {
+            let job = clipboard_watcher::default_job(&url, &settings_clone.borrow());
+            status_label_clone.set_text(&format!("Added to queue: {}", job.url));
+            status_label_clone.set_visible(true);
+            queue_view_clone.enqueue(job);
         });
-    });
+    launch::accept_dropped_urls(&window);
+
+    // Connect fetch button
+    let fetch_view = FetchView {
+        window: window.clone(),
+        url_entry: url_entry.clone(),
+        path_entry: path_entry.clone(),
+        spinner: spinner.clone(),
+        status_label: status_label.clone(),
+        format_info: format_info.clone(),
+        download_button: download_button.clone(),
+        media_preview: media_preview.clone(),
+        selection: media_selection.clone(),
+        queue_view: queue_view.clone(),
+        settings: Rc::clone(&settings),
+    };
+    fetch_button.connect_clicked(move |_| fetch_view.fetch());
 
     // Connect quality combo box to update FPS options
     let fps_combo_clone = fps_combo.clone();
@@ -895,6 +1722,7 @@ pub fn build_ui(app: &Application) {
     let download_button_clone = download_button.clone();
     let format_info_clone = format_info.clone();
     let resolution_map_clone = Rc::clone(&resolution_map);
+    let settings_clone = Rc::clone(&settings);
     
     quality_combo.connect_changed(move |combo| {
         // Clear the FPS combo
@@ -931,9 +1759,12 @@ pub fn build_ui(app: &Application) {
                         }
                     }
                     
-                    // Make the FPS dropdown active
+                    // Make the FPS dropdown active, preferring the remembered FPS
+                    let preferred_fps = settings_clone.borrow().preferred_fps.map(|fps| fps.to_string());
+                    if !preferred_fps.is_some_and(|fps| fps_combo_clone.set_active_id(Some(&fps))) {
                         fps_combo_clone.set_active(Some(0));
-                        fps_combo_clone.set_sensitive(true);
+                    }
+                    fps_combo_clone.set_sensitive(true);
                     
                     // Update format info text
                     let resolution = &map[idx].0;
@@ -948,7 +1779,9 @@ pub fn build_ui(app: &Application) {
     let download_button_clone = download_button.clone();
     let format_info_clone = format_info.clone();
     let resolution_map_clone = Rc::clone(&resolution_map);
+    let fetched_formats_clone = Rc::clone(&fetched_formats);
     let quality_combo_clone = quality_combo.clone();
+    let settings_clone = Rc::clone(&settings);
     
     fps_combo.connect_changed(move |combo| {
         // Clear the format type combo
@@ -993,7 +1826,7 @@ pub fn build_ui(app: &Application) {
                         
                         // Add each unique format type to the combo box
                         let mut added_formats = Vec::new();
-                        let mut format_index = 0;
+                        let mut option_ids = Vec::new();
                         
                         // Convert HashSet to Vec for sorting
                         let mut ext_vec: Vec<String> = unique_extensions.into_iter().collect();
@@ -1002,19 +1835,61 @@ pub fn build_ui(app: &Application) {
                         // Make sure we're actually adding all formats to the dropdown
                         println!("Adding {} format options to dropdown", ext_vec.len());
                         
+                        let all_formats = fetched_formats_clone.borrow();
+                        
                         for ext in ext_vec {
-                            println!("Adding format option: {}", ext);
-                            // Make sure we're appending to the format_combo_clone
-                            format_combo_clone.append(Some(&format_index.to_string()), &ext.to_uppercase());
+                            // Streams with this extension; to_queue_job picks the first one that fits the option
+                            let streams: Vec<&FormatOption> = available_formats.iter()
+                                .filter(|(_, format_ext)| *format_ext == ext)
+                                .filter_map(|(format_id, _)| all_formats.iter().find(|f| &f.format_id == format_id))
+                                .collect();
+                            
+                            // Check whether any stream with this extension already carries audio
+                            let has_audio = streams.iter().any(|f| !f.is_video_only());
+                            
+                            // All codecs this format comes in, the preferred one first
+                            let mut codecs: Vec<String> = Vec::new();
+                            for codec in streams.iter().filter(|f| !has_audio || !f.is_video_only()).map(|f| f.codec_label()) {
+                                if !codecs.contains(&codec) {
+                                    codecs.push(codec);
+                                }
+                            }
+                            let codec = codecs.join("/");
+                            
+                            if has_audio {
+                                println!("Adding format option: {}", ext);
+                                let size = streams.iter().find(|f| !f.is_video_only()).and_then(|f| f.estimated_size());
+                                format_combo_clone.append(
+                                    Some(&ext),
+                                    &format!("{} · {}{}", ext.to_uppercase(), codec, size_suffix(size))
+                                );
+                                option_ids.push(ext.clone());
+                            } else {
+                                // Video-only stream: offer each merge container, id is "ext:container"
+                                for container in merge_containers_for(&ext) {
+                                    println!("Adding merged format option: {} -> {}", ext, container);
+                                    let size = streams.first().and_then(|f| merged_size(f, &all_formats, container));
+                                    format_combo_clone.append(
+                                        Some(&format!("{}:{}", ext, container)),
+                                        &format!("{} + audio → {} · {}{}", ext.to_uppercase(), container.to_uppercase(), codec, size_suffix(size))
+                                    );
+                                    option_ids.push(format!("{}:{}", ext, container));
+                                }
+                            }
                             added_formats.push(ext);
-                            format_index += 1;
                         }
                         
                         println!("Added formats: {:?}", added_formats);
+                        
+                        // Prefer the option whose output container matches the remembered one
+                        let preferred_container = settings_clone.borrow().preferred_container.clone();
+                        let preferred_index = option_ids.iter()
+                            .position(|id| id.rsplit(':').next() == Some(preferred_container.as_str()))
+                            .unwrap_or(0);
 
-                        // Set the first format type as active if available
+                        // Set the preferred format type as active if available
                         if !added_formats.is_empty() {
-                            format_combo_clone.set_active(Some(0));
+                            format_combo_clone.set_active(Some(preferred_index as u32));
                             format_combo_clone.set_sensitive(true);
                             download_button_clone.set_sensitive(true);
                             format_info_clone.set_text(&format!("Selected: {} at {} fps", resolution, selected_fps));
@@ -1029,11 +1904,85 @@ pub fn build_ui(app: &Application) {
         }
     });
 
+    // Connect format combo box to update codec options
+    let codec_combo_clone = codec_combo.clone();
+    let quality_combo_clone = quality_combo.clone();
+    let fps_combo_clone = fps_combo.clone();
+    let resolution_map_clone = Rc::clone(&resolution_map);
+    let fetched_formats_clone = Rc::clone(&fetched_formats);
+    
+    format_combo.connect_changed(move |combo| {
+        codec_combo_clone.remove_all();
+        codec_combo_clone.set_sensitive(false);
+        
+        let Some(format_choice) = combo.active_id() else {
+            return;
+        };
+        let Some(quality_idx) = quality_combo_clone.active_id().and_then(|id| id.parse::<usize>().ok()) else {
+            return;
+        };
+        let selected_fps = fps_combo_clone.active_id()
+            .and_then(|id| id.parse::<u32>().ok())
+            .unwrap_or(0);
+        
+        // The id is "ext" or "ext:container" for merged streams
+        let (ext, container) = match format_choice.split_once(':') {
+            Some((ext, container)) => (ext.to_string(), Some(container.to_string())),
+            None => (format_choice.to_string(), None),
+        };
+        
+        let map = resolution_map_clone.borrow();
+        let Some((_, fps_values)) = map.get(quality_idx) else {
+            return;
+        };
+        
+        // One entry per codec in preference order, the map is already sorted that way
+        let all_formats = fetched_formats_clone.borrow();
+        let mut codecs = Vec::new();
+        for (_, format_id, _) in fps_values.iter()
+            .filter(|(fps, _, format_ext)| (*fps == selected_fps || selected_fps == 0) && *format_ext == ext) {
+            let Some(stream) = all_formats.iter().find(|f| &f.format_id == format_id) else {
+                continue;
+            };
+            // Same rule as to_queue_job: a plain format needs a stream with audio
+            if container.is_none() && stream.is_video_only() {
+                continue;
+            }
+            let codec = stream.codec_label();
+            if codecs.contains(&codec) {
+                continue;
+            }
+            
+            let size = match &container {
+                Some(container) => merged_size(stream, &all_formats, container),
+                None => stream.estimated_size(),
+            };
+            codec_combo_clone.append(Some(&codec), &format!("{}{}", codec, size_suffix(size)));
+            codecs.push(codec);
+        }
+        
+        if !codecs.is_empty() {
+            codec_combo_clone.set_active(Some(0));
+            codec_combo_clone.set_sensitive(codecs.len() > 1);
+        }
+    });
+    
+    // Show how well the selected codec plays back
+    let format_info_clone = format_info.clone();
+    codec_combo.connect_changed(move |combo| {
+        if let Some(codec) = combo.active_id() {
+            let hint = codec_compatibility_hint(&codec);
+            combo.set_tooltip_text(Some(hint));
+            format_info_clone.set_text(hint);
+        }
+    });
+    
     // Connect audio quality combo box to update audio format options
     let audio_format_combo_clone = audio_format_combo.clone();
     let download_button_clone = download_button.clone();
     let format_info_clone = format_info.clone();
     let audio_bitrate_map_clone = Rc::clone(&audio_bitrate_map);
+    let settings_clone = Rc::clone(&settings);
     
     audio_quality_combo.connect_changed(move |combo| {
         // Clear the audio format combo
@@ -1066,9 +2015,13 @@ pub fn build_ui(app: &Application) {
                         audio_format_combo_clone.append(Some(&i.to_string()), &ext.to_uppercase());
                     }
                     
-                    // Set the first format as active if available
+                    // Set the preferred format as active if available, otherwise the first one
                     if !ext_vec.is_empty() {
-                        audio_format_combo_clone.set_active(Some(0));
+                        let preferred_format = settings_clone.borrow().audio_format.clone();
+                        let preferred_index = ext_vec.iter()
+                            .position(|ext| ext.eq_ignore_ascii_case(&preferred_format))
+                            .unwrap_or(0);
+                        audio_format_combo_clone.set_active(Some(preferred_index as u32));
                         audio_format_combo_clone.set_sensitive(true);
                         download_button_clone.set_sensitive(true);
                         format_info_clone.set_text(&format!("Selected audio: {} kbps", bitrate));
@@ -1084,17 +2037,16 @@ pub fn build_ui(app: &Application) {
     // Connect the download button to the download function
     let url_entry_clone = url_entry.clone();
     let output_entry_clone = path_entry_for_download.clone();
-    let quality_combo_clone = quality_combo.clone();
-    let fps_combo_clone = fps_combo.clone();
-    let format_combo_clone = format_combo.clone();
-    let audio_quality_combo_clone = audio_quality_combo.clone();
-    let audio_format_combo_clone = audio_format_combo.clone();
     let download_status_clone = download_status.clone();
     let progress_bar_clone = progress_bar.clone();
     let open_folder_button_clone = open_folder_button.clone();
-    let resolution_map_clone = Rc::clone(&resolution_map);
-    let audio_bitrate_map_clone = Rc::clone(&audio_bitrate_map);
-    let media_stack_for_download = media_stack.clone();
+    let download_controls_clone = download_controls.clone();
+    let pause_button_clone = pause_button.clone();
+    let current_download_clone = Rc::clone(&current_download);
+    let media_selection_clone = media_selection.clone();
+    let settings_clone = Rc::clone(&settings);
+    let history_page_clone = history_page.clone();
+    let notifier_clone = notifier.clone();
     let window_clone = window.clone();
     
     download_button.connect_clicked(move |button| {
@@ -1113,572 +2065,259 @@ pub fn build_ui(app: &Application) {
             return;
         }
 
+        // Resolve the dropdown choices to a job before touching the progress UI
+        let job = match media_selection_clone.to_queue_job(&url, &output_path) {
+            Ok(job) => job,
+            Err(message) => {
+                download_status_clone.set_text(&message);
+                components::show_error_dialog(&window_clone, "Format Error", &message);
+                return;
+            }
+        };
+        
+        // Merging, extraction and embedding need ffmpeg, say so before anything starts
+        if let Err(message) = check_ffmpeg(&job) {
+            download_status_clone.set_text("ffmpeg not found");
+            components::show_error_dialog(&window_clone, "ffmpeg Required", &message);
+            return;
+        }
+        
+        // Remember the chosen quality for next time
+        {
+            let mut settings = settings_clone.borrow_mut();
+            media_selection_clone.remember_preferences(&mut settings);
+            if let Err(e) = settings.save() {
+                println!("Failed to save settings: {}", e);
+            }
+        }
+
         // Reset and show progress bar, hide open folder button
         progress_bar_clone.set_fraction(0.0);
         progress_bar_clone.set_text(Some("0%"));
         progress_bar_clone.set_visible(true);
         open_folder_button_clone.set_visible(false);
+        download_status_clone.set_text("Starting download...");
         
         // Disable the download button during download
         button.set_sensitive(false);
-
-        // Get the active tab
-        let active_tab = media_stack_for_download.visible_child_name().unwrap_or_else(|| "video".into());
         
-        if active_tab == "video" {
-            // Download video
-            // Get selected quality index
-            if let Some(quality_idx_str) = quality_combo_clone.active_id() {
-                if let Some(quality_idx) = quality_idx_str.parse::<usize>().ok() {
-                    // Get FPS from the active text
-                    if let Some(fps_text) = fps_combo_clone.active_text() {
-                        // Extract the numeric part from "XX fps"
-                        let selected_fps = fps_text.split_whitespace()
-                            .next()
-                            .and_then(|s| s.parse::<u32>().ok())
-                            .unwrap_or(0);
+        // The control handle of this download is used by the Pause and Cancel buttons
+        let download = DownloadJob::new(job.clone());
+        let control = download.control().clone();
+        *current_download_clone.borrow_mut() = Some(control.clone());
+        pause_button_clone.set_label("Pause");
+        
+        println!("Starting download: {}", job.label);
+        
+        // Create a typed channel for events from the worker thread
+        let (sender, receiver) = glib::MainContext::channel::<DownloadEvent>(glib::PRIORITY_DEFAULT);
+        
+        // Handle download events in the UI thread
+        let download_status_clone = download_status_clone.clone();
+        let progress_bar_clone = progress_bar_clone.clone();
+        let open_folder_button_clone = open_folder_button_clone.clone();
+        let download_controls_clone = download_controls_clone.clone();
+        let history_page = history_page_clone.clone();
+        let notifier = notifier_clone.clone();
+        let button = button.clone();
+        let window = window_clone.clone();
+        let last_progress = Rc::new(RefCell::new(-1.0)); // Track last progress to avoid duplicates
+        
+        receiver.attach(None, move |event| {
+            match event {
+                DownloadEvent::Started => {
+                    // Reset the progress bar state
+                    progress_bar_clone.set_fraction(0.0);
+                    progress_bar_clone.set_text(Some("0.0%"));
+                    download_status_clone.set_text("Download started");
+                    download_controls_clone.set_visible(true);
+                    *last_progress.borrow_mut() = 0.0;
+                },
+                DownloadEvent::Progress(progress) => {
+                    // Only update if progress has changed significantly (at least 0.5%)
+                    let progress_diff = (progress.overall_percent - *last_progress.borrow()) * 100.0;
+                    if progress_diff >= 0.5 || progress.overall_percent >= 0.99 {
+                        progress_bar_clone.set_fraction(progress.overall_percent);
+                        progress_bar_clone.set_text(Some(&format!("{:.1}%", progress.overall_percent * 100.0)));
+                        download_status_clone.set_text(&progress.status_message);
                         
-                        // Get format type
-                        if let Some(selected_format) = format_combo_clone.active_text() {
-                            let map = resolution_map_clone.borrow();
-                            if quality_idx < map.len() {
-                                let resolution = &map[quality_idx].0;
-                                let fps_values = &map[quality_idx].1;
-                                
-                                // Convert to lowercase for case-insensitive comparison
-                                let selected_format_lower = selected_format.to_string().to_lowercase();
-                                
-                                println!("Selected for download: resolution={}, fps={}, format={}",
-                                    resolution, selected_fps, selected_format_lower);
-                                
-                                // Find the format_id that matches both FPS and format type
-                                let matching_formats: Vec<_> = fps_values.iter()
-                                    .filter(|(fps, _, ext)| {
-                                        // If the selected FPS is 0, we match all formats with the same extension
-                                        // Otherwise, we match formats with the exact FPS
-                                        (*fps == selected_fps || selected_fps == 0) && 
-                                        ext.to_lowercase() == selected_format_lower
-                                    })
-                                    .collect();
-                                
-                                println!("Found {} matching formats for download", matching_formats.len());
-                                
-                                if !matching_formats.is_empty() {
-                                    let format_id = matching_formats[0].1.clone();
-                                    let resolution_str = resolution.clone();
-                                    
-                                    download_status_clone.set_text(
-                                        &format!("Downloading {} @ {} fps ({})", 
-                                            resolution, selected_fps, selected_format.to_string().to_uppercase())
-                                    );
-                                    
-                                    // Create a clone of the output path for the worker thread
-                                    let output_path_clone = output_path.clone();
-                                    let url_clone = url.clone();
-                                    let download_status_clone2 = download_status_clone.clone();
-                                    let progress_bar_clone2 = progress_bar_clone.clone();
-                                    let open_folder_button_clone2 = open_folder_button_clone.clone();
-                                    let button_clone = button.clone();
-                                    let window_clone2 = window_clone.clone();
-                                    
-                                    // Create a channel for communication between threads
-                                    let (sender, receiver) = glib::MainContext::channel::<String>(glib::PRIORITY_DEFAULT);
-                                    
-                                    // Handle progress updates in the UI thread
-                                    let last_progress = Rc::new(RefCell::new(-1.0)); // Track last progress to avoid duplicates
-                                    
-                                    receiver.attach(None, move |msg: String| {
-                                        if msg == "start" {
-                                            // Reset the progress bar state
-                                            progress_bar_clone2.set_fraction(0.0);
-                                            progress_bar_clone2.set_text(Some("0.0%"));
-                                            download_status_clone2.set_text("Download started");
-                                            *last_progress.borrow_mut() = 0.0;
-                                        } else if msg.starts_with("progress:") {
-                                            // New format: "progress:0.123:Status message text"
-                                            let parts: Vec<&str> = msg.splitn(3, ':').collect();
-                                            
-                                            if parts.len() >= 2 {
-                                                // Parse the progress percentage
-                                                let progress = parts[1].parse::<f64>().unwrap_or(0.0);
-                                                
-                                                // Only update if progress has changed significantly (at least 0.5%)
-                                                let progress_diff = (progress - *last_progress.borrow()) * 100.0;
-                                                if progress_diff >= 0.5 || progress >= 0.99 {
-                                                    println!("Updating progress bar: {:.1}%", progress * 100.0);
-                                                    
-                                                    // Update progress bar
-                                                    progress_bar_clone2.set_fraction(progress);
-                                                    progress_bar_clone2.set_text(Some(&format!("{:.1}%", progress * 100.0)));
-                                                    
-                                                    // Update status with the provided message if we have one
-                                                    if parts.len() >= 3 {
-                                                        download_status_clone2.set_text(parts[2]);
-                                                    }
-                                                    
-                                                    // Update last progress
-                                                    *last_progress.borrow_mut() = progress;
-                                                }
-                                            }
-                                        } else if msg.starts_with("complete:success") {
-                                            // Ensure the progress bar shows 100%
-                                            progress_bar_clone2.set_fraction(1.0);
-                                            progress_bar_clone2.set_text(Some("100.0%"));
-                                            download_status_clone2.set_text("Download complete!");
-                                            
-                                            // Show Open Folder button
-                                            open_folder_button_clone2.set_visible(true);
-                                            
-                                            // Re-enable download button
-                                            button_clone.set_sensitive(true);
-                                        } else if msg.starts_with("complete:error:") {
-                                            // Download failed
-                                            let error_msg = msg.strip_prefix("complete:error:")
-                                                .unwrap_or("Unknown error");
-                                            
-                                            download_status_clone2.set_text(&format!("Download failed: {}", error_msg));
-                                            progress_bar_clone2.set_visible(false);
-                                            
-                                            // Re-enable download button
-                                            button_clone.set_sensitive(true);
-                                            
-                                            components::show_error_dialog(&window_clone2, "Download Error", 
-                                                &format!("Download failed: {}", error_msg));
-                                        } else if msg.starts_with("error:") {
-                                            // Error starting download
-                                            let error_msg = msg.strip_prefix("error:")
-                                                .unwrap_or("Unknown error");
-                                                
-                                            download_status_clone2.set_text(&format!("Error starting download: {}", error_msg));
-                                            progress_bar_clone2.set_visible(false);
-                                            
-                                            // Re-enable download button
-                                            button_clone.set_sensitive(true);
-                                            
-                                            components::show_error_dialog(&window_clone2, "Download Error", 
-                                                &format!("Error starting download: {}", error_msg));
-                                        }
-                                        
-                                        glib::Continue(true)
-                                    });
-                                    
-                                    // Spawn a thread to monitor the download progress
-                                    let sender_clone = sender.clone();
-                                    thread::spawn(move || {
-                                        // Start the download process
-                                        match crate::downloader::download_media_with_format(&url_clone, &output_path_clone, &format_id) {
-                                            Ok(mut child) => {
-                                                // Send initial start message
-                                                sender_clone.send("start".to_string()).unwrap();
-                                                
-                                                // Get stdout and stderr from the child process
-                                                let stdout = child.stdout.take().expect("Failed to capture stdout");
-                                                let stderr = child.stderr.take().expect("Failed to capture stderr");
-                                                
-                                                // Create a buffer reader for stdout and stderr
-                                                let stdout_reader = std::io::BufReader::new(stdout);
-                                                let stderr_reader = std::io::BufReader::new(stderr);
-                                                
-                                                // Create a thread to read stdout and track progress
-                                                let sender_stdout = sender_clone.clone();
-                                                let stdout_thread = thread::spawn(move || {
-                                                    let mut reader = stdout_reader.lines();
-                                                    let mut progress_state = crate::downloader::ProgressState::default();
-                                                    
-                                                    while let Some(line_result) = reader.next() {
-                                                        match line_result {
-                                                            Ok(line) => {
-                                                                // Only log important lines
-                                                                if line.contains("%") || line.contains("download") || 
-                                                                   line.contains("ffmpeg") || line.contains("Merger") {
-                                                                    println!("STDOUT: {}", line);
-                                                                }
-                                                                
-                                                                // Update progress state based on this line
-                                                                if crate::downloader::update_progress_state(&line, &mut progress_state) {
-                                                                    // Send progress update with percentage and message
-                                                                    sender_stdout.send(format!(
-                                                                        "progress:{:.3}:{}", 
-                                                                        progress_state.overall_percent,
-                                                                        progress_state.status_message
-                                                                    )).unwrap();
-                                                                }
-                                                            },
-                                                            Err(e) => {
-                                                                println!("Error reading stdout: {}", e);
-                                                                break;
-                                                            }
-                                                        }
-                                                    }
-                                                });
-                                                
-                                                // Create a thread to read stderr and track progress
-                                                let sender_stderr = sender_clone.clone();
-                                                let stderr_thread = thread::spawn(move || {
-                                                    let mut reader = stderr_reader.lines();
-                                                    let mut progress_state = crate::downloader::ProgressState::default();
-                                                    
-                                                    while let Some(line_result) = reader.next() {
-                                                        match line_result {
-                                                            Ok(line) => {
-                                                                // Only log important lines
-                                                                if line.contains("%") || line.contains("download") || 
-                                                                   line.contains("ffmpeg") || line.contains("Merger") {
-                                                                    println!("STDERR: {}", line);
-                                                                }
-                                                                
-                                                                // Update progress state based on this line
-                                                                if crate::downloader::update_progress_state(&line, &mut progress_state) {
-                                                                    // Send progress update with percentage and message
-                                                                    sender_stderr.send(format!(
-                                                                        "progress:{:.3}:{}", 
-                                                                        progress_state.overall_percent,
-                                                                        progress_state.status_message
-                                                                    )).unwrap();
-                                                                }
-                                                            },
-                                                            Err(e) => {
-                                                                println!("Error reading stderr: {}", e);
-                                                                break;
-                                                            }
-                                                        }
-                                                    }
-                                                });
-                                                
-                                                // Wait for the child process to complete
-                                                match child.wait() {
-                                                    Ok(status) => {
-                                                        if status.success() {
-                                                            // Ensure progress is 100% when truly complete
-                                                            sender_clone.send("progress:1.0:Download complete!".to_string()).unwrap();
-                                                            // Slight delay to let UI update before sending completion message
-                                                            thread::sleep(std::time::Duration::from_millis(200));
-                                                            sender_clone.send("complete:success".to_string()).unwrap();
-                                                        } else {
-                                                            sender_clone.send(format!("complete:error:{}", status)).unwrap();
-                                                        }
-                                                    },
-                                                    Err(e) => {
-                                                        sender_clone.send(format!("complete:error:{}", e)).unwrap();
-                                                    }
-                                                }
-                                                
-                                                // Wait for stdout and stderr threads to complete
-                                                let _ = stdout_thread.join();
-                                                let _ = stderr_thread.join();
-                                            },
-                                            Err(e) => {
-                                                sender_clone.send(format!("error:{}", e)).unwrap();
-                                            }
-                                        }
-                                    });
-                                } else {
-                                    download_status_clone.set_text("No matching format found");
-                                    progress_bar_clone.set_visible(false);
-                                    
-                                    // Re-enable download button
-                                    button.set_sensitive(true);
-                                    
-                                    components::show_error_dialog(&window_clone, "Format Error", 
-                                        "No matching format found for the selected quality, FPS and format type.");
-                                }
-                            }
-                        }
+                        *last_progress.borrow_mut() = progress.overall_percent;
                     }
-                }
-            }
-        } else if active_tab == "audio" {
-            // Similar approach for audio downloads
-            // Get selected audio quality index
-            if let Some(quality_idx_str) = audio_quality_combo_clone.active_id() {
-                if let Some(quality_idx) = quality_idx_str.parse::<usize>().ok() {
-                    // Get format type
-                    if let Some(selected_format) = audio_format_combo_clone.active_text() {
-                        let audio_map = audio_bitrate_map_clone.borrow();
-                        if quality_idx < audio_map.len() {
-                            let bitrate = audio_map[quality_idx].0;
-                            let format_values = &audio_map[quality_idx].1;
-                            
-                            // Convert to lowercase for case-insensitive comparison
-                            let selected_format_lower = selected_format.to_string().to_lowercase();
-                            
-                            println!("Selected for audio download: bitrate={}kbps, format={}",
-                                bitrate, selected_format_lower);
-                            
-                            // Find the format_id that matches the format type
-                            let matching_formats: Vec<_> = format_values.iter()
-                                .filter(|(_, ext)| ext.to_lowercase() == selected_format_lower)
-                                .collect();
-                            
-                            println!("Found {} matching audio formats for download", matching_formats.len());
-                            
-                            if !matching_formats.is_empty() {
-                                let format_id = matching_formats[0].0.clone();
-                                
-                                download_status_clone.set_text(
-                                    &format!("Downloading audio @ {} kbps ({})", 
-                                        bitrate, selected_format.to_string().to_uppercase())
-                                );
-                                
-                                // Use specific audio format for download
-                                let target_format = match selected_format_lower.as_str() {
-                                    "m4a" => "m4a",
-                                    "mp3" => "mp3",
-                                    "opus" => "opus",
-                                    "webm" => "webm",
-                                    _ => "best" // Default to best
-                                };
-                                
-                                // Create a clone of the output path for the worker thread
-                                let output_path_clone = output_path.clone();
-                                let url_clone = url.clone();
-                                let target_format = target_format.to_string();
-                                let download_status_clone2 = download_status_clone.clone();
-                                let progress_bar_clone2 = progress_bar_clone.clone();
-                                let open_folder_button_clone2 = open_folder_button_clone.clone();
-                                let button_clone = button.clone();
-                                let window_clone2 = window_clone.clone();
-                                
-                                // Create a channel for communication between threads
-                                let (sender, receiver) = glib::MainContext::channel::<String>(glib::PRIORITY_DEFAULT);
-                                
-                                // Handle progress updates in the UI thread
-                                let last_progress = Rc::new(RefCell::new(-1.0)); // Track last progress to avoid duplicates
-                                
-                                receiver.attach(None, move |msg: String| {
-                                    if msg == "start" {
-                                        // Reset the progress bar state
-                                        progress_bar_clone2.set_fraction(0.0);
-                                        progress_bar_clone2.set_text(Some("0.0%"));
-                                        download_status_clone2.set_text("Audio download started");
-                                        *last_progress.borrow_mut() = 0.0;
-                                    } else if msg.starts_with("progress:") {
-                                        // New format: "progress:0.123:Status message text"
-                                        let parts: Vec<&str> = msg.splitn(3, ':').collect();
-                                        
-                                        if parts.len() >= 2 {
-                                            // Parse the progress percentage
-                                            let progress = parts[1].parse::<f64>().unwrap_or(0.0);
-                                            
-                                            // Only update if progress has changed significantly (at least 0.5%)
-                                            let progress_diff = (progress - *last_progress.borrow()) * 100.0;
-                                            if progress_diff >= 0.5 || progress >= 0.99 {
-                                                println!("Updating progress bar: {:.1}%", progress * 100.0);
-                                                
-                                                // Update progress bar
-                                                progress_bar_clone2.set_fraction(progress);
-                                                progress_bar_clone2.set_text(Some(&format!("{:.1}%", progress * 100.0)));
-                                                
-                                                // Update status with the provided message if we have one
-                                                if parts.len() >= 3 {
-                                                    download_status_clone2.set_text(parts[2]);
-                                                }
-                                                
-                                                // Update last progress
-                                                *last_progress.borrow_mut() = progress;
-                                            }
-                                        }
-                                    } else if msg.starts_with("complete:success") {
-                                        // Ensure the progress bar shows 100%
-                                        progress_bar_clone2.set_fraction(1.0);
-                                        progress_bar_clone2.set_text(Some("100.0%"));
-                                        download_status_clone2.set_text("Audio download complete!");
-                                        
-                                        // Show Open Folder button
-                                        open_folder_button_clone2.set_visible(true);
-                                        
-                                        // Re-enable download button
-                                        button_clone.set_sensitive(true);
-                                    } else if msg.starts_with("complete:error:") {
-                                        // Download failed
-                                        let error_msg = msg.strip_prefix("complete:error:")
-                                            .unwrap_or("Unknown error");
-                                        
-                                        download_status_clone2.set_text(&format!("Audio download failed: {}", error_msg));
-                                        progress_bar_clone2.set_visible(false);
-                                        
-                                        // Re-enable download button
-                                        button_clone.set_sensitive(true);
-                                        
-                                        components::show_error_dialog(&window_clone2, "Download Error", 
-                                            &format!("Audio download failed: {}", error_msg));
-                                    } else if msg.starts_with("error:") {
-                                        // Error starting download
-                                        let error_msg = msg.strip_prefix("error:")
-                                            .unwrap_or("Unknown error");
-                                            
-                                        download_status_clone2.set_text(&format!("Error starting audio download: {}", error_msg));
-                                        progress_bar_clone2.set_visible(false);
-                                        
-                                        // Re-enable download button
-                                        button_clone.set_sensitive(true);
-                                        
-                                        components::show_error_dialog(&window_clone2, "Download Error", 
-                                            &format!("Error starting audio download: {}", error_msg));
-                                    }
-                                    
-                                    glib::Continue(true)
-                                });
-                                
-                                // Spawn a thread to monitor the download progress
-                                let sender_clone = sender.clone();
-                                thread::spawn(move || {
-                                    // Start the download process
-                                    match crate::downloader::download_audio_with_format(&url_clone, &output_path_clone, &format_id, &target_format) {
-                                        Ok(mut child) => {
-                                            // Send initial start message
-                                            sender_clone.send("start".to_string()).unwrap();
-                                            
-                                            // Get stdout and stderr from the child process
-                                            let stdout = child.stdout.take().expect("Failed to capture stdout");
-                                            let stderr = child.stderr.take().expect("Failed to capture stderr");
-                                            
-                                            // Create a buffer reader for stdout and stderr
-                                            let stdout_reader = std::io::BufReader::new(stdout);
-                                            let stderr_reader = std::io::BufReader::new(stderr);
-                                            
-                                            // Create a thread to read stdout and track progress
-                                            let sender_stdout = sender_clone.clone();
-                                            let stdout_thread = thread::spawn(move || {
-                                                let mut reader = stdout_reader.lines();
-                                                let mut progress_state = crate::downloader::ProgressState::default();
-                                                
-                                                while let Some(line_result) = reader.next() {
-                                                    match line_result {
-                                                        Ok(line) => {
-                                                            // Only log important lines
-                                                            if line.contains("%") || line.contains("download") || 
-                                                               line.contains("ffmpeg") || line.contains("Extracting") {
-                                                                println!("STDOUT: {}", line);
-                                                            }
-                                                            
-                                                            // Update progress state based on this line
-                                                            if crate::downloader::update_progress_state(&line, &mut progress_state) {
-                                                                // Add "Audio" to status message for audio downloads
-                                                                let status = if !progress_state.status_message.contains("Audio") && 
-                                                                              !progress_state.status_message.starts_with("Download") {
-                                                                    format!("Audio {}", progress_state.status_message.to_lowercase())
-                                                                } else {
-                                                                    progress_state.status_message.clone()
-                                                                };
-                                                                
-                                                                // Send progress update with percentage and message
-                                                                sender_stdout.send(format!(
-                                                                    "progress:{:.3}:{}", 
-                                                                    progress_state.overall_percent,
-                                                                    status
-                                                                )).unwrap();
-                                                                
-                                                                // If complete, send completion message
-                                                                if progress_state.phase == crate::downloader::DownloadPhase::Complete {
-                                                                    sender_stdout.send("complete:success".to_string()).unwrap();
-                                                                }
-                                                            }
-                                                        },
-                                                        Err(e) => {
-                                                            println!("Error reading stdout: {}", e);
-                                                            break;
-                                                        }
-                                                    }
-                                                }
-                                            });
-                                            
-                                            // Create a thread to read stderr and track progress
-                                            let sender_stderr = sender_clone.clone();
-                                            let stderr_thread = thread::spawn(move || {
-                                                let mut reader = stderr_reader.lines();
-                                                let mut progress_state = crate::downloader::ProgressState::default();
-                                                
-                                                while let Some(line_result) = reader.next() {
-                                                    match line_result {
-                                                        Ok(line) => {
-                                                            // Only log important lines
-                                                            if line.contains("%") || line.contains("download") || 
-                                                               line.contains("ffmpeg") || line.contains("Extracting") {
-                                                                println!("STDERR: {}", line);
-                                                            }
-                                                            
-                                                            // Update progress state based on this line
-                                                            if crate::downloader::update_progress_state(&line, &mut progress_state) {
-                                                                // Add "Audio" to status message for audio downloads
-                                                                let status = if !progress_state.status_message.contains("Audio") && 
-                                                                              !progress_state.status_message.starts_with("Download") {
-                                                                    format!("Audio {}", progress_state.status_message.to_lowercase())
-                                                                } else {
-                                                                    progress_state.status_message.clone()
-                                                                };
-                                                                
-                                                                // Send progress update with percentage and message
-                                                                sender_stderr.send(format!(
-                                                                    "progress:{:.3}:{}", 
-                                                                    progress_state.overall_percent,
-                                                                    status
-                                                                )).unwrap();
-                                                                
-                                                                // If complete, send completion message
-                                                                if progress_state.phase == crate::downloader::DownloadPhase::Complete {
-                                                                    sender_stderr.send("complete:success".to_string()).unwrap();
-                                                                }
-                                                            }
-                                                        },
-                                                        Err(e) => {
-                                                            println!("Error reading stderr: {}", e);
-                                                            break;
-                                                        }
-                                                    }
-                                                }
-                                            });
-                                            
-                                            // Wait for the child process to complete
-                                            match child.wait() {
-                                                Ok(status) => {
-                                                    if status.success() {
-                                                        // Ensure progress is 100% when truly complete
-                                                        sender_clone.send("progress:1.0:Audio download complete!".to_string()).unwrap();
-                                                        // Slight delay to let UI update before sending completion message
-                                                        thread::sleep(std::time::Duration::from_millis(200));
-                                                        sender_clone.send("complete:success".to_string()).unwrap();
-                                                    } else {
-                                                        sender_clone.send(format!("complete:error:{}", status)).unwrap();
-                                                    }
-                                                },
-                                                Err(e) => {
-                                                    sender_clone.send(format!("complete:error:{}", e)).unwrap();
-                                                }
-                                            }
-                                            
-                                            // Wait for stdout and stderr threads to complete
-                                            let _ = stdout_thread.join();
-                                            let _ = stderr_thread.join();
-                                        },
-                                        Err(e) => {
-                                            sender_clone.send(format!("error:{}", e)).unwrap();
-                                        }
-                                    }
-                                });
-                            } else {
-                                download_status_clone.set_text("No matching audio format found");
-                                progress_bar_clone.set_visible(false);
-                                
-                                // Re-enable download button
-                                button.set_sensitive(true);
-                                
-                                components::show_error_dialog(&window_clone, "Format Error", 
-                                    "No matching format found for the selected audio quality and format type.");
-                            }
-                        }
+                },
+                DownloadEvent::Stage(stage) => {
+                    println!("Download stage: {}", stage);
+                    download_status_clone.set_text(&stage);
+                },
+                DownloadEvent::Finished { path } => {
+                    if let Some(path) = &path {
+                        println!("Saved to {}", path.display());
                     }
-                }
+                    notifier.download_finished("download-current", &job, path.as_deref());
+                    history_page.record(HistoryEntry::new(&job, path, HistoryOutcome::Completed));
+                    
+                    // Ensure the progress bar shows 100%
+                    progress_bar_clone.set_fraction(1.0);
+                    progress_bar_clone.set_text(Some("100.0%"));
+                    download_status_clone.set_text("Download complete!");
+                    
+                    // Show Open Folder button
+                    open_folder_button_clone.set_visible(true);
+                    
+                    download_controls_clone.set_visible(false);
+                    
+                    // Re-enable download button
+                    button.set_sensitive(true);
+                },
+                DownloadEvent::Failed { reason, stderr_tail } => {
+                    notifier.download_failed("download-current", &job, &reason, None);
+                    history_page.record(HistoryEntry::new(&job, control.output_file(), HistoryOutcome::Failed(reason.clone())));
+                    
+                    download_status_clone.set_text(&format!("Download failed: {}", reason));
+                    progress_bar_clone.set_visible(false);
+                    
+                    download_controls_clone.set_visible(false);
+                    
+                    // Re-enable download button
+                    button.set_sensitive(true);
+                    
+                    // The last lines from yt-dlp usually say what went wrong
+                    let mut message = format!("Download failed: {}", reason);
+                    if !stderr_tail.is_empty() {
+                        message.push_str(&format!("\n\n{}", stderr_tail.join("\n")));
+                    }
+                    components::show_error_dialog(&window, "Download Error", &message);
+                },
+                DownloadEvent::Cancelled => {
+                    history_page.record(HistoryEntry::new(&job, control.output_file(), HistoryOutcome::Cancelled));
+                    
+                    // Download was stopped by the user
+                    download_status_clone.set_text("Download cancelled");
+                    progress_bar_clone.set_visible(false);
+                    download_controls_clone.set_visible(false);
+                    
+                    // Re-enable download button
+                    button.set_sensitive(true);
+                },
             }
-        } else {
-            download_status_clone.set_text("Please select video or audio options first");
-            progress_bar_clone.set_visible(false);
             
-            // Re-enable download button
-            button.set_sensitive(true);
+            glib::Continue(true)
+        });
+        
+        // Run the download on a worker thread; the glib sender is not Sync, so share it behind a lock
+        let sender = Mutex::new(sender);
+        download.start(move |event| {
+            let _ = sender.lock().unwrap().send(event);
+        });
+    });
+
+    // Update queue rows from worker thread events
+    let queue_reporter = QueueReporter {
+        queue: download_queue.clone(),
+        history_page: history_page.clone(),
+        notifier: notifier.clone(),
+        finished_counts: Cell::new((0, 0)),
+    };
+    let queue_view_clone = queue_view.clone();
+    queue_receiver.attach(None, move |event| {
+        queue_reporter.report(&event);
+        queue_view_clone.update_row(&event);
+        glib::Continue(true)
+    });
+    
+    // Changing the parallel limit applies to the running queue right away
+    let download_queue_clone = download_queue.clone();
+    let settings_clone = Rc::clone(&settings);
+    parallel_spin.connect_value_changed(move |spin| {
+        download_queue_clone.set_max_concurrent(spin.value() as usize);
+        
+        let mut settings = settings_clone.borrow_mut();
+        settings.max_concurrent = spin.value() as usize;
+        if let Err(e) = settings.save() {
+            println!("Failed to save settings: {}", e);
+        }
+    });
+    
+    // Connect the Add to Queue button to enqueue the current selection
+    let url_entry_clone = url_entry.clone();
+    let output_entry_clone = path_entry.clone();
+    let queue_view_clone = queue_view.clone();
+    let settings_clone = Rc::clone(&settings);
+    let window_clone = window.clone();
+    queue_button.connect_clicked(move |_| {
+        let url = url_entry_clone.text().to_string();
+        let output_path = output_entry_clone.text().to_string();
+        
+        if url.is_empty() {
+            components::show_error_dialog(&window_clone, "Invalid URL", "Please enter a URL");
+            return;
+        }
+        
+        if output_path.is_empty() {
+            components::show_error_dialog(&window_clone, "Missing Download Location", "Please specify a download folder");
+            return;
+        }
+        
+        let job = match media_selection.to_queue_job(&url, &output_path) {
+            Ok(job) => job,
+            Err(e) => {
+                components::show_error_dialog(&window_clone, "Format Error", &e);
+                return;
+            }
+        };
+        
+        if let Err(message) = check_ffmpeg(&job) {
+            components::show_error_dialog(&window_clone, "ffmpeg Required", &message);
+            return;
+        }
+        
+        // Remember the chosen quality for next time
+        {
+            let mut settings = settings_clone.borrow_mut();
+            media_selection.remember_preferences(&mut settings);
+            if let Err(e) = settings.save() {
+                println!("Failed to save settings: {}", e);
+            }
+        }
+        
+        queue_view_clone.enqueue(job);
+    });
+
+    // Connect the Pause button to suspend or resume the running download
+    let current_download_clone = Rc::clone(&current_download);
+    let download_status_clone = download_status.clone();
+    let window_clone = window.clone();
+    pause_button.connect_clicked(move |button| {
+        if let Some(control) = current_download_clone.borrow().as_ref() {
+            let result = if control.is_paused() {
+                control.resume().map(|_| {
+                    button.set_label("Pause");
+                    download_status_clone.set_text("Download resumed");
+                })
+            } else {
+                control.pause().map(|_| {
+                    button.set_label("Resume");
+                    download_status_clone.set_text("Download paused");
+                })
+            };
             
-            components::show_error_dialog(&window_clone, "Selection Error", 
-                "Please select video or audio options before downloading.");
+            if let Err(e) = result {
+                components::show_error_dialog(&window_clone, "Download Error", 
+                    &format!("Failed to pause or resume download: {}", e));
+            }
+        }
+    });
+    
+    // Connect the Cancel button to stop the running download
+    let current_download_clone = Rc::clone(&current_download);
+    let download_status_clone = download_status.clone();
+    let window_clone = window.clone();
+    cancel_button.connect_clicked(move |_| {
+        if let Some(control) = current_download_clone.borrow().as_ref() {
+            download_status_clone.set_text("Cancelling download...");
+            if let Err(e) = control.cancel() {
+                components::show_error_dialog(&window_clone, "Download Error", 
+                    &format!("Failed to cancel download: {}", e));
+            }
         }
     });
 
@@ -1708,7 +2347,8 @@ pub fn build_ui(app: &Application) {
     // Connect media stack to update the download button state and download path
     let format_info_clone = format_info.clone();
     let path_entry_clone = path_entry_for_tab_change.clone();
-    let default_path = get_default_download_path().unwrap_or_else(|_| String::from("."));
+    let settings_clone = Rc::clone(&settings);
+    let template_row_clone = template_row.clone();
     let media_stack_for_tab_change = media_stack.clone();
     
     media_stack_for_tab_change.connect_visible_child_name_notify(move |stack| {
@@ -1717,29 +2357,38 @@ pub fn build_ui(app: &Application) {
         // Get current path
         let current_path = path_entry_clone.text().to_string();
         
-        // Only change the path if it contains "HyprDownloader" (meaning it's using our default path structure)
-        // or if it's empty/default
-        let should_update_path = current_path.contains("HyprDownloader") || 
-                               current_path.is_empty() || 
+        let mut settings = settings_clone.borrow_mut();
+        
+        // Only change the path if it is one of the configured folders or empty,
+        // so a folder picked by hand is kept
+        let should_update_path = current_path.is_empty() || 
                                current_path == "." || 
-                               current_path == default_path;
+                               current_path == settings.video_output_dir || 
+                               current_path == settings.audio_output_dir;
         
         if active_tab == "video" {
             format_info_clone.set_text("Video options selected. Choose quality, FPS and format.");
-            
-            // Set path to video subfolder only if using default path
-            if should_update_path {
-                let video_path = format!("{}/video", default_path);
-                path_entry_clone.set_text(&video_path);
-            }
         } else if active_tab == "audio" {
             format_info_clone.set_text("Audio options selected. Choose quality and format.");
-            
-            // Set path to audio subfolder only if using default path
-            if should_update_path {
-                let audio_path = format!("{}/audio", default_path);
-                path_entry_clone.set_text(&audio_path);
-            }
+        }
+        
+        if should_update_path {
+            path_entry_clone.set_text(settings.output_dir_for(&active_tab));
+        }
+        
+        // Keep the template edited on the other tab and show the one of this tab
+        let template = template_row_clone.template().template;
+        if active_tab == "audio" {
+            settings.video_template = template;
+        } else {
+            settings.audio_template = template;
+        }
+        template_row_clone.set_template(&settings.template_for(&active_tab));
+        
+        // Reopen this tab on the next start
+        settings.last_tab = active_tab.to_string();
+        if let Err(e) = settings.save() {
+            println!("Failed to save settings: {}", e);
         }
     });
 
@@ -1764,6 +2413,17 @@ pub fn build_ui(app: &Application) {
         dialog.present();
     });
 
+    // Connect the Preferences button to edit the persistent settings
+    let preferences_view = PreferencesView {
+        window: window.clone(),
+        settings: Rc::clone(&settings),
+        path_entry: path_entry.clone(),
+        status_label: status_label.clone(),
+        parallel_spin: parallel_spin.clone(),
+        selection: media_selection_for_preview.clone(),
+    };
+    preferences_button.connect_clicked(move |_| preferences_view.show());
+
     // Connect the About button to show information dialog
     let window_clone = window.clone();
     about_button.connect_clicked(move |_| {
@@ -1785,8 +2445,11 @@ pub fn build_ui(app: &Application) {
 
     // Connect format combo to show format suggestions
     let video_format_info = format_info.clone();
+    let codec_combo_clone = codec_combo.clone();
     format_combo.connect_changed(move |combo| {
-        if let Some(selected_format) = combo.active_text() {
+        if let Some(format_choice) = combo.active_id() {
+            // Describe the output container, which is the merge target for merged streams
+            let selected_format = format_choice.rsplit(':').next().unwrap_or("").to_string();
             let format_suggestion = match selected_format.to_lowercase().as_str() {
                 "mp4" => "MP4 - Good compatibility, balanced quality/size ratio.",
                 "webm" => "WebM - Better compression, may not play on all devices.",
@@ -1796,7 +2459,11 @@ pub fn build_ui(app: &Application) {
                 _ => "Selected format"
             };
             
-            video_format_info.set_text(format_suggestion);
+            // Followed by the playback hint of the codec picked for this format
+            match codec_combo_clone.active_id() {
+                Some(codec) => video_format_info.set_text(&format!("{} {}", format_suggestion, codec_compatibility_hint(&codec))),
+                None => video_format_info.set_text(format_suggestion),
+            }
         }
     });
     
@@ -1818,10 +2485,41 @@ pub fn build_ui(app: &Application) {
         }
     });
 
+    // Keep the file name preview in sync with the selected stream and format
+    for combo in [&quality_combo, &format_combo, &audio_format_combo] {
+        let media_selection_clone = media_selection_for_preview.clone();
+        combo.connect_changed(move |_| {
+            media_selection_clone.refresh_template_preview();
+        });
+    }
+    
+    media_stack.connect_visible_child_name_notify(move |_| {
+        media_selection_for_preview.refresh_template_preview();
+    });
+
     // Add widgets to the container
     main_container.append(&header_container);
     container.append(&main_area);
-    main_container.append(&container);
+    main_stack.add_titled(&container, Some("download"), "Download");
+    main_stack.add_titled(&history_page.container, Some("history"), "History");
+    main_container.append(&main_stack);
 
     window.present();
+
+    // Find a working yt-dlp, or explain how to set one up
+    ytdlp_setup::check_ytdlp(&window, Rc::clone(&settings), &status_label);
+    
+    // Probe ffmpeg in the background and mark the options that need it
+    let (ffmpeg_sender, ffmpeg_receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+    thread::spawn(move || {
+        let _ = ffmpeg_sender.send(ffmpeg_tools());
+    });
+    
+    ffmpeg_receiver.attach(None, move |tools: FfmpegTools| {
+        println!("Using {}", tools.description());
+        subtitle_panel.set_ffmpeg_available(tools.ffmpeg.is_some());
+        tag_editor.set_ffmpeg_available(tools.ffmpeg.is_some());
+        ffmpeg_warning.set_visible(!tools.is_complete());
+        glib::Continue(false)
+    });
 } 
\ No newline at end of file
@@ -27,4 +27,31 @@ pub fn get_default_download_path() -> Result<String> {
     }
     
     Ok(hypr_dir.to_string_lossy().to_string())
-} 
\ No newline at end of file
+} 
+
+// Format a duration in seconds as H:MM:SS or M:SS
+pub fn format_duration(seconds: u64) -> String {
+    let (hours, minutes, secs) = (seconds / 3600, (seconds % 3600) / 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, secs)
+    } else {
+        format!("{}:{:02}", minutes, secs)
+    }
+}
+
+// Format a byte count with a binary unit, e.g. "412.3 MB"
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
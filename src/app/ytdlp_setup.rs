@@ -0,0 +1,262 @@
+use gtk4::{
+    prelude::*,
+    Window, Box as GtkBox, Orientation, Align, Label, Entry
+};
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::thread;
+
+use crate::ui::components;
+use crate::downloader::{YtdlpBinary, active_ytdlp, locate_ytdlp, install_ytdlp, ytdlp_version};
+use super::Settings;
+
+const SETUP_HELP: &str = "Install yt-dlp with your package manager or with \"pip install -U yt-dlp\", \
+choose an existing executable, or pick the yt-dlp file downloaded from \
+https://github.com/yt-dlp/yt-dlp/releases to keep a private copy that is preferred over other installs.";
+
+// "yt-dlp 2024.08.06 (PATH: /usr/bin/yt-dlp)", with a hint when the release is old
+pub fn describe_ytdlp(binary: Option<&YtdlpBinary>) -> String {
+    match binary {
+        Some(binary) if binary.is_outdated() => format!("{}, over six months old, update it if downloads fail", binary.description()),
+        Some(binary) => binary.description(),
+        None => "No working yt-dlp found".to_string(),
+    }
+}
+
+// locate_ytdlp starts the binary, which can take seconds, so it runs on a worker thread
+fn locate_in_background<F>(custom_path: String, on_result: F)
+where
+    F: Fn(Result<YtdlpBinary, String>) + 'static,
+{
+    let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+    thread::spawn(move || {
+        let _ = sender.send(locate_ytdlp(&custom_path).map_err(|e| format!("{:#}", e)));
+    });
+
+    receiver.attach(None, move |result| {
+        on_result(result);
+        glib::Continue(false)
+    });
+}
+
+// Look for yt-dlp in the background at startup and after the preferences change;
+// the setup dialog opens if none works
+pub fn check_ytdlp(window: &impl IsA<Window>, settings: Rc<RefCell<Settings>>, status_label: &Label) {
+    let custom_path = settings.borrow().ytdlp_path.clone();
+    let window = window.upcast_ref::<Window>().clone();
+    let status_label = status_label.clone();
+    locate_in_background(custom_path, move |result| {
+        match result {
+            Ok(binary) => {
+                eprintln!("Using {}", binary.description());
+                if binary.is_outdated() {
+                    status_label.set_text(&describe_ytdlp(Some(&binary)));
+                    status_label.set_visible(true);
+                }
+            },
+            Err(problems) => show_setup_dialog(&window, Rc::clone(&settings), &problems),
+        }
+    });
+}
+
+// Explains what is missing and offers ways to get a working yt-dlp
+pub fn show_setup_dialog(parent: &impl IsA<Window>, settings: Rc<RefCell<Settings>>, problems: &str) {
+    let dialog = Window::builder()
+        .title("yt-dlp Setup")
+        .transient_for(parent)
+        .modal(true)
+        .default_width(520)
+        .build();
+
+    let container = GtkBox::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(10)
+        .margin_top(12)
+        .margin_bottom(12)
+        .margin_start(12)
+        .margin_end(12)
+        .build();
+
+    container.add_css_class("content-box");
+    container.append(&components::create_section_title("yt-dlp Is Required"));
+
+    let intro_label = Label::builder()
+        .label("Every fetch and download runs yt-dlp, but no working copy was found:")
+        .halign(Align::Start)
+        .wrap(true)
+        .build();
+
+    let problems_label = Label::builder()
+        .label(problems)
+        .halign(Align::Start)
+        .selectable(true)
+        .wrap(true)
+        .build();
+
+    problems_label.add_css_class("status-label");
+
+    let help_label = Label::builder()
+        .label(SETUP_HELP)
+        .halign(Align::Start)
+        .wrap(true)
+        .build();
+
+    let button_row = GtkBox::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(8)
+        .halign(Align::End)
+        .build();
+
+    let close_button = components::create_button("Close", "browse-button");
+    let retry_button = components::create_button("Check Again", "browse-button");
+    let executable_button = components::create_button("Choose Executable", "browse-button");
+    let install_button = components::create_button("Use Downloaded File", "fetch-button");
+
+    button_row.append(&close_button);
+    button_row.append(&retry_button);
+    button_row.append(&executable_button);
+    button_row.append(&install_button);
+
+    container.append(&intro_label);
+    container.append(&problems_label);
+    container.append(&help_label);
+    container.append(&button_row);
+    dialog.set_child(Some(&container));
+
+    // Close once a working executable turns up, otherwise list what is still wrong
+    let check = {
+        let dialog = dialog.clone();
+        let settings = Rc::clone(&settings);
+        Rc::new(move || {
+            problems_label.set_text("Checking...");
+            let dialog = dialog.clone();
+            let problems_label = problems_label.clone();
+            locate_in_background(settings.borrow().ytdlp_path.clone(), move |result| {
+                match result {
+                    Ok(binary) => {
+                        eprintln!("Using {}", binary.description());
+                        dialog.close();
+                    },
+                    Err(problems) => problems_label.set_text(&problems),
+                }
+            });
+        })
+    };
+
+    let dialog_clone = dialog.clone();
+    close_button.connect_clicked(move |_| {
+        dialog_clone.close();
+    });
+
+    let check_clone = Rc::clone(&check);
+    retry_button.connect_clicked(move |_| {
+        check_clone();
+    });
+
+    // An existing yt-dlp becomes the custom executable in the preferences
+    let dialog_clone = dialog.clone();
+    let check_clone = Rc::clone(&check);
+    executable_button.connect_clicked(move |_| {
+        let settings = Rc::clone(&settings);
+        let check = Rc::clone(&check_clone);
+        let parent = dialog_clone.clone();
+        choose_file(&dialog_clone, "Select yt-dlp Executable", move |path| {
+            if let Err(e) = ytdlp_version(&path) {
+                components::show_error_dialog(&parent, "yt-dlp Setup", &format!("{:#}", e));
+                return;
+            }
+
+            let updated = {
+                let mut settings = settings.borrow_mut();
+                settings.ytdlp_path = path.to_string_lossy().to_string();
+                settings.clone()
+            };
+            if let Err(e) = updated.save() {
+                eprintln!("Failed to save settings: {:#}", e);
+            }
+            check();
+        });
+    });
+
+    // A downloaded release is copied into the data dir
+    let dialog_clone = dialog.clone();
+    install_button.connect_clicked(move |_| {
+        let check = Rc::clone(&check);
+        let parent = dialog_clone.clone();
+        choose_file(&dialog_clone, "Select Downloaded yt-dlp", move |path| {
+            match install_ytdlp(&path) {
+                Ok(_) => check(),
+                Err(e) => components::show_error_dialog(&parent, "yt-dlp Setup", &format!("{:#}", e)),
+            }
+        });
+    });
+
+    dialog.present();
+}
+
+// Current yt-dlp and a button to replace the app copy, shown under the executable field
+pub fn create_ytdlp_row(parent: &Window, executable_entry: &Entry) -> GtkBox {
+    let row = GtkBox::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(8)
+        .build();
+
+    let status_label = Label::builder()
+        .label(describe_ytdlp(active_ytdlp().as_ref()))
+        .halign(Align::Start)
+        .hexpand(true)
+        .wrap(true)
+        .build();
+
+    status_label.add_css_class("status-label");
+
+    let install_button = components::create_button("Update from File", "browse-button");
+    install_button.set_valign(Align::Center);
+    install_button.set_tooltip_text(Some("Replace the app copy of yt-dlp with a downloaded release"));
+
+    row.append(&status_label);
+    row.append(&install_button);
+
+    let parent = parent.clone();
+    let executable_entry = executable_entry.clone();
+    install_button.connect_clicked(move |_| {
+        let status_label = status_label.clone();
+        let executable_entry = executable_entry.clone();
+        let error_parent = parent.clone();
+        choose_file(&parent, "Select Downloaded yt-dlp", move |path| {
+            match install_ytdlp(&path) {
+                Ok(binary) => {
+                    // A custom executable still wins over the app copy
+                    let custom = executable_entry.text().trim().to_string();
+                    let text = if custom.is_empty() || custom == "yt-dlp" {
+                        format!("Installed {}", binary.description())
+                    } else {
+                        format!("Installed {}, clear the executable field to use it", binary.description())
+                    };
+                    status_label.set_text(&text);
+                },
+                Err(e) => components::show_error_dialog(&error_parent, "yt-dlp Update", &format!("{:#}", e)),
+            }
+        });
+    });
+
+    row
+}
+
+fn choose_file<F>(parent: &Window, title: &str, on_chosen: F)
+where
+    F: Fn(PathBuf) + 'static,
+{
+    let dialog = components::create_file_chooser_dialog(parent, title);
+    dialog.connect_response(move |dialog, response| {
+        if response == gtk4::ResponseType::Accept {
+            if let Some(path) = dialog.file().and_then(|file| file.path()) {
+                on_chosen(path);
+            }
+        }
+        dialog.destroy();
+    });
+
+    dialog.present();
+}
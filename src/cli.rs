@@ -0,0 +1,541 @@
+use anyhow::{Result, Context};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc, Mutex};
+
+use crate::app::{Settings, load_auth_config, History, HistoryEntry, HistoryOutcome, format_duration, format_size};
+use crate::downloader::{fetch_available_formats, select_video_format, select_audio_format,
+                        build_format_selector, FormatOption, DownloadQueue, QueueJob, JobKind,
+                        JobStatus, QueueEvent, MERGE_CONTAINERS, VIDEO_CODECS, SubtitleOptions, SubtitleMode, AudioTags, OutputTemplate,
+                        CookieSource, AuthConfig, COOKIE_BROWSERS, locate_ytdlp, install_ytdlp, ffmpeg_tools, set_auth_config,
+                        set_network_options, parse_rate_limit, BackendKind, BackendConfig, set_backend_config,
+                        direct_file_for, DirectFile, parse_checksum};
+
+const USAGE: &str = "Usage:
+  media_downloader                      Start the graphical interface
+  media_downloader get URL [OPTIONS]    Download media without a window
+  media_downloader formats URL [--json] [--backend NAME]
+                                        List available formats
+  media_downloader ytdlp [--install FILE]
+                                        Show the yt-dlp in use, or install a downloaded release as the app copy
+  media_downloader help                 Show this help
+
+Options for get:
+  --video HEIGHT       Video height, e.g. 1080 or 1080p (default: best)
+  --fps FPS            Frame rate, e.g. 60 (default: highest)
+  --ext EXT            Stream extension, e.g. mp4 or webm
+  --container EXT      Merge container for video-only streams (mp4, mkv, webm)
+  --codec CODEC        Preferred video codec (h264, vp9, av1, h265; default: codec order from Preferences)
+  --subs LANGS         Subtitle languages, e.g. en,de (saved as the subtitle format from Preferences)
+  --embed-subs         Embed the subtitles into the video instead
+  --audio [FORMAT]     Download audio only, optionally converted (default: preferred format)
+  --abr KBPS           Maximum audio bitrate
+  --no-tags            Don't write title/artist/album/date tags into audio files
+  --no-cover           Don't embed the thumbnail as cover art into audio files
+  --cookies FILE       Cookies from a Netscape cookies.txt file (default: from Preferences)
+  --cookies-from-browser BROWSER[:PROFILE]
+                       Cookies from a browser, e.g. firefox or chrome:Profile 1
+  --template TEMPLATE  File name template, e.g. '%(uploader)s - %(title)s.%(ext)s' (default: from Preferences)
+  --limit-rate RATE    Maximum download speed, e.g. 500K or 2M (default: from Preferences)
+  --backend NAME       Downloader to use, ytdlp or http (default: from Preferences)
+  --sha256 DIGEST      Expected SHA-256 of a direct file link, checked after the download
+  -o, --output DIR     Output folder (default: the video or audio folder from Preferences)";
+
+// Values accepted by yt-dlp --audio-format
+const AUDIO_FORMATS: [&str; 9] = ["best", "aac", "alac", "flac", "m4a", "mp3", "opus", "vorbis", "wav"];
+
+#[derive(Debug, Default)]
+pub struct GetOptions {
+    pub url: String,
+    pub height: Option<u32>,
+    pub fps: Option<u32>,
+    pub ext: Option<String>,
+    pub container: Option<String>,
+    pub codec: Option<String>,     // Tried before the codec order from the settings
+    pub subtitles: Vec<String>,    // Subtitle languages
+    pub embed_subs: bool,
+    pub no_tags: bool,
+    pub no_cover: bool,
+    pub template: Option<String>,  // yt-dlp output template for the file name
+    pub cookies: Option<CookieSource>,  // Replaces the cookie source from the settings
+    pub rate_limit: Option<String>,     // Replaces the speed limit from the settings
+    pub backend: Option<BackendKind>,   // Replaces the downloader and rules from the settings
+    pub checksum: Option<String>,       // SHA-256 of a direct file link, lowercase hex
+    pub audio: Option<String>,     // Target audio format, empty when --audio has no value
+    pub abr: Option<u32>,
+    pub output: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum CliCommand {
+    Get(Box<GetOptions>),
+    Formats { url: String, json: bool, backend: Option<BackendKind> },
+    Ytdlp { install: Option<PathBuf> },
+    Help,
+}
+
+// Parse command line arguments; None means no subcommand was given and the GUI should start
+pub fn parse_args(args: &[String]) -> Option<Result<CliCommand>> {
+    let command = args.first()?;
+
+    let result = match command.as_str() {
+        "get" => parse_get(&args[1..]).map(|options| CliCommand::Get(Box::new(options))),
+        "formats" => parse_formats(&args[1..]),
+        "ytdlp" => parse_ytdlp(&args[1..]),
+        "help" | "--help" | "-h" => Ok(CliCommand::Help),
+        _ => return None,
+    };
+
+    Some(result)
+}
+
+fn parse_number(option: &str, value: Option<&String>) -> Result<u32> {
+    let value = value.ok_or_else(|| anyhow::anyhow!("Missing value for {}", option))?;
+    value.trim_end_matches('p')
+        .parse()
+        .with_context(|| format!("Invalid value '{}' for {}", value, option))
+}
+
+fn parse_backend(value: Option<&String>) -> Result<BackendKind> {
+    let value = value.ok_or_else(|| anyhow::anyhow!("Missing value for --backend"))?;
+    BackendKind::from_id(&value.to_lowercase().replace('-', ""))
+        .ok_or_else(|| anyhow::anyhow!("Unsupported backend '{}', use one of {:?}", value, BackendKind::ALL.map(|kind| kind.id())))
+}
+
+// Accept "h264", "H.264", "hevc" etc. and return the name used by FormatOption::codec_label
+fn parse_codec(value: &str) -> Result<String> {
+    let codec = match value.to_lowercase().replace('.', "").as_str() {
+        "h264" | "avc" | "avc1" => "H.264",
+        "h265" | "hevc" => "H.265",
+        "vp9" | "vp09" => "VP9",
+        "av1" | "av01" => "AV1",
+        _ => return Err(anyhow::anyhow!("Unsupported codec '{}', use one of {:?}", value, VIDEO_CODECS)),
+    };
+    Ok(codec.to_string())
+}
+
+fn parse_get(args: &[String]) -> Result<GetOptions> {
+    let mut options = GetOptions::default();
+    let mut iter = args.iter().peekable();
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--video" => options.height = Some(parse_number(arg, iter.next())?),
+            "--fps" => options.fps = Some(parse_number(arg, iter.next())?),
+            "--abr" => options.abr = Some(parse_number(arg, iter.next())?),
+            "--ext" => options.ext = Some(iter.next().ok_or_else(|| anyhow::anyhow!("Missing value for --ext"))?.to_lowercase()),
+            "--container" => {
+                let container = iter.next().ok_or_else(|| anyhow::anyhow!("Missing value for --container"))?.to_lowercase();
+                if !MERGE_CONTAINERS.contains(&container.as_str()) {
+                    return Err(anyhow::anyhow!("Unsupported container '{}', use one of {:?}", container, MERGE_CONTAINERS));
+                }
+                options.container = Some(container);
+            },
+            "--audio" => {
+                // The audio format is optional, so only take the next argument if it names one
+                let format = match iter.peek() {
+                    Some(next) if AUDIO_FORMATS.contains(&next.to_lowercase().as_str()) => iter.next().unwrap().to_lowercase(),
+                    _ => String::new(),
+                };
+                options.audio = Some(format);
+            },
+            "--codec" => {
+                let codec = iter.next().ok_or_else(|| anyhow::anyhow!("Missing value for --codec"))?;
+                options.codec = Some(parse_codec(codec)?);
+            },
+            "--subs" => {
+                let languages = iter.next().ok_or_else(|| anyhow::anyhow!("Missing value for --subs"))?;
+                options.subtitles = languages.split(',')
+                    .map(|language| language.trim().to_string())
+                    .filter(|language| !language.is_empty())
+                    .collect();
+            },
+            "--embed-subs" => options.embed_subs = true,
+            "--no-tags" => options.no_tags = true,
+            "--no-cover" => options.no_cover = true,
+            "--cookies" => {
+                let path = iter.next().ok_or_else(|| anyhow::anyhow!("Missing value for --cookies"))?;
+                options.cookies = Some(CookieSource::File { path: path.clone() });
+            },
+            "--cookies-from-browser" => {
+                let spec = iter.next().ok_or_else(|| anyhow::anyhow!("Missing value for --cookies-from-browser"))?;
+                let (browser, profile) = spec.split_once(':').unwrap_or((spec, ""));
+                let browser = browser.to_lowercase();
+                if !COOKIE_BROWSERS.contains(&browser.as_str()) {
+                    return Err(anyhow::anyhow!("Unsupported browser '{}', use one of {:?}", browser, COOKIE_BROWSERS));
+                }
+                options.cookies = Some(CookieSource::Browser { browser, profile: profile.to_string() });
+            },
+            "--limit-rate" => {
+                let rate = iter.next().ok_or_else(|| anyhow::anyhow!("Missing value for --limit-rate"))?;
+                options.rate_limit = parse_rate_limit(rate).map_err(|e| anyhow::anyhow!(e))?;
+            },
+            "--backend" => options.backend = Some(parse_backend(iter.next())?),
+            "--sha256" => {
+                let digest = iter.next().ok_or_else(|| anyhow::anyhow!("Missing value for --sha256"))?;
+                options.checksum = Some(parse_checksum(digest).map_err(|e| anyhow::anyhow!(e))?);
+            },
+            "--template" => options.template = Some(iter.next().ok_or_else(|| anyhow::anyhow!("Missing value for --template"))?.clone()),
+            "-o" | "--output" => options.output = Some(iter.next().ok_or_else(|| anyhow::anyhow!("Missing value for {}", arg))?.clone()),
+            _ if arg.starts_with('-') => return Err(anyhow::anyhow!("Unknown option '{}'\n\n{}", arg, USAGE)),
+            _ if options.url.is_empty() => options.url = arg.clone(),
+            _ => return Err(anyhow::anyhow!("Unexpected argument '{}'", arg)),
+        }
+    }
+
+    if options.url.is_empty() {
+        return Err(anyhow::anyhow!("Missing URL\n\n{}", USAGE));
+    }
+
+    Ok(options)
+}
+
+fn parse_formats(args: &[String]) -> Result<CliCommand> {
+    let mut url = None;
+    let mut json = false;
+    let mut backend = None;
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--backend" => backend = Some(parse_backend(iter.next())?),
+            _ if arg.starts_with('-') => return Err(anyhow::anyhow!("Unknown option '{}'\n\n{}", arg, USAGE)),
+            _ => url = Some(arg.clone()),
+        }
+    }
+
+    let url = url.ok_or_else(|| anyhow::anyhow!("Missing URL\n\n{}", USAGE))?;
+    Ok(CliCommand::Formats { url, json, backend })
+}
+
+fn parse_ytdlp(args: &[String]) -> Result<CliCommand> {
+    let mut install = None;
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--install" => install = Some(PathBuf::from(iter.next().ok_or_else(|| anyhow::anyhow!("Missing value for --install"))?)),
+            _ => return Err(anyhow::anyhow!("Unexpected argument '{}'\n\n{}", arg, USAGE)),
+        }
+    }
+
+    Ok(CliCommand::Ytdlp { install })
+}
+
+pub fn run(command: CliCommand) -> Result<()> {
+    let settings = Settings::load();
+    set_auth_config(load_auth_config(&settings));
+    set_network_options(settings.network.clone());
+    set_backend_config(settings.backend_config());
+
+    match command {
+        CliCommand::Help => {
+            println!("{}", USAGE);
+            Ok(())
+        },
+        CliCommand::Ytdlp { install } => manage_ytdlp(install, &settings),
+        CliCommand::Formats { url, json, backend } => {
+            if let Some(kind) = backend {
+                set_backend_config(BackendConfig { default: kind, rules: Vec::new(), ..settings.backend_config() });
+            }
+
+            // Plain file links have no formats to list and don't need yt-dlp
+            if let Some(file) = direct_file_for(&url)? {
+                return print_direct_file(&file, json);
+            }
+            require_ytdlp(&settings)?;
+            print_formats(&url, json)
+        },
+        CliCommand::Get(options) => download(*options, &settings),
+    }
+}
+
+// Stop with setup instructions instead of a bare "Failed to execute" later on
+fn require_ytdlp(settings: &Settings) -> Result<()> {
+    match locate_ytdlp(&settings.ytdlp_path) {
+        Ok(binary) => {
+            if binary.is_outdated() {
+                eprintln!("Warning: {} is over six months old, update it if downloads fail", binary.description());
+            }
+            Ok(())
+        },
+        Err(e) => Err(anyhow::anyhow!("{:#}\n\nInstall yt-dlp with your package manager or \"pip install -U yt-dlp\", \
+            set its path in Preferences, or run \"media_downloader ytdlp --install FILE\" with a release from \
+            https://github.com/yt-dlp/yt-dlp/releases", e)),
+    }
+}
+
+fn manage_ytdlp(install: Option<PathBuf>, settings: &Settings) -> Result<()> {
+    let Some(file) = install else {
+        let binary = locate_ytdlp(&settings.ytdlp_path)?;
+        println!("{}", binary.description());
+        if binary.is_outdated() {
+            println!("This release is over six months old, update it if downloads fail");
+        }
+        println!("{}", ffmpeg_tools().description());
+        return Ok(());
+    };
+
+    let installed = install_ytdlp(&file)?;
+    println!("Installed {}", installed.description());
+
+    // A custom executable from the settings still takes precedence
+    let active = locate_ytdlp(&settings.ytdlp_path)?;
+    if active.path != installed.path {
+        println!("Still using {}, clear the yt-dlp executable in Preferences to use the app copy", active.description());
+    }
+    Ok(())
+}
+
+fn print_direct_file(file: &DirectFile, json: bool) -> Result<()> {
+    if json {
+        let output = serde_json::json!({
+            "direct": {
+                "url": file.url,
+                "file_name": file.file_name,
+                "content_type": file.mime_type(),
+                "size": file.size,
+                "resumable": file.resumable,
+                "sha256": file.digest,
+            }
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    println!("{}", file.file_name);
+    let size = file.size.map(format_size).unwrap_or_else(|| "unknown size".to_string());
+    println!("{} · {}", file.mime_type(), size);
+    if let Some(digest) = &file.digest {
+        println!("SHA-256: {}", digest);
+    }
+    println!("Direct file link, downloaded as it is without choosing a format");
+    Ok(())
+}
+
+fn print_formats(url: &str, json: bool) -> Result<()> {
+    let (info, formats) = fetch_available_formats(url)?;
+
+    if json {
+        let output = serde_json::json!({ "info": info, "formats": formats });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    println!("{}", info.title);
+    let mut details = vec![info.uploader.clone()];
+    if let Some(duration) = info.duration {
+        details.push(format_duration(duration));
+    }
+    if let Some(date) = info.formatted_upload_date() {
+        details.push(date);
+    }
+    println!("{}", details.into_iter().filter(|d| !d.is_empty()).collect::<Vec<_>>().join(" · "));
+
+    let uploaded: Vec<&str> = info.subtitles.iter()
+        .filter(|track| !track.automatic)
+        .map(|track| track.language.as_str())
+        .collect();
+    let automatic = info.subtitles.iter().filter(|track| track.automatic).count();
+    if !uploaded.is_empty() {
+        println!("Subtitles: {}", uploaded.join(", "));
+    }
+    if automatic > 0 {
+        println!("Auto-generated captions: {} languages", automatic);
+    }
+    println!();
+
+    println!("{:<10} {:<12} {:>4} {:<6} {:<14} {:<14} {:>5} {:>6} {:>11}", "ID", "RESOLUTION", "FPS", "EXT", "VCODEC", "ACODEC", "ABR", "TBR", "SIZE");
+    for format in &formats {
+        let tbr = format.tbr.map(|tbr| format!("{:.0}k", tbr)).unwrap_or_default();
+        // Estimated sizes are marked with "~"
+        let size = match (format.filesize, format.filesize_approx) {
+            (Some(size), _) => format_size(size),
+            (None, Some(size)) => format!("~{}", format_size(size)),
+            (None, None) => String::new(),
+        };
+        println!("{:<10} {:<12} {:>4} {:<6} {:<14} {:<14} {:>5} {:>6} {:>11}",
+            format.format_id, format.resolution, format.fps, format.ext, format.vcodec, format.acodec, format.abr, tbr, size);
+    }
+    Ok(())
+}
+
+// Job for media that yt-dlp extracts, with the format picked from the options
+fn media_job(options: &GetOptions, settings: &Settings) -> Result<QueueJob> {
+    let (info, formats) = fetch_available_formats(&options.url)?;
+
+    let tab = if options.audio.is_some() { "audio" } else { "video" };
+    let output_path = match &options.output {
+        Some(output) => output.clone(),
+        None => settings.output_dir_for(tab).to_string(),
+    };
+
+    let mut job = build_job(options, &formats, output_path, &settings.codec_preference)?;
+    job.title = Some(info.title.clone()).filter(|title| !title.is_empty());
+    job.duration = info.duration;
+    job.output_template = Some(match &options.template {
+        Some(template) => OutputTemplate::new(template, settings.restrict_filenames),
+        None => settings.template_for(tab),
+    });
+    job.rate_limit = options.rate_limit.clone();
+    
+    // Audio is tagged from the fetched metadata, like the prefilled fields in the GUI
+    let embed_metadata = settings.write_audio_tags && !options.no_tags;
+    let embed_cover = settings.embed_cover && !options.no_cover;
+    if options.audio.is_some() && (embed_metadata || embed_cover) {
+        job.tags = Some(AudioTags { embed_metadata, embed_cover, ..AudioTags::from_media_info(&info) });
+    }
+    
+    // Uploaded subtitles are preferred, auto-generated captions fill the gaps
+    if !options.subtitles.is_empty() && options.audio.is_none() {
+        job.subtitles = Some(SubtitleOptions {
+            languages: options.subtitles.clone(),
+            automatic: true,
+            mode: if options.embed_subs { SubtitleMode::Embed } else { settings.subtitle_mode },
+        });
+    }
+    Ok(job)
+}
+
+// A direct file link is saved as the server sends it, so the format options don't apply
+fn direct_job(options: &GetOptions, file: &DirectFile, settings: &Settings) -> QueueJob {
+    let tab = if file.mime_type().starts_with("audio/") { "audio" } else { "video" };
+    let output_path = match &options.output {
+        Some(output) => output.clone(),
+        None => settings.output_dir_for(tab).to_string(),
+    };
+
+    QueueJob {
+        url: options.url.clone(),
+        output_path,
+        label: format!("{} ({})", file.file_name, file.mime_type()),
+        kind: JobKind::Direct { checksum: options.checksum.clone() },
+        title: Some(file.file_name.clone()),
+        duration: None,
+        subtitles: None,
+        tags: None,
+        output_template: None,
+        rate_limit: options.rate_limit.clone(),
+    }
+}
+
+// Resolve the requested quality to a queue job using the same selection helpers as the GUI
+fn build_job(options: &GetOptions, formats: &[FormatOption], output_path: String, codec_preference: &[String]) -> Result<QueueJob> {
+    if let Some(audio_format) = &options.audio {
+        let audio = select_audio_format(formats, options.abr, options.ext.as_deref())
+            .ok_or_else(|| anyhow::anyhow!("No audio format matches the requested bitrate/extension"))?;
+
+        return Ok(QueueJob {
+            url: options.url.clone(),
+            output_path,
+            label: format!("Audio @ {} kbps ({})", audio.abr, audio_format.to_uppercase()),
+            kind: JobKind::Audio { format_id: audio.format_id.clone(), audio_format: audio_format.clone() },
+            title: None,
+            duration: None,
+            subtitles: None,
+            tags: None,
+            output_template: None,
+            rate_limit: None,
+        });
+    }
+
+    let codec_preference: Vec<String> = options.codec.iter()
+        .chain(codec_preference)
+        .cloned()
+        .collect();
+    let video = select_video_format(formats, options.height, options.fps, options.ext.as_deref(), &codec_preference)
+        .ok_or_else(|| anyhow::anyhow!("No video format matches the requested quality/FPS/extension"))?;
+
+    // Video-only streams get the best compatible audio merged in
+    let (format_id, merge_format) = if video.is_video_only() {
+        let container = options.container.clone().unwrap_or_else(|| {
+            if MERGE_CONTAINERS.contains(&video.ext.as_str()) { video.ext.clone() } else { "mkv".to_string() }
+        });
+        (build_format_selector(video, formats, &container), Some(container))
+    } else {
+        (video.format_id.clone(), None)
+    };
+
+    Ok(QueueJob {
+        url: options.url.clone(),
+        output_path,
+        label: format!("{} @ {} fps ({})", video.resolution, video.fps, merge_format.as_deref().unwrap_or(&video.ext).to_uppercase()),
+        kind: JobKind::Video { format_id, merge_format },
+        title: None,
+        duration: None,
+        subtitles: None,
+        tags: None,
+        output_template: None,
+        rate_limit: None,
+    })
+}
+
+fn download(mut options: GetOptions, settings: &Settings) -> Result<()> {
+    // A bare --audio uses the preferred audio format
+    if options.audio.as_deref() == Some("") {
+        options.audio = Some(settings.audio_format.clone());
+    }
+
+    if let Some(cookies) = &options.cookies {
+        set_auth_config(AuthConfig { cookies: cookies.clone(), ..load_auth_config(settings) });
+    }
+
+    if let Some(kind) = options.backend {
+        set_backend_config(BackendConfig { default: kind, rules: Vec::new(), ..settings.backend_config() });
+    }
+
+    let job = match direct_file_for(&options.url)? {
+        Some(file) => direct_job(&options, &file, settings),
+        None if options.checksum.is_some() => {
+            return Err(anyhow::anyhow!("--sha256 only applies to direct file links"));
+        },
+        None => {
+            require_ytdlp(settings)?;
+            media_job(&options, settings)?
+        },
+    };
+    println!("Downloading {} to {}", job.label, job.output_path);
+
+    // Run the job through the same queue the GUI uses and wait for its events
+    let (sender, receiver) = mpsc::channel();
+    let sender = Mutex::new(sender);
+    let queue = DownloadQueue::new(1, Arc::new(move |event| {
+        let _ = sender.lock().unwrap().send(event);
+    }));
+    let id = queue.add(job);
+
+    // Headless downloads show up in the GUI history as well
+    let record_history = |outcome| {
+        if let Some((job, output_file)) = queue.finished_job(id) {
+            History::load().record(HistoryEntry::new(&job, output_file, outcome));
+        }
+    };
+
+    for event in receiver {
+        match event {
+            QueueEvent::Progress(_, progress) => {
+                print!("\r\x1b[K[{:>5.1}%] {}", progress.overall_percent * 100.0, progress.status_message);
+                let _ = std::io::stdout().flush();
+            },
+            QueueEvent::Status(_, JobStatus::Completed) => {
+                record_history(HistoryOutcome::Completed);
+                println!("\nDownload complete!");
+                return Ok(());
+            },
+            QueueEvent::Status(_, JobStatus::Failed(reason)) => {
+                record_history(HistoryOutcome::Failed(reason.clone()));
+                println!();
+                return Err(anyhow::anyhow!("Download failed: {}", reason));
+            },
+            QueueEvent::Status(_, JobStatus::Cancelled) => {
+                record_history(HistoryOutcome::Cancelled);
+                println!();
+                return Err(anyhow::anyhow!("Download cancelled"));
+            },
+            QueueEvent::Status(_, _) => {},
+        }
+    }
+
+    Err(anyhow::anyhow!("Download ended without a result"))
+}
@@ -0,0 +1,135 @@
+use once_cell::sync::Lazy;
+use serde::{Serialize, Deserialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+use std::process::{self, Command};
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Browsers yt-dlp can read cookies from
+pub const COOKIE_BROWSERS: [&str; 9] = ["firefox", "chrome", "chromium", "brave", "edge", "opera", "vivaldi", "whale", "safari"];
+
+// Where yt-dlp takes cookies for members-only, age-restricted and private media from
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "source", rename_all = "lowercase")]
+pub enum CookieSource {
+    #[default]
+    None,
+    File { path: String },                       // Netscape cookies.txt
+    Browser { browser: String, profile: String }, // Empty profile = the browser's default
+}
+
+// Account for a site, the password lives in the credential store
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SiteLogin {
+    pub site: String,      // Domain, e.g. "vimeo.com", also matches its subdomains
+    pub username: String,
+}
+
+impl SiteLogin {
+    fn matches(&self, url: &str) -> bool {
+        let site = self.site.trim().trim_start_matches("www.").to_lowercase();
+        let Some(host) = url.split("://").nth(1)
+            .and_then(|rest| rest.split(['/', '?', '#']).next())
+            .and_then(|authority| authority.rsplit('@').next())
+            .and_then(|authority| authority.split(':').next()) else {
+            return false;
+        };
+        let host = host.to_lowercase();
+        !site.is_empty() && (host == site || host.ends_with(&format!(".{}", site)))
+    }
+}
+
+// Authentication applied to every fetch and download
+#[derive(Debug, Clone, Default)]
+pub struct AuthConfig {
+    pub cookies: CookieSource,
+    pub use_netrc: bool,                     // Credentials from ~/.netrc
+    pub logins: Vec<(SiteLogin, String)>,    // Logins with their passwords
+}
+
+static AUTH_CONFIG: Lazy<RwLock<AuthConfig>> = Lazy::new(|| RwLock::new(AuthConfig::default()));
+
+pub fn set_auth_config(config: AuthConfig) {
+    *AUTH_CONFIG.write().unwrap() = config;
+}
+
+// Stored login for the site of a URL, for requests made without yt-dlp
+pub(super) fn login_for(url: &str) -> Option<(String, String)> {
+    let config = AUTH_CONFIG.read().unwrap();
+    config.logins.iter()
+        .find(|(login, _)| login.matches(url))
+        .map(|(login, password)| (login.username.clone(), password.clone()))
+}
+
+// Private netrc file holding a stored login for one yt-dlp run, removed when dropped.
+// Keep it alive until the process has exited.
+pub(super) struct LoginFile {
+    path: PathBuf,
+}
+
+impl LoginFile {
+    fn create(username: &str, password: &str) -> io::Result<Self> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        // The runtime dir is only readable by the user, the file itself is 0600 either way
+        let dir = dirs::runtime_dir().unwrap_or_else(std::env::temp_dir);
+        let path = dir.join(format!("media_downloader-{}-{}.netrc", process::id(), COUNTER.fetch_add(1, Ordering::Relaxed)));
+
+        let mut file = OpenOptions::new().write(true).create_new(true).mode(0o600).open(&path)?;
+        let login = LoginFile { path };
+
+        // "default" matches whatever machine name the extractor asks for
+        writeln!(file, "default login {} password {}", netrc_token(username), netrc_token(password))?;
+        Ok(login)
+    }
+}
+
+impl Drop for LoginFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// Quote values with spaces or quotes so netrc reads them as one token
+fn netrc_token(value: &str) -> String {
+    if value.is_empty() || value.contains(|c: char| c.is_whitespace() || c == '"' || c == '\\') {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// Add cookie and login flags for the given URL. A stored login goes through a netrc file
+// so the password never shows up in the process list; the returned file must outlive the run.
+pub(super) fn add_auth_args(command: &mut Command, url: &str) -> io::Result<Option<LoginFile>> {
+    let config = AUTH_CONFIG.read().unwrap();
+
+    match &config.cookies {
+        CookieSource::None => {},
+        CookieSource::File { path } => {
+            command.arg("--cookies").arg(path);
+        },
+        CookieSource::Browser { browser, profile } => {
+            // BROWSER[:PROFILE]
+            let spec = if profile.trim().is_empty() { browser.clone() } else { format!("{}:{}", browser, profile.trim()) };
+            command.arg("--cookies-from-browser").arg(spec);
+        },
+    }
+
+    // A stored login wins over ~/.netrc for its site
+    match config.logins.iter().find(|(login, _)| login.matches(url)) {
+        Some((login, password)) => {
+            let file = LoginFile::create(&login.username, password)?;
+            command.arg("--netrc").arg("--netrc-location").arg(&file.path);
+            Ok(Some(file))
+        },
+        None if config.use_netrc => {
+            command.arg("--netrc");
+            Ok(None)
+        },
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,350 @@
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use serde::{Serialize, Deserialize};
+use std::sync::{Arc, RwLock};
+
+use super::control::DownloadControl;
+use super::download::YtdlpBackend;
+use super::events::DownloadEvent;
+use super::formats::{FormatOption, MediaProbe};
+use super::http::{HttpBackend, DirectFile, is_direct_link, fetch_direct_file};
+use super::media_info::MediaInfo;
+use super::queue::QueueJob;
+
+// Called from the backend's worker threads for every event of a running download
+pub type EventSink = Arc<dyn Fn(DownloadEvent) + Send + Sync>;
+
+// Fetches metadata and runs downloads for a URL; yt-dlp is the default implementation
+pub trait Backend: Send + Sync {
+    fn kind(&self) -> BackendKind;
+
+    // A single media with its formats, or the entries of a playlist
+    fn probe(&self, url: &str) -> Result<MediaProbe>;
+
+    // Metadata and formats of a single media, ignoring any playlist around it
+    fn fetch_formats(&self, url: &str) -> Result<(MediaInfo, Vec<FormatOption>)>;
+
+    // Run the job to completion and return the final event; pause, resume and cancel
+    // arrive through control while progress is reported to on_event
+    fn run(&self, job: &QueueJob, control: &DownloadControl, on_event: EventSink) -> DownloadEvent;
+}
+
+// Backends that can be chosen in the settings
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    #[default]
+    Ytdlp,
+    Http,    // Plain file links, see http.rs
+}
+
+impl BackendKind {
+    pub const ALL: [BackendKind; 2] = [BackendKind::Ytdlp, BackendKind::Http];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            BackendKind::Ytdlp => "ytdlp",
+            BackendKind::Http => "http",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            BackendKind::Ytdlp => "yt-dlp",
+            BackendKind::Http => "Direct HTTP",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<BackendKind> {
+        BackendKind::ALL.into_iter().find(|kind| kind.id() == id)
+    }
+}
+
+// Sends URLs matching a pattern to a specific backend
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackendRule {
+    pub pattern: String,   // '*' matches anything, a pattern without '*' matches anywhere in the URL
+    pub backend: BackendKind,
+}
+
+impl BackendRule {
+    pub fn matches(&self, url: &str) -> bool {
+        let pattern = self.pattern.trim().to_lowercase();
+        if pattern.is_empty() {
+            return false;
+        }
+
+        let url = url.trim().to_lowercase();
+        if pattern.contains('*') {
+            wildcard_match(&pattern, &url)
+        } else {
+            url.contains(&pattern)
+        }
+    }
+}
+
+// Anchored match where every '*' stands for any run of characters
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+
+    if text.len() < first.len() + last.len() || !text.starts_with(first) || !text.ends_with(last) {
+        return false;
+    }
+
+    // The leftmost occurrence of each middle part leaves the most room for the rest
+    let mut rest = &text[first.len()..text.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+// "*.example.com/* = ytdlp; vimeo = ytdlp" -> rules; empty means no rules
+pub fn parse_backend_rules(text: &str) -> Result<Vec<BackendRule>, String> {
+    text.split([';', '\n'])
+        .map(|entry| entry.trim())
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            // URLs may contain '=', backend ids don't
+            let Some((pattern, id)) = entry.rsplit_once('=') else {
+                return Err(format!("Invalid backend rule '{}', use PATTERN = BACKEND", entry));
+            };
+            let pattern = pattern.trim();
+            if pattern.is_empty() {
+                return Err(format!("Backend rule '{}' has no URL pattern", entry));
+            }
+
+            let backend = BackendKind::from_id(id.trim().to_lowercase().as_str())
+                .ok_or_else(|| format!("Unknown backend '{}', use one of {:?}",
+                    id.trim(), BackendKind::ALL.map(|kind| kind.id())))?;
+            Ok(BackendRule { pattern: pattern.to_string(), backend })
+        })
+        .collect()
+}
+
+// Inverse of parse_backend_rules
+pub fn format_backend_rules(rules: &[BackendRule]) -> String {
+    rules.iter()
+        .map(|rule| format!("{} = {}", rule.pattern, rule.backend.id()))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+// Which backend handles a URL: the first matching rule, then a detected direct file link,
+// otherwise the default
+#[derive(Debug, Clone)]
+pub struct BackendConfig {
+    pub default: BackendKind,
+    pub rules: Vec<BackendRule>,
+    pub detect_direct_links: bool,   // Ask the server whether an unknown link is a plain file
+    pub http_connections: usize,     // Parallel connections per file for the HTTP backend
+}
+
+impl Default for BackendConfig {
+    fn default() -> Self {
+        BackendConfig {
+            default: BackendKind::default(),
+            rules: Vec::new(),
+            detect_direct_links: true,
+            http_connections: 4,
+        }
+    }
+}
+
+static BACKEND_CONFIG: Lazy<RwLock<BackendConfig>> = Lazy::new(|| RwLock::new(BackendConfig::default()));
+
+pub fn set_backend_config(config: BackendConfig) {
+    *BACKEND_CONFIG.write().unwrap() = config;
+}
+
+pub(super) fn backend_config() -> BackendConfig {
+    BACKEND_CONFIG.read().unwrap().clone()
+}
+
+pub fn backend(kind: BackendKind) -> Arc<dyn Backend> {
+    match kind {
+        BackendKind::Ytdlp => Arc::new(YtdlpBackend),
+        BackendKind::Http => Arc::new(HttpBackend),
+    }
+}
+
+// May ask the server about the link, so call it off the UI thread
+pub fn backend_for(url: &str) -> Arc<dyn Backend> {
+    let config = backend_config();
+    let kind = match config.rules.iter().find(|rule| rule.matches(url)) {
+        Some(rule) => rule.backend,
+        None if config.default != BackendKind::Http && config.detect_direct_links && is_direct_link(url) => BackendKind::Http,
+        None => config.default,
+    };
+    backend(kind)
+}
+
+// Fetch a URL once, returning either its formats or its playlist entries
+pub fn probe_media(url: &str) -> Result<MediaProbe> {
+    backend_for(url).probe(url)
+}
+
+// The file behind a URL if it goes to the HTTP backend, None for media yt-dlp extracts
+pub fn direct_file_for(url: &str) -> Result<Option<DirectFile>> {
+    if backend_for(url).kind() != BackendKind::Http {
+        return Ok(None);
+    }
+    fetch_direct_file(url).map(Some)
+}
+
+// Fetch the metadata and formats of a single media
+pub fn fetch_available_formats(url: &str) -> Result<(MediaInfo, Vec<FormatOption>)> {
+    backend_for(url).fetch_formats(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    // Replays a fixed list of events instead of running a download
+    struct ScriptedBackend {
+        formats: Vec<FormatOption>,
+        events: Vec<DownloadEvent>,
+    }
+
+    impl Backend for ScriptedBackend {
+        fn kind(&self) -> BackendKind {
+            BackendKind::Ytdlp
+        }
+
+        fn probe(&self, url: &str) -> Result<MediaProbe> {
+            let (info, formats) = self.fetch_formats(url)?;
+            Ok(MediaProbe::Single(Box::new(info), formats))
+        }
+
+        fn fetch_formats(&self, url: &str) -> Result<(MediaInfo, Vec<FormatOption>)> {
+            let info = MediaInfo { webpage_url: url.to_string(), ..MediaInfo::default() };
+            Ok((info, self.formats.clone()))
+        }
+
+        fn run(&self, _job: &QueueJob, control: &DownloadControl, on_event: EventSink) -> DownloadEvent {
+            for event in &self.events {
+                if control.is_cancelled() {
+                    return DownloadEvent::Cancelled;
+                }
+                on_event(event.clone());
+            }
+            DownloadEvent::Finished { path: None }
+        }
+    }
+
+    fn job(url: &str) -> QueueJob {
+        QueueJob {
+            url: url.to_string(),
+            output_path: ".".to_string(),
+            kind: super::super::queue::JobKind::Direct { checksum: None },
+            label: String::new(),
+            title: None,
+            duration: None,
+            subtitles: None,
+            tags: None,
+            output_template: None,
+            rate_limit: None,
+        }
+    }
+
+    fn scripted() -> Arc<dyn Backend> {
+        Arc::new(ScriptedBackend {
+            formats: Vec::new(),
+            events: vec![DownloadEvent::Started, DownloadEvent::Stage("Merging formats...".to_string())],
+        })
+    }
+
+    #[test]
+    fn scripted_backend_reports_events_in_order() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink_seen = Arc::clone(&seen);
+        let sink: EventSink = Arc::new(move |event| sink_seen.lock().unwrap().push(format!("{:?}", event)));
+
+        let event = scripted().run(&job("https://example.com/a"), &DownloadControl::new(), sink);
+        assert!(matches!(event, DownloadEvent::Finished { path: None }));
+        assert_eq!(*seen.lock().unwrap(), vec!["Started".to_string(), "Stage(\"Merging formats...\")".to_string()]);
+    }
+
+    #[test]
+    fn scripted_backend_stops_when_cancelled() {
+        let control = DownloadControl::new();
+        control.cancel().unwrap();
+        let event = scripted().run(&job("https://example.com/a"), &control, Arc::new(|_| {}));
+        assert!(matches!(event, DownloadEvent::Cancelled));
+    }
+
+    #[test]
+    fn scripted_backend_probes_a_single_media() {
+        let probe = scripted().probe("https://example.com/a").unwrap();
+        assert!(matches!(probe, MediaProbe::Single(info, formats) if info.webpage_url == "https://example.com/a" && formats.is_empty()));
+    }
+
+    #[test]
+    fn wildcard_match_is_anchored() {
+        assert!(wildcard_match("*.example.com/*", "https://cdn.example.com/file.mp4"));
+        assert!(wildcard_match("https://*/files/*.zip", "https://host.org/files/a.zip"));
+        assert!(wildcard_match("*", ""));
+        assert!(!wildcard_match("*.example.com/*", "https://example.org/file.mp4"));
+        assert!(!wildcard_match("https://*.zip", "https://host.org/a.zip?x=1"));
+        // Overlapping start and end must not reuse the same characters
+        assert!(!wildcard_match("ab*ba", "aba"));
+    }
+
+    #[test]
+    fn rules_without_wildcard_match_anywhere() {
+        let rule = BackendRule { pattern: "Vimeo".to_string(), backend: BackendKind::Http };
+        assert!(rule.matches("https://player.vimeo.com/video/1"));
+        assert!(!rule.matches("https://example.com"));
+        assert!(!BackendRule { pattern: "  ".to_string(), backend: BackendKind::Http }.matches("https://example.com"));
+    }
+
+    #[test]
+    fn parses_backend_rules() {
+        let rules = parse_backend_rules("*.example.com/* = HTTP; vimeo = ytdlp\nhttps://a.org/?x=1 = http").unwrap();
+        assert_eq!(rules, vec![
+            BackendRule { pattern: "*.example.com/*".to_string(), backend: BackendKind::Http },
+            BackendRule { pattern: "vimeo".to_string(), backend: BackendKind::Ytdlp },
+            BackendRule { pattern: "https://a.org/?x=1".to_string(), backend: BackendKind::Http },
+        ]);
+        assert_eq!(format_backend_rules(&rules), "*.example.com/* = http; vimeo = ytdlp; https://a.org/?x=1 = http");
+        assert_eq!(parse_backend_rules(" ; \n").unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn rejects_invalid_backend_rules() {
+        assert!(parse_backend_rules("example.com").is_err());
+        assert!(parse_backend_rules(" = http").is_err());
+        assert!(parse_backend_rules("example.com = wget").is_err());
+    }
+
+    // The only test that changes the global config
+    #[test]
+    fn backend_for_follows_rules_then_default() {
+        set_backend_config(BackendConfig {
+            default: BackendKind::Ytdlp,
+            rules: parse_backend_rules("*.example.com/* = http; example.com/video = ytdlp").unwrap(),
+            detect_direct_links: false,
+            ..BackendConfig::default()
+        });
+        assert_eq!(backend_for("https://cdn.example.com/file.bin").kind(), BackendKind::Http);
+        assert_eq!(backend_for("https://youtube.com/watch?v=1").kind(), BackendKind::Ytdlp);
+
+        // The first matching rule wins
+        set_backend_config(BackendConfig {
+            default: BackendKind::Http,
+            rules: parse_backend_rules("example.com/video = ytdlp; *.example.com/* = http").unwrap(),
+            detect_direct_links: false,
+            ..BackendConfig::default()
+        });
+        assert_eq!(backend_for("https://www.example.com/video/1").kind(), BackendKind::Ytdlp);
+        assert_eq!(backend_for("https://other.org/file.bin").kind(), BackendKind::Http);
+
+        set_backend_config(BackendConfig::default());
+    }
+}
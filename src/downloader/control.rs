@@ -0,0 +1,163 @@
+use anyhow::{Result, Context};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Default)]
+struct ControlState {
+    pid: Option<u32>,
+    cancelled: bool,
+    paused: bool,
+    destinations: Vec<PathBuf>,
+    merged_file: Option<PathBuf>,
+}
+
+// Shared handle used by the UI thread to pause, resume or cancel a running download.
+// yt-dlp processes get signals, the HTTP backend checks the flags between chunks.
+#[derive(Debug, Clone, Default)]
+pub struct DownloadControl {
+    state: Arc<Mutex<ControlState>>,
+}
+
+impl DownloadControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Remember the process so signals can be sent to it (and to its ffmpeg children)
+    pub fn attach(&self, child: &Child) {
+        let mut state = self.state.lock().unwrap();
+        state.pid = Some(child.id());
+
+        // Cancelled while the process was starting, stop it before it downloads anything
+        if state.cancelled {
+            if let Err(e) = signal_process_group(child.id(), "TERM") {
+                eprintln!("Failed to stop cancelled download: {}", e);
+            }
+        }
+    }
+
+    // Track output files announced by yt-dlp so partial files can be removed on cancel
+    pub fn record_line(&self, line: &str) {
+        if let Some(index) = line.find("Destination: ") {
+            let path = line[index + "Destination: ".len()..].trim();
+            if !path.is_empty() {
+                let mut state = self.state.lock().unwrap();
+                let path = PathBuf::from(path);
+                if !state.destinations.contains(&path) {
+                    state.destinations.push(path);
+                }
+            }
+        } else if let Some(index) = line.find("Merging formats into \"") {
+            // [Merger] Merging formats into "path"
+            let path = line[index + "Merging formats into \"".len()..].trim().trim_end_matches('"');
+            self.state.lock().unwrap().merged_file = Some(PathBuf::from(path));
+        } else if let Some(path) = line.strip_prefix("[download] ").and_then(|l| l.strip_suffix(" has already been downloaded")) {
+            let mut state = self.state.lock().unwrap();
+            let path = PathBuf::from(path.trim());
+            if !state.destinations.contains(&path) {
+                state.destinations.push(path);
+            }
+        }
+    }
+
+    // Output file of a download that doesn't go through yt-dlp's output
+    pub fn add_destination(&self, path: &Path) {
+        let mut state = self.state.lock().unwrap();
+        if !state.destinations.iter().any(|destination| destination == path) {
+            state.destinations.push(path.to_path_buf());
+        }
+    }
+
+    // Final file of the download: the merged file, otherwise the last announced destination
+    pub fn output_file(&self) -> Option<PathBuf> {
+        let state = self.state.lock().unwrap();
+        state.merged_file.clone().or_else(|| state.destinations.last().cloned())
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.lock().unwrap().cancelled
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state.lock().unwrap().paused
+    }
+
+    pub fn pause(&self) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(pid) = state.pid {
+            signal_process_group(pid, "STOP")?;
+        }
+        state.paused = true;
+        Ok(())
+    }
+
+    pub fn resume(&self) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(pid) = state.pid {
+            signal_process_group(pid, "CONT")?;
+        }
+        state.paused = false;
+        Ok(())
+    }
+
+    // Terminate the whole process tree; the monitoring thread reports the cancellation
+    pub fn cancel(&self) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.cancelled = true;
+
+        if let Some(pid) = state.pid {
+            // A stopped process can't handle SIGTERM until it is continued
+            if state.paused {
+                signal_process_group(pid, "CONT")?;
+                state.paused = false;
+            }
+            signal_process_group(pid, "TERM")?;
+        }
+        Ok(())
+    }
+
+    // Remove .part, .ytdl and fragment files left behind by an interrupted download
+    pub fn cleanup_partial_files(&self) {
+        let destinations = self.state.lock().unwrap().destinations.clone();
+
+        for destination in destinations {
+            let (Some(folder), Some(file_name)) = (destination.parent(), destination.file_name()) else {
+                continue;
+            };
+            let file_name = file_name.to_string_lossy().to_string();
+            let partial_prefix = format!("{}.part", file_name);
+            let ytdl_file = format!("{}.ytdl", file_name);
+
+            let Ok(entries) = fs::read_dir(folder) else {
+                continue;
+            };
+
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.starts_with(&partial_prefix) || name == ytdl_file {
+                    eprintln!("Removing partial file: {}", entry.path().display());
+                    if let Err(e) = fs::remove_file(entry.path()) {
+                        eprintln!("Failed to remove {}: {}", entry.path().display(), e);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// yt-dlp is started in its own process group, so signalling -pid reaches ffmpeg too
+fn signal_process_group(pid: u32, signal: &str) -> Result<()> {
+    let status = Command::new("kill")
+        .arg(format!("-{}", signal))
+        .arg("--")
+        .arg(format!("-{}", pid))
+        .status()
+        .context("Failed to execute kill command")?;
+
+    if !status.success() {
+        return Err(anyhow::anyhow!("Failed to send SIG{} to process group {}", signal, pid));
+    }
+    Ok(())
+}
@@ -1,206 +1,201 @@
 use anyhow::Result;
-use std::process::{Command, Child, Stdio};
-use std::io::{BufReader, BufRead};
-
-pub fn download_media_with_format(url: &str, output_path: &str, format_id: &str) -> Result<Child> {
-    // Start the yt-dlp process with the selected format and capture stdout/stderr
-    let child = Command::new("yt-dlp")
-        .arg("-f")
-        .arg(format_id)
-        .arg(url)
-        .arg("-P")
-        .arg(output_path)
-        .arg("--newline")  // Force newlines to ensure consistent output
-        .arg("--progress")
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()?;
-
-    Ok(child)
-}
-
-pub fn download_audio_only(url: &str, output_path: &str, format_id: &str) -> Result<Child> {
-    // Start the yt-dlp process with the selected format and extract audio
-    let child = Command::new("yt-dlp")
-        .arg("-f")
-        .arg(format_id)
-        .arg("-x")  // Extract audio
-        .arg("--audio-format")
-        .arg("best")  // Use the best audio format available
-        .arg("--audio-quality")
-        .arg("0")   // Best quality
-        .arg("--audio-format-choices")
-        .arg("m4a/mp3/aac/opus/vorbis/webm")  // Provide format choices in preferred order
-        .arg(url)
-        .arg("-P")
-        .arg(output_path)
-        .arg("--newline")  // Force newlines to ensure consistent output
-        .arg("--progress")
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()?;
-
-    Ok(child)
-}
+use std::process::{Child, Command, Stdio};
+use std::io::{self, BufReader, BufRead, Read};
+use std::os::unix::process::CommandExt;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use super::backend::{Backend, BackendKind, EventSink};
+use super::control::DownloadControl;
+use super::events::{DownloadEvent, StderrTail};
+use super::ffmpeg::{check_ffmpeg, is_missing_ffmpeg_message, FFMPEG_HELP};
+use super::formats::{FormatOption, MediaProbe, fetch_ytdlp_formats, probe_ytdlp};
+use super::media_info::MediaInfo;
+use super::progress::{ProgressState, DownloadPhase, update_progress_state, add_progress_template};
+use super::queue::{QueueJob, JobKind};
+use super::ytdlp::ytdlp_command;
+use super::auth::{add_auth_args, LoginFile};
+use super::network::add_network_args;
+use super::subtitles::add_subtitle_args;
+use super::tagging::add_tag_args;
+
+// Default backend: every probe and download is a yt-dlp process
+pub(super) struct YtdlpBackend;
+
+impl Backend for YtdlpBackend {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Ytdlp
+    }
 
-// New function to download audio with specific format
-pub fn download_audio_with_format(url: &str, output_path: &str, format_id: &str, audio_format: &str) -> Result<Child> {
-    // Start the yt-dlp process with specific audio format extraction
-    let child = Command::new("yt-dlp")
-        .arg("-f")
-        .arg(format_id)
-        .arg("-x")  // Extract audio
-        .arg("--audio-format")
-        .arg(audio_format)  // Specific format like m4a, mp3, etc.
-        .arg("--audio-quality") 
-        .arg("0")  // Best quality
-        .arg(url)
-        .arg("-P")
-        .arg(output_path)
-        .arg("--newline")  // Force newlines to ensure consistent output
-        .arg("--progress")
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()?;
+    fn probe(&self, url: &str) -> Result<MediaProbe> {
+        probe_ytdlp(url)
+    }
 
-    Ok(child)
-}
+    fn fetch_formats(&self, url: &str) -> Result<(MediaInfo, Vec<FormatOption>)> {
+        fetch_ytdlp_formats(url)
+    }
 
-// Process phases for tracking download progress
-#[derive(Debug, PartialEq, Clone, Copy)]
-pub enum DownloadPhase {
-    Initializing,   // 0-5%
-    Downloading,    // 5-80%
-    Postprocessing, // 80-95%
-    Finalizing,     // 95-99%
-    Complete        // 100%
-}
+    fn run(&self, job: &QueueJob, control: &DownloadControl, on_event: EventSink) -> DownloadEvent {
+        // Don't download for minutes only to fail at the merge or conversion step
+        if let Err(reason) = check_ffmpeg(job) {
+            return DownloadEvent::Failed { reason, stderr_tail: Vec::new() };
+        }
 
-// Track the actual percentage and phase
-#[derive(Debug, Clone)]
-pub struct ProgressState {
-    pub phase: DownloadPhase,
-    pub download_percent: f64,
-    pub overall_percent: f64,
-    pub status_message: String,
-}
+        // Picking the backend may have asked the server first, the user can cancel meanwhile
+        if control.is_cancelled() {
+            return DownloadEvent::Cancelled;
+        }
 
-impl Default for ProgressState {
-    fn default() -> Self {
-        ProgressState {
-            phase: DownloadPhase::Initializing,
-            download_percent: 0.0,
-            overall_percent: 0.0,
-            status_message: "Initializing download...".to_string(),
+        // The login file has to stay until yt-dlp exits
+        match spawn_download(job) {
+            Ok((child, _login)) => watch(job, control, child, on_event),
+            Err(e) => DownloadEvent::Failed {
+                reason: format!("Error starting download: {}", e),
+                stderr_tail: Vec::new(),
+            },
         }
     }
 }
 
-// Parse a line from yt-dlp output and update the progress state
-pub fn update_progress_state(line: &str, state: &mut ProgressState) -> bool {
-    let mut updated = false;
+// Build the yt-dlp command line for a job: format selection, then the options shared by all jobs
+fn download_command(job: &QueueJob) -> io::Result<(Command, Option<LoginFile>)> {
+    let mut command = ytdlp_command();
 
-    // Initial phases
-    if line.contains("[info]") && !line.contains("Downloading") {
-        state.phase = DownloadPhase::Initializing;
-        state.overall_percent = 0.05;
-        state.status_message = "Gathering information...".to_string();
-        updated = true;
-    }
+    match &job.kind {
+        JobKind::Video { format_id, merge_format } => {
+            command.arg("-f").arg(format_id);
 
-    // During download phase - parse percentage
-    if line.contains("[download]") && line.contains("%") {
-        // Extract percentage from line
-        if let Some(percent_index) = line.find('%') {
-            // Find start of percentage number
-            let mut start_index = percent_index;
-            while start_index > 0 {
-                start_index -= 1;
-                let c = line.chars().nth(start_index).unwrap_or(' ');
-                if !c.is_digit(10) && c != '.' {
-                    start_index += 1;
-                    break;
-                }
+            // Container for merged video+audio selections (e.g. "137+140")
+            if let Some(container) = merge_format {
+                command.arg("--merge-output-format").arg(container);
             }
-            
-            // Extract and parse the percentage
-            if let Ok(percent) = line[start_index..percent_index].parse::<f64>() {
-                state.phase = DownloadPhase::Downloading;
-                state.download_percent = percent / 100.0;
-                
-                // Map download percentage (0-100%) to overall percentage (5-80%)
-                state.overall_percent = 0.05 + (state.download_percent * 0.75);
-                
-                // Extract speed and ETA if present
-                let mut status = format!("Downloading: {:.1}%", percent);
-                
-                if let Some(speed_index) = line.find("at ") {
-                    if let Some(eta_index) = line.find("ETA") {
-                        let speed = &line[speed_index + 3..line[speed_index..].find(" ETA").map_or(line.len(), |i| i + speed_index)];
-                        let eta = &line[eta_index + 4..];
-                        status = format!("Downloading: {:.1}% ({}, ETA: {})", percent, speed, eta);
-                    }
-                }
-                
-                state.status_message = status;
-                updated = true;
+
+            if let Some(subtitles) = &job.subtitles {
+                add_subtitle_args(&mut command, subtitles);
             }
-        }
+        },
+        JobKind::Audio { format_id, audio_format } => {
+            command
+                .arg("-f")
+                .arg(format_id)
+                .arg("-x")  // Extract audio
+                .arg("--audio-format")
+                .arg(audio_format)  // Specific format like m4a, mp3, etc.
+                .arg("--audio-quality")
+                .arg("0");  // Best quality
+
+            if let Some(tags) = &job.tags {
+                add_tag_args(&mut command, tags);
+            }
+        },
+        // yt-dlp's generic extractor finds the file on its own
+        JobKind::Direct { .. } => {},
     }
 
-    // Full download complete (100%)
-    if line.contains("[download]") && line.contains("100%") {
-        state.phase = DownloadPhase::Postprocessing;
-        state.download_percent = 1.0;
-        state.overall_percent = 0.8;
-        state.status_message = "Download complete, processing...".to_string();
-        updated = true;
+    if let Some(template) = &job.output_template {
+        template.add_args(&mut command);
     }
 
-    // Post-processing phases
-    if line.contains("[Merger]") {
-        state.phase = DownloadPhase::Postprocessing;
-        state.overall_percent = 0.85;
-        state.status_message = "Merging formats...".to_string();
-        updated = true;
-    }
+    command
+        .arg(&job.url)
+        .arg("-P")
+        .arg(&job.output_path);
 
-    if line.contains("[ExtractAudio]") {
-        state.phase = DownloadPhase::Postprocessing;
-        state.overall_percent = 0.85;
-        state.status_message = "Extracting audio...".to_string();
-        updated = true;
-    }
+    let login = add_auth_args(&mut command, &job.url)?;
+    add_network_args(&mut command, job.rate_limit.as_deref());
 
-    if line.contains("[ffmpeg]") {
-        state.phase = DownloadPhase::Postprocessing; 
-        state.overall_percent = 0.9;
-        state.status_message = "Processing with ffmpeg...".to_string();
-        updated = true;
-    }
+    // JSON progress lines for update_progress_state
+    add_progress_template(&mut command);
+    Ok((command, login))
+}
 
-    if line.contains("Deleting original file") {
-        state.phase = DownloadPhase::Finalizing;
-        state.overall_percent = 0.95;
-        state.status_message = "Cleaning up temporary files...".to_string();
-        updated = true;
-    }
+// Start the yt-dlp process for a job and capture stdout/stderr
+fn spawn_download(job: &QueueJob) -> Result<(Child, Option<LoginFile>)> {
+    let (mut command, login) = download_command(job)?;
+    let child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0)  // Own process group so cancel/pause also reach ffmpeg
+        .spawn()?;
+
+    Ok((child, login))
+}
+
+// Read both output streams until the process exits and return the final event
+fn watch(job: &QueueJob, control: &DownloadControl, mut child: Child, on_event: EventSink) -> DownloadEvent {
+    control.attach(&child);
+    on_event(DownloadEvent::Started);
+
+    // Both streams feed the same progress state so the bar doesn't jump around
+    let format_id = match &job.kind {
+        JobKind::Video { format_id, .. } | JobKind::Audio { format_id, .. } => format_id.as_str(),
+        JobKind::Direct { .. } => "best",
+    };
+    let progress_state = Arc::new(Mutex::new(ProgressState::for_format(format_id)));
+    let stderr_tail = StderrTail::default();
+
+    let mut readers = Vec::new();
+    let streams: Vec<(Box<dyn Read + Send>, bool)> = vec![
+        (Box::new(child.stdout.take().expect("Failed to capture stdout")), false),
+        (Box::new(child.stderr.take().expect("Failed to capture stderr")), true),
+    ];
+
+    for (stream, is_stderr) in streams {
+        let control = control.clone();
+        let progress_state = Arc::clone(&progress_state);
+        let stderr_tail = stderr_tail.clone();
+        let on_event = Arc::clone(&on_event);
+
+        readers.push(thread::spawn(move || {
+            for line in BufReader::new(stream).lines().map_while(io::Result::ok) {
+                control.record_line(&line);
+                if is_stderr {
+                    stderr_tail.push(&line);
+                }
 
-    // Final completion indicators
-    if line.contains("has already been downloaded") {
-        state.phase = DownloadPhase::Complete;
-        state.overall_percent = 1.0;
-        state.status_message = "File was already downloaded".to_string();
-        updated = true;
+                let mut state = progress_state.lock().unwrap();
+                let previous_phase = state.phase;
+                if update_progress_state(&line, &mut state) {
+                    if state.phase != previous_phase {
+                        on_event(DownloadEvent::Stage(state.status_message.clone()));
+                    }
+                    on_event(DownloadEvent::Progress(state.clone()));
+                }
+            }
+        }));
     }
 
-    if (line.contains("Downloaded") && line.contains("bytes") && !line.contains("%")) || 
-       (line.contains("Destination: ") && line.contains(".") && !line.contains("[download]")) {
-        state.phase = DownloadPhase::Complete;
-        state.overall_percent = 1.0;
-        state.status_message = "Download complete!".to_string();
-        updated = true;
+    let status = child.wait();
+
+    // Let the readers drain so the stderr tail is complete
+    for reader in readers {
+        let _ = reader.join();
     }
 
-    updated
-} 
\ No newline at end of file
+    match status {
+        Ok(_) if control.is_cancelled() => {
+            // Killed by cancel, remove leftovers
+            control.cleanup_partial_files();
+            DownloadEvent::Cancelled
+        },
+        Ok(status) if status.success() => {
+            let mut state = progress_state.lock().unwrap();
+            state.phase = DownloadPhase::Complete;
+            state.overall_percent = 1.0;
+            state.status_message = "Download complete!".to_string();
+            on_event(DownloadEvent::Progress(state.clone()));
+
+            DownloadEvent::Finished { path: control.output_file() }
+        },
+        Ok(status) => {
+            let stderr_tail = stderr_tail.lines();
+            let reason = if stderr_tail.iter().any(|line| is_missing_ffmpeg_message(line)) {
+                format!("ffmpeg not found. {}", FFMPEG_HELP)
+            } else {
+                status.to_string()
+            };
+            DownloadEvent::Failed { reason, stderr_tail }
+        },
+        Err(e) => DownloadEvent::Failed { reason: e.to_string(), stderr_tail: stderr_tail.lines() },
+    }
+}
@@ -0,0 +1,39 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use super::progress::ProgressState;
+
+// Number of stderr lines kept to explain a failed download
+const STDERR_TAIL_LINES: usize = 10;
+
+// Everything a running download reports to its observer
+#[derive(Debug, Clone)]
+pub enum DownloadEvent {
+    Started,
+    Progress(ProgressState),
+    Stage(String),                                      // A new phase began, e.g. "Merging formats..."
+    Finished { path: Option<PathBuf> },                 // Final file if yt-dlp announced it
+    Failed { reason: String, stderr_tail: Vec<String> },
+    Cancelled,
+}
+
+// Last lines written to stderr, shared between the reader thread and the waiter
+#[derive(Clone, Default)]
+pub(super) struct StderrTail {
+    lines: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl StderrTail {
+    pub(super) fn push(&self, line: &str) {
+        let mut lines = self.lines.lock().unwrap();
+        if lines.len() == STDERR_TAIL_LINES {
+            lines.pop_front();
+        }
+        lines.push_back(line.to_string());
+    }
+
+    pub(super) fn lines(&self) -> Vec<String> {
+        self.lines.lock().unwrap().iter().cloned().collect()
+    }
+}
@@ -0,0 +1,151 @@
+use once_cell::sync::Lazy;
+use std::process::Command;
+use std::sync::RwLock;
+
+use super::queue::{QueueJob, JobKind};
+use super::subtitles::SubtitleMode;
+
+pub const FFMPEG_HELP: &str = "Install ffmpeg (which includes ffprobe) with your package manager, \
+e.g. \"sudo apt install ffmpeg\" or \"sudo pacman -S ffmpeg\", then try again.";
+
+// ffmpeg and ffprobe versions, None if the tool is missing or broken
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FfmpegTools {
+    pub ffmpeg: Option<String>,
+    pub ffprobe: Option<String>,
+}
+
+impl FfmpegTools {
+    // Run both tools from PATH like yt-dlp does
+    pub fn detect() -> FfmpegTools {
+        FfmpegTools {
+            ffmpeg: tool_version("ffmpeg"),
+            ffprobe: tool_version("ffprobe"),
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.ffmpeg.is_some() && self.ffprobe.is_some()
+    }
+
+    // "ffmpeg 6.1.1, ffprobe 6.1.1" or what is missing
+    pub fn description(&self) -> String {
+        let describe = |name: &str, version: &Option<String>| match version {
+            Some(version) => format!("{} {}", name, version),
+            None => format!("{} not found", name),
+        };
+        format!("{}, {}", describe("ffmpeg", &self.ffmpeg), describe("ffprobe", &self.ffprobe))
+    }
+}
+
+// "ffmpeg version 6.1.1-3ubuntu5 Copyright ..." -> "6.1.1-3ubuntu5"
+fn tool_version(name: &str) -> Option<String> {
+    let output = Command::new(name).arg("-version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let version = stdout.lines().next()?
+        .split_whitespace()
+        .skip_while(|word| *word != "version")
+        .nth(1)
+        .unwrap_or("unknown version");
+    Some(version.to_string())
+}
+
+// Detected once on first use; refresh_ffmpeg_tools picks up a later install
+static FFMPEG_TOOLS: Lazy<RwLock<FfmpegTools>> = Lazy::new(|| RwLock::new(FfmpegTools::detect()));
+
+pub fn ffmpeg_tools() -> FfmpegTools {
+    FFMPEG_TOOLS.read().unwrap().clone()
+}
+
+pub fn refresh_ffmpeg_tools() -> FfmpegTools {
+    let tools = FfmpegTools::detect();
+    *FFMPEG_TOOLS.write().unwrap() = tools.clone();
+    tools
+}
+
+// yt-dlp steps that run ffmpeg after the download
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FfmpegFeature {
+    Merge,            // Video-only stream + audio stream
+    AudioExtraction,  // -x, also needs ffprobe
+    EmbedSubtitles,
+    EmbedThumbnail,
+    EmbedMetadata,
+}
+
+impl FfmpegFeature {
+    pub fn label(&self) -> &'static str {
+        match self {
+            FfmpegFeature::Merge => "merging video and audio streams",
+            FfmpegFeature::AudioExtraction => "extracting and converting audio",
+            FfmpegFeature::EmbedSubtitles => "embedding subtitles",
+            FfmpegFeature::EmbedThumbnail => "embedding cover art",
+            FfmpegFeature::EmbedMetadata => "writing audio tags",
+        }
+    }
+}
+
+// What a job will ask of ffmpeg, following the flags the download functions pass
+pub fn required_features(job: &QueueJob) -> Vec<FfmpegFeature> {
+    let mut features = Vec::new();
+    match &job.kind {
+        JobKind::Video { format_id, .. } => {
+            // "137+140" or "bestvideo+bestaudio/best" download two streams
+            if format_id.contains('+') {
+                features.push(FfmpegFeature::Merge);
+            }
+            if job.subtitles.as_ref().is_some_and(|subtitles| subtitles.mode == SubtitleMode::Embed) {
+                features.push(FfmpegFeature::EmbedSubtitles);
+            }
+        },
+        JobKind::Audio { .. } => {
+            features.push(FfmpegFeature::AudioExtraction);
+            if let Some(tags) = &job.tags {
+                if tags.embed_cover {
+                    features.push(FfmpegFeature::EmbedThumbnail);
+                }
+                if tags.embed_metadata {
+                    features.push(FfmpegFeature::EmbedMetadata);
+                }
+            }
+        },
+        JobKind::Direct { .. } => {},
+    }
+    features
+}
+
+// Fail before yt-dlp starts instead of after the download, when the merge or conversion would break
+pub fn check_ffmpeg(job: &QueueJob) -> Result<(), String> {
+    let features = required_features(job);
+    if features.is_empty() {
+        return Ok(());
+    }
+
+    // Only look again when something is missing, ffmpeg may have been installed meanwhile
+    let mut tools = ffmpeg_tools();
+    if !tools.is_complete() {
+        tools = refresh_ffmpeg_tools();
+    }
+
+    let needs_ffprobe = features.contains(&FfmpegFeature::AudioExtraction);
+    let missing = match (&tools.ffmpeg, &tools.ffprobe) {
+        (None, None) if needs_ffprobe => "ffmpeg and ffprobe were",
+        (None, _) => "ffmpeg was",
+        (Some(_), None) if needs_ffprobe => "ffprobe was",
+        _ => return Ok(()),
+    };
+
+    let labels: Vec<&str> = features.iter().map(|feature| feature.label()).collect();
+    Err(format!("This download needs ffmpeg for {}, but {} not found. {}", labels.join(", "), missing, FFMPEG_HELP))
+}
+
+// yt-dlp messages printed when a postprocessor finds no ffmpeg
+pub(super) fn is_missing_ffmpeg_message(line: &str) -> bool {
+    let line = line.to_lowercase();
+    (line.contains("ffmpeg") || line.contains("ffprobe"))
+        && (line.contains("not found") || line.contains("not installed"))
+}
@@ -1,10 +1,17 @@
 use anyhow::{Result, Context};
-use std::process::Command;
 use serde_json::Value;
 use std::str;
 use std::collections::HashSet;
+use serde::Serialize;
 
-#[derive(Debug, Clone)]
+use super::http::DirectFile;
+use super::media_info::{MediaInfo, parse_media_info};
+use super::playlist::{Playlist, parse_playlist};
+use super::ytdlp::ytdlp_command;
+use super::auth::add_auth_args;
+use super::network::add_network_args;
+
+#[derive(Debug, Clone, Serialize)]
 pub struct FormatOption {
     pub format_id: String,
     pub resolution: String,
@@ -15,33 +22,127 @@ pub struct FormatOption {
     pub acodec: String,
     pub abr: u32,     // Audio bitrate in kbps
     pub is_audio_only: bool,
+    pub filesize: Option<u64>,         // Exact size in bytes when the site reports it
+    pub filesize_approx: Option<u64>,  // yt-dlp's estimate, or one derived from tbr and duration
+    pub tbr: Option<f64>,              // Total bitrate in kbps
+}
+
+// Containers yt-dlp can merge separate video and audio streams into
+pub const MERGE_CONTAINERS: [&str; 3] = ["mp4", "mkv", "webm"];
+
+// Video codecs as named by FormatOption::codec_label, most compatible first
+pub const VIDEO_CODECS: [&str; 4] = ["H.264", "VP9", "AV1", "H.265"];
+
+// How widely a video codec can be decoded in hardware and played back
+pub fn codec_compatibility_hint(codec: &str) -> &'static str {
+    match codec {
+        "H.264" => "H.264 - Plays everywhere, hardware decoding on practically all devices.",
+        "VP9" => "VP9 - Smaller than H.264, hardware decoding on most GPUs from 2016 on.",
+        "AV1" => "AV1 - Smallest files, hardware decoding only on recent GPUs (2020+).",
+        "H.265" => "H.265 - Efficient with common hardware decoding, limited browser support.",
+        _ => "Uncommon codec, may need software decoding.",
+    }
+}
+
+// Position of a codec in the preference order; unlisted codecs come last
+pub fn codec_rank(codec: &str, preference: &[String]) -> usize {
+    preference.iter()
+        .position(|preferred| preferred.eq_ignore_ascii_case(codec))
+        .unwrap_or(preference.len())
+}
+
+impl FormatOption {
+    // Video streams without an audio track (typical for DASH formats above 720p)
+    pub fn is_video_only(&self) -> bool {
+        !self.is_audio_only && self.acodec == "none"
+    }
+    
+    // Best known size in bytes, exact if available
+    pub fn estimated_size(&self) -> Option<u64> {
+        self.filesize.or(self.filesize_approx)
+    }
+    
+    // Short codec name for labels, e.g. "VP9" or "H.264"
+    pub fn codec_label(&self) -> String {
+        let codec = if self.is_audio_only { &self.acodec } else { &self.vcodec };
+        let family = codec.split('.').next().unwrap_or(codec).to_lowercase();
+        
+        match family.as_str() {
+            "avc1" | "avc3" | "h264" => "H.264".to_string(),
+            "hev1" | "hvc1" | "h265" | "hevc" => "H.265".to_string(),
+            "vp09" | "vp9" => "VP9".to_string(),
+            "vp8" => "VP8".to_string(),
+            "av01" | "av1" => "AV1".to_string(),
+            "mp4a" => "AAC".to_string(),
+            _ => family.to_uppercase(),
+        }
+    }
+    
+    // Height parsed from the "WIDTHxHEIGHT" resolution, 0 for audio
+    pub fn height(&self) -> u32 {
+        self.resolution.split('x').nth(1)
+            .and_then(|h| h.parse().ok())
+            .unwrap_or(0)
+    }
 }
 
 #[derive(Debug)]
 pub enum FormatMessage {
-    Formats(Vec<FormatOption>),
+    Formats(Box<MediaInfo>, Vec<FormatOption>),
+    Playlist(Playlist),
+    Direct(Box<DirectFile>),
     Error,
 }
 
-pub fn fetch_available_formats(url: &str) -> Result<Vec<FormatOption>> {
-    println!("Fetching formats for URL: {}", url);
+// Result of probing a URL that may be a single video or a playlist/channel
+#[derive(Debug)]
+pub enum MediaProbe {
+    Single(Box<MediaInfo>, Vec<FormatOption>),
+    Playlist(Playlist),
+    Direct(Box<DirectFile>),   // Plain file link, downloaded without yt-dlp
+}
+
+// Fetch the metadata and formats of a single video with yt-dlp
+pub(super) fn fetch_ytdlp_formats(url: &str) -> Result<(MediaInfo, Vec<FormatOption>)> {
+    eprintln!("Fetching formats for URL: {}", url);
     
-    // Use --list-formats to get a full list of all available formats
-    let output = Command::new("yt-dlp")
-        .arg("--dump-json")
-        .arg("--no-playlist")
+    let json = run_yt_dlp_json(url, &["--dump-json", "--no-playlist"])?;
+    Ok((parse_media_info(&json), parse_formats(&json)))
+}
+
+// Fetch a URL once with yt-dlp, returning either its formats or its playlist entries
+pub(super) fn probe_ytdlp(url: &str) -> Result<MediaProbe> {
+    eprintln!("Probing URL: {}", url);
+    
+    // --flat-playlist lists playlist entries without resolving every video,
+    // while single videos still come back with their full format list
+    let json = run_yt_dlp_json(url, &["-J", "--flat-playlist"])?;
+    
+    match parse_playlist(&json) {
+        Some(playlist) => Ok(MediaProbe::Playlist(playlist)),
+        None => Ok(MediaProbe::Single(Box::new(parse_media_info(&json)), parse_formats(&json))),
+    }
+}
+
+fn run_yt_dlp_json(url: &str, args: &[&str]) -> Result<Value> {
+    let mut command = ytdlp_command();
+    command.args(args);
+    let _login = add_auth_args(&mut command, url).context("Failed to write the login for yt-dlp")?;
+    add_network_args(&mut command, None);
+
+    let output = command
         .arg(url)
         .output()
-        .context("Failed to execute yt-dlp command")?;
+        .context("Failed to execute yt-dlp, set up a working executable in Preferences")?;
     
     if !output.status.success() {
         let stderr = str::from_utf8(&output.stderr)
             .unwrap_or("Unable to decode error output");
-        println!("yt-dlp error: {}", stderr);
+        eprintln!("yt-dlp error: {}", stderr);
         return Err(anyhow::anyhow!("yt-dlp failed: {}", stderr));
     }
     
-    println!("Successfully fetched data from yt-dlp");
+    eprintln!("Successfully fetched data from yt-dlp");
     
     let json_str = str::from_utf8(&output.stdout)
         .context("Failed to decode yt-dlp output")?;
@@ -49,11 +150,16 @@ pub fn fetch_available_formats(url: &str) -> Result<Vec<FormatOption>> {
     let json: Value = serde_json::from_str(json_str)
         .context("Failed to parse JSON from yt-dlp output")?;
     
+    Ok(json)
+}
+
+fn parse_formats(json: &Value) -> Vec<FormatOption> {
     let mut formats = Vec::new();
     let mut found_extensions = HashSet::new();
+    let duration = json["duration"].as_f64();
     
     if let Some(format_array) = json["formats"].as_array() {
-        println!("Found {} format entries", format_array.len());
+        eprintln!("Found {} format entries", format_array.len());
         
         // First, log all available extensions for debugging
         for format in format_array.iter() {
@@ -62,10 +168,10 @@ pub fn fetch_available_formats(url: &str) -> Result<Vec<FormatOption>> {
             }
         }
         
-        println!("Available extensions in response: {:?}", found_extensions);
+        eprintln!("Available extensions in response: {:?}", found_extensions);
         
         // Log all available audio codecs and bitrates
-        println!("\nAudio format details from API response:");
+        eprintln!("\nAudio format details from API response:");
         for (i, format) in format_array.iter().enumerate() {
             let acodec = format["acodec"].as_str().unwrap_or("none");
             if acodec != "none" {
@@ -76,11 +182,11 @@ pub fn fetch_available_formats(url: &str) -> Result<Vec<FormatOption>> {
                 let format_note = format["format_note"].as_str().unwrap_or("");
                 let ext = format["ext"].as_str().unwrap_or("");
                 
-                println!("Format {}: id={}, codec={}, ext={}, abr={}kbps, tbr={}kbps, asr={}, note='{}'",
+                eprintln!("Format {}: id={}, codec={}, ext={}, abr={}kbps, tbr={}kbps, asr={}, note='{}'",
                     i, format_id, acodec, ext, abr, tbr, asr, format_note);
             }
         }
-        println!("End of audio format details\n");
+        eprintln!("End of audio format details\n");
         
         // Process each format entry
         for (i, format) in format_array.iter().enumerate() {
@@ -90,7 +196,7 @@ pub fn fetch_available_formats(url: &str) -> Result<Vec<FormatOption>> {
             
             // Skip formats with no audio and no video
             if vcodec == "none" && acodec == "none" {
-                println!("Skipping format {} - no audio or video codec", i);
+                eprintln!("Skipping format {} - no audio or video codec", i);
                 continue;
             }
             
@@ -137,11 +243,20 @@ pub fn fetch_available_formats(url: &str) -> Result<Vec<FormatOption>> {
             // Get format note (quality description)
             let format_note = format["format_note"].as_str().unwrap_or("").to_string();
             
+            // Sizes are missing for many streams, estimate them from the bitrate if possible
+            let tbr = format["tbr"].as_f64();
+            let filesize = format["filesize"].as_u64();
+            let filesize_approx = format["filesize_approx"].as_u64()
+                .or_else(|| match (tbr, duration) {
+                    (Some(tbr), Some(duration)) if filesize.is_none() => Some((tbr * 1000.0 / 8.0 * duration) as u64),
+                    _ => None,
+                });
+            
             if is_audio_only {
-                println!("Adding audio format: id={}, bitrate={}kbps, ext={}, acodec={}",
+                eprintln!("Adding audio format: id={}, bitrate={}kbps, ext={}, acodec={}",
                     format_id, effective_abr, ext, acodec);
             } else {
-                println!("Adding video format: id={}, resolution={}, fps={}, ext={}, vcodec={}",
+                eprintln!("Adding video format: id={}, resolution={}, fps={}, ext={}, vcodec={}",
                     format_id, resolution, fps, ext, vcodec);
             }
             
@@ -155,11 +270,14 @@ pub fn fetch_available_formats(url: &str) -> Result<Vec<FormatOption>> {
                 acodec,
                 abr: effective_abr,
                 is_audio_only,
+                filesize,
+                filesize_approx,
+                tbr,
             });
         }
     } else {
-        println!("No formats array found in JSON response");
-        println!("Full response: {}", json_str);
+        eprintln!("No formats array found in JSON response");
+        eprintln!("Full response: {}", json);
     }
     
     // Log all unique extensions found
@@ -167,7 +285,7 @@ pub fn fetch_available_formats(url: &str) -> Result<Vec<FormatOption>> {
     for format in &formats {
         unique_exts.insert(&format.ext);
     }
-    println!("Unique extensions in processed formats: {:?}", unique_exts);
+    eprintln!("Unique extensions in processed formats: {:?}", unique_exts);
     
     // Check if all formats have zero FPS
     let all_zero_fps = formats.iter()
@@ -178,9 +296,9 @@ pub fn fetch_available_formats(url: &str) -> Result<Vec<FormatOption>> {
     if !all_zero_fps {
         // Filter out formats with 0 FPS for video formats only
         formats.retain(|f| f.is_audio_only || f.fps > 0);
-        println!("After filtering zero FPS: {} formats remain", formats.len());
+        eprintln!("After filtering zero FPS: {} formats remain", formats.len());
     } else {
-        println!("All video formats have zero FPS - skipping FPS filtering");
+        eprintln!("All video formats have zero FPS - skipping FPS filtering");
     }
     
     // Sort by resolution (height), FPS, and then file extension
@@ -210,5 +328,103 @@ pub fn fetch_available_formats(url: &str) -> Result<Vec<FormatOption>> {
         }
     });
     
-    Ok(formats)
-} 
\ No newline at end of file
+    formats
+} 
+
+// List merge containers for a video-only stream, with its own extension first
+pub fn merge_containers_for(video_ext: &str) -> Vec<&'static str> {
+    let mut containers: Vec<&'static str> = MERGE_CONTAINERS.iter()
+        .copied()
+        .filter(|c| *c == video_ext)
+        .collect();
+
+    for container in MERGE_CONTAINERS.iter() {
+        if *container != video_ext {
+            containers.push(container);
+        }
+    }
+
+    containers
+}
+
+// Pick the best audio stream to pair with a video-only stream in the given container
+pub fn select_audio_for_video<'a>(formats: &'a [FormatOption], container: &str) -> Option<&'a FormatOption> {
+    let audio_formats: Vec<&FormatOption> = formats.iter()
+        .filter(|f| f.is_audio_only)
+        .collect();
+
+    // Prefer codecs the container can hold without re-encoding
+    let compatible = |f: &&FormatOption| match container {
+        "mp4" => f.ext == "m4a" || f.acodec.starts_with("mp4a"),
+        "webm" => f.ext == "webm" || f.acodec.starts_with("opus") || f.acodec.starts_with("vorbis"),
+        _ => true,
+    };
+
+    audio_formats.iter()
+        .copied()
+        .filter(compatible)
+        .max_by_key(|f| f.abr)
+        .or_else(|| audio_formats.iter().copied().max_by_key(|f| f.abr))
+}
+
+// Build the yt-dlp format selector for a video stream, adding an audio stream when needed
+pub fn build_format_selector(video: &FormatOption, formats: &[FormatOption], container: &str) -> String {
+    if !video.is_video_only() {
+        return video.format_id.clone();
+    }
+
+    match select_audio_for_video(formats, container) {
+        Some(audio) => {
+            eprintln!("Pairing video format {} with audio format {} ({} kbps, {})",
+                video.format_id, audio.format_id, audio.abr, audio.acodec);
+            format!("{}+{}", video.format_id, audio.format_id)
+        },
+        None => {
+            // Let yt-dlp pick the audio stream itself
+            eprintln!("No audio-only format found, using bestaudio for video format {}", video.format_id);
+            format!("{}+bestaudio", video.format_id)
+        }
+    }
+}
+
+// Combined size of the streams in a selector like "137+140"; None if any stream size is unknown
+pub fn estimate_selector_size(selector: &str, formats: &[FormatOption]) -> Option<u64> {
+    selector.split('+')
+        .map(|id| formats.iter().find(|f| f.format_id == id).and_then(|f| f.estimated_size()))
+        .sum()
+}
+
+// Pick a video stream by height, FPS and extension; unset criteria take the best available.
+// A height with no exact match falls back to the tallest stream below it, and ties between
+// codecs go to the first one in codec_preference.
+pub fn select_video_format<'a>(formats: &'a [FormatOption], height: Option<u32>, fps: Option<u32>, ext: Option<&str>, codec_preference: &[String]) -> Option<&'a FormatOption> {
+    let candidates: Vec<&FormatOption> = formats.iter()
+        .filter(|f| !f.is_audio_only)
+        .filter(|f| fps.is_none_or(|fps| f.fps == fps))
+        .filter(|f| ext.is_none_or(|ext| f.ext.eq_ignore_ascii_case(ext)))
+        .collect();
+    
+    let best = |list: Vec<&'a FormatOption>| list.into_iter()
+        .max_by_key(|f| (f.height(), f.fps, std::cmp::Reverse(codec_rank(&f.codec_label(), codec_preference)), !f.is_video_only()));
+    
+    match height {
+        Some(height) => {
+            let exact: Vec<_> = candidates.iter().copied().filter(|f| f.height() == height).collect();
+            if !exact.is_empty() {
+                best(exact)
+            } else {
+                best(candidates.into_iter().filter(|f| f.height() < height).collect())
+            }
+        },
+        None => best(candidates),
+    }
+}
+
+// Pick an audio-only stream by bitrate and extension; the bitrate is an upper bound
+pub fn select_audio_format<'a>(formats: &'a [FormatOption], abr: Option<u32>, ext: Option<&str>) -> Option<&'a FormatOption> {
+    formats.iter()
+        .filter(|f| f.is_audio_only)
+        .filter(|f| ext.is_none_or(|ext| f.ext.eq_ignore_ascii_case(ext)))
+        .filter(|f| abr.is_none_or(|abr| f.abr <= abr))
+        .max_by_key(|f| f.abr)
+}
\ No newline at end of file
@@ -0,0 +1,746 @@
+use anyhow::{Result, Context};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use once_cell::sync::Lazy;
+use percent_encoding::percent_decode_str;
+use reqwest::header::{HeaderMap, HeaderName, CONTENT_DISPOSITION, CONTENT_RANGE, CONTENT_TYPE, RANGE};
+use reqwest::{Client, RequestBuilder, StatusCode};
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::future::Future;
+use std::io::{Read, SeekFrom};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+use tokio::task::JoinSet;
+
+use super::auth::login_for;
+use super::backend::{Backend, BackendKind, EventSink, backend_config};
+use super::control::DownloadControl;
+use super::events::DownloadEvent;
+use super::formats::{FormatOption, MediaProbe};
+use super::media_info::MediaInfo;
+use super::network::{network_options, rate_limit_bytes, IpVersion};
+use super::progress::{ProgressState, ProgressEvent, DownloadProgress, DownloadPhase};
+use super::queue::{QueueJob, JobKind};
+use super::sites::{SupportedSites, url_host};
+
+// Connections only pay off for larger files, each one gets at least this much
+const MIN_SEGMENT_SIZE: u64 = 4 * 1024 * 1024;
+
+// How often progress is reported and the resume state is written
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+// Format id used for progress and history, direct files have a single stream
+const DIRECT_FORMAT_ID: &str = "direct";
+
+// A file behind a plain http(s) link, as described by the server
+#[derive(Debug, Clone)]
+pub struct DirectFile {
+    pub url: String,               // After redirects
+    pub file_name: String,
+    pub content_type: String,
+    pub size: Option<u64>,         // Bytes, None if the server doesn't say
+    pub resumable: bool,           // Server answers Range requests
+    pub digest: Option<String>,    // SHA-256 announced in a Digest header, lowercase hex
+}
+
+impl DirectFile {
+    // Metadata for the preview card and the history
+    pub fn media_info(&self) -> MediaInfo {
+        MediaInfo {
+            id: self.file_name.clone(),
+            title: self.file_name.clone(),
+            uploader: url_host(&self.url).unwrap_or_default(),
+            webpage_url: self.url.clone(),
+            ..MediaInfo::default()
+        }
+    }
+
+    // "video/mp4" without parameters
+    pub fn mime_type(&self) -> &str {
+        self.content_type.split(';').next().unwrap_or("").trim()
+    }
+
+    fn is_page(&self) -> bool {
+        is_page_type(self.mime_type())
+    }
+}
+
+// Pages, playlists and manifests need an extractor; everything else can be saved as it is
+fn is_page_type(mime_type: &str) -> bool {
+    let mime_type = mime_type.to_lowercase();
+    mime_type.is_empty()
+        || mime_type.starts_with("text/")
+        || mime_type.contains("mpegurl")                // HLS playlists
+        || mime_type.ends_with("xml")                   // XHTML, DASH manifests, feeds
+        || mime_type.ends_with("json")
+        || mime_type.ends_with("javascript")
+}
+
+// "sha256:ABC...", "sha256=abc..." or just the hex digest -> lowercase hex
+pub fn parse_checksum(text: &str) -> Result<String, String> {
+    let text = text.trim();
+    let digest = match text.split_once([':', '=']) {
+        Some((algorithm, digest)) if algorithm.trim().eq_ignore_ascii_case("sha256")
+            || algorithm.trim().eq_ignore_ascii_case("sha-256") => digest.trim(),
+        Some((algorithm, _)) => return Err(format!("Unsupported checksum type '{}', use a SHA-256 digest", algorithm.trim())),
+        None => text,
+    };
+
+    if digest.len() != 64 || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid checksum '{}', use the 64 hex digits of a SHA-256 digest", text));
+    }
+    Ok(digest.to_lowercase())
+}
+
+// Answers seen by is_direct_link, so choosing the backend and probing the file ask the server once
+static DIRECT_LINKS: Lazy<Mutex<HashMap<String, DirectFile>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// yt-dlp's extractor list, loaded once by the first check
+static EXTRACTOR_SITES: Lazy<Mutex<Option<Arc<SupportedSites>>>> = Lazy::new(|| Mutex::new(None));
+static EXTRACTORS_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+// The built-in sites stand in while the list loads and when yt-dlp can't list its extractors
+fn has_extractor(url: &str) -> bool {
+    // Only the first caller runs yt-dlp, and without holding the lock
+    if !EXTRACTORS_REQUESTED.swap(true, Ordering::SeqCst) {
+        let loaded = SupportedSites::load().unwrap_or_else(|e| {
+            eprintln!("Could not load the yt-dlp extractor list: {:#}", e);
+            SupportedSites::default()
+        });
+        *EXTRACTOR_SITES.lock().unwrap() = Some(Arc::new(loaded));
+    }
+
+    let sites = EXTRACTOR_SITES.lock().unwrap().clone();
+    match sites {
+        Some(sites) => sites.matches(url),
+        None => SupportedSites::default().matches(url),
+    }
+}
+
+// True for links that serve a file instead of a page, judged by the content type.
+// Sites with a yt-dlp extractor are never asked.
+pub(super) fn is_direct_link(url: &str) -> bool {
+    if url_host(url).is_none() || has_extractor(url) {
+        return false;
+    }
+
+    if let Some(file) = DIRECT_LINKS.lock().unwrap().get(url) {
+        return !file.is_page();
+    }
+
+    // Errors aren't remembered, the next fetch asks again
+    match request_direct_file(url) {
+        Ok(file) => {
+            let direct = !file.is_page();
+            DIRECT_LINKS.lock().unwrap().insert(url.to_string(), file);
+            direct
+        },
+        Err(e) => {
+            eprintln!("Could not check for a direct file link: {:#}", e);
+            false
+        },
+    }
+}
+
+// The file behind a URL; an answer cached by is_direct_link is used once, later calls ask again
+pub fn fetch_direct_file(url: &str) -> Result<DirectFile> {
+    if let Some(file) = DIRECT_LINKS.lock().unwrap().remove(url) {
+        return Ok(file);
+    }
+    request_direct_file(url)
+}
+
+// Ask the server about the file behind a URL
+fn request_direct_file(url: &str) -> Result<DirectFile> {
+    block_on(async {
+        let client = build_client()?;
+        probe_file(&client, url).await
+    })
+}
+
+// Downloads plain files over HTTP(S) without yt-dlp
+pub(super) struct HttpBackend;
+
+impl Backend for HttpBackend {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Http
+    }
+
+    fn probe(&self, url: &str) -> Result<MediaProbe> {
+        let file = fetch_direct_file(url)?;
+        if file.is_page() {
+            return Err(anyhow::anyhow!("{} is a web page ({}), not a file", url, file.mime_type()));
+        }
+        Ok(MediaProbe::Direct(Box::new(file)))
+    }
+
+    fn fetch_formats(&self, url: &str) -> Result<(MediaInfo, Vec<FormatOption>)> {
+        Err(anyhow::anyhow!("{} is a direct file link without formats to choose from", url))
+    }
+
+    fn run(&self, job: &QueueJob, control: &DownloadControl, on_event: EventSink) -> DownloadEvent {
+        match block_on(download(job, control, &on_event)) {
+            Ok(path) => DownloadEvent::Finished { path: Some(path) },
+            Err(_) if control.is_cancelled() => {
+                control.cleanup_partial_files();
+                DownloadEvent::Cancelled
+            },
+            Err(e) => DownloadEvent::Failed { reason: format!("{:#}", e), stderr_tail: Vec::new() },
+        }
+    }
+}
+
+// Each probe and download gets its own runtime on the calling worker thread
+fn block_on<T, F: Future<Output = Result<T>>>(future: F) -> Result<T> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("Failed to start the async runtime")?;
+    runtime.block_on(future)
+}
+
+// Client following the proxy, timeout and IP settings of the Network preferences
+fn build_client() -> Result<Client> {
+    let options = network_options();
+    let mut builder = Client::builder()
+        .user_agent(concat!("HyprDownloader/", env!("CARGO_PKG_VERSION")))
+        .connect_timeout(Duration::from_secs(30));
+
+    if let Some(timeout) = options.socket_timeout {
+        builder = builder.read_timeout(Duration::from_secs(timeout as u64));
+    }
+
+    if !options.proxy.trim().is_empty() {
+        let proxy = reqwest::Proxy::all(options.proxy.trim())
+            .with_context(|| format!("Unsupported proxy {}", options.proxy.trim()))?;
+        builder = builder.proxy(proxy);
+    }
+
+    // A source address already decides the IP version
+    let local_address = options.source_address.trim().parse::<IpAddr>().ok()
+        .or(match options.ip_version {
+            IpVersion::Any => None,
+            IpVersion::Ipv4 => Some(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+            IpVersion::Ipv6 => Some(IpAddr::V6(Ipv6Addr::UNSPECIFIED)),
+        });
+
+    builder.local_address(local_address)
+        .build()
+        .context("Failed to set up the HTTP client")
+}
+
+// GET with the stored login for the site, if any
+fn get(client: &Client, url: &str) -> RequestBuilder {
+    let request = client.get(url);
+    match login_for(url) {
+        Some((username, password)) => request.basic_auth(username, Some(password)),
+        None => request,
+    }
+}
+
+async fn probe_file(client: &Client, url: &str) -> Result<DirectFile> {
+    // Some servers reject HEAD; a one byte range tells the same and whether ranges work
+    let response = get(client, url)
+        .header(RANGE, "bytes=0-0")
+        .send().await
+        .with_context(|| format!("Failed to connect to {}", url))?
+        .error_for_status()?;
+
+    let headers = response.headers();
+    let final_url = response.url().to_string();
+    let content_type = header_text(headers, CONTENT_TYPE).unwrap_or_default();
+
+    let (size, resumable) = if response.status() == StatusCode::PARTIAL_CONTENT {
+        // "bytes 0-0/12345"
+        let size = header_text(headers, CONTENT_RANGE)
+            .and_then(|range| range.rsplit('/').next().and_then(|total| total.trim().parse().ok()));
+        (size, size.is_some())
+    } else {
+        (response.content_length(), false)
+    };
+
+    let file_name = header_text(headers, CONTENT_DISPOSITION)
+        .and_then(|disposition| disposition_file_name(&disposition))
+        .or_else(|| url_file_name(&final_url))
+        .map(|name| sanitize_file_name(&name))
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "download".to_string());
+
+    Ok(DirectFile {
+        url: final_url,
+        file_name,
+        content_type,
+        size,
+        resumable,
+        digest: announced_digest(headers),
+    })
+}
+
+fn header_text(headers: &HeaderMap, name: HeaderName) -> Option<String> {
+    headers.get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string())
+}
+
+// attachment; filename*=UTF-8''My%20File.mp4 or filename="My File.mp4"
+fn disposition_file_name(disposition: &str) -> Option<String> {
+    let params: Vec<(String, &str)> = disposition.split(';')
+        .filter_map(|param| param.split_once('='))
+        .map(|(key, value)| (key.trim().to_lowercase(), value.trim()))
+        .collect();
+
+    let encoded = params.iter()
+        .find(|(key, _)| key == "filename*")
+        .and_then(|(_, value)| value.rsplit_once('\'').map(|(_, name)| percent_decode_str(name).decode_utf8_lossy().into_owned()));
+    let plain = params.iter()
+        .find(|(key, _)| key == "filename")
+        .map(|(_, value)| value.trim_matches('"').to_string());
+
+    encoded.or(plain).filter(|name| !name.is_empty())
+}
+
+// Last path segment of a URL, without query and fragment
+fn url_file_name(url: &str) -> Option<String> {
+    let path = url.split(['?', '#']).next()?;
+    let rest = path.split_once("://").map(|(_, rest)| rest).unwrap_or(path);
+    let (_, path) = rest.split_once('/')?;
+    path.rsplit('/').next()
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| percent_decode_str(segment).decode_utf8_lossy().into_owned())
+}
+
+// Server supplied names must not leave the output folder or hide the file
+fn sanitize_file_name(name: &str) -> String {
+    let name: String = name.chars()
+        .map(|c| if c == '/' || c == '\\' || c.is_control() { '_' } else { c })
+        .collect();
+    name.trim().trim_start_matches('.').to_string()
+}
+
+// SHA-256 from "Repr-Digest: sha-256=:BASE64:" or the older "Digest: SHA-256=BASE64"
+fn announced_digest(headers: &HeaderMap) -> Option<String> {
+    ["repr-digest", "digest"].iter()
+        .filter_map(|name| headers.get(*name).and_then(|value| value.to_str().ok()))
+        .flat_map(|value| value.split(','))
+        .filter_map(|entry| entry.split_once('='))
+        .find(|(algorithm, _)| algorithm.trim().eq_ignore_ascii_case("sha-256"))
+        .and_then(|(_, value)| BASE64_STANDARD.decode(value.trim().trim_matches(':')).ok())
+        .filter(|digest| digest.len() == 32)
+        .map(hex::encode)
+}
+
+// A byte range of the file fetched over its own connection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Segment {
+    start: u64,
+    end: Option<u64>,   // Inclusive, None = until the server stops
+    done: u64,          // Bytes written from start
+}
+
+impl Segment {
+    fn is_complete(&self) -> bool {
+        self.end.is_some_and(|end| self.start + self.done > end)
+    }
+}
+
+// Written next to the .part file so an interrupted download continues where it stopped
+#[derive(Debug, Serialize, Deserialize)]
+struct ResumeState {
+    url: String,
+    size: Option<u64>,
+    segments: Vec<Segment>,
+}
+
+// Split the file into one segment per connection, never smaller than MIN_SEGMENT_SIZE
+fn plan_segments(size: Option<u64>, resumable: bool, connections: usize) -> Vec<Segment> {
+    let Some(size) = size.filter(|size| *size > 0) else {
+        return vec![Segment { start: 0, end: None, done: 0 }];
+    };
+
+    let count = if resumable { (size / MIN_SEGMENT_SIZE).clamp(1, connections.max(1) as u64) } else { 1 };
+    let length = size / count;
+    (0..count)
+        .map(|index| {
+            let start = index * length;
+            let end = if index == count - 1 { size - 1 } else { start + length - 1 };
+            Segment { start, end: Some(end), done: 0 }
+        })
+        .collect()
+}
+
+// Shared speed limit for all connections of a download
+struct RateLimiter {
+    bytes_per_second: u64,
+    started: Instant,
+    sent: Mutex<u64>,
+}
+
+impl RateLimiter {
+    async fn throttle(&self, bytes: usize) {
+        let wait = {
+            let mut sent = self.sent.lock().unwrap();
+            *sent += bytes as u64;
+            Duration::from_secs_f64(*sent as f64 / self.bytes_per_second as f64).checked_sub(self.started.elapsed())
+        };
+        if let Some(wait) = wait {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+// What every connection of one download needs
+struct Transfer {
+    client: Client,
+    url: String,
+    part_file: PathBuf,
+    resumable: bool,
+    segments: Mutex<Vec<Segment>>,
+    control: DownloadControl,
+    limiter: Option<RateLimiter>,
+    retries: u32,
+}
+
+impl Transfer {
+    fn downloaded(&self) -> u64 {
+        self.segments.lock().unwrap().iter().map(|segment| segment.done).sum()
+    }
+
+    fn save_state(&self, state_file: &Path, size: Option<u64>) {
+        if !self.resumable {
+            return;
+        }
+        let state = ResumeState { url: self.url.clone(), size, segments: self.segments.lock().unwrap().clone() };
+        let json = serde_json::to_string(&state).expect("Resume state is serializable");
+        if let Err(e) = fs::write(state_file, json) {
+            eprintln!("Failed to write {}: {}", state_file.display(), e);
+        }
+    }
+}
+
+// Fetch one segment, reconnecting after errors as often as the Network preferences allow
+async fn fetch_segment(transfer: Arc<Transfer>, index: usize) -> Result<()> {
+    let mut attempt = 0;
+    loop {
+        let before = transfer.downloaded();
+        match fetch_segment_once(&transfer, index).await {
+            Ok(()) => return Ok(()),
+            Err(e) if transfer.control.is_cancelled() => return Err(e),
+            Err(e) if attempt < transfer.retries => {
+                // A connection that made progress before dropping starts a new series of retries
+                attempt = if transfer.downloaded() > before { 1 } else { attempt + 1 };
+                eprintln!("Connection {} failed ({:#}), retry {}/{}", index + 1, e, attempt, transfer.retries);
+                tokio::time::sleep(Duration::from_secs(attempt.min(5) as u64)).await;
+            },
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+async fn fetch_segment_once(transfer: &Transfer, index: usize) -> Result<()> {
+    let (start, end, done) = {
+        let mut segments = transfer.segments.lock().unwrap();
+        // Without ranges every attempt starts over
+        if !transfer.resumable {
+            segments[index].done = 0;
+        }
+        let segment = &segments[index];
+        if segment.is_complete() {
+            return Ok(());
+        }
+        (segment.start, segment.end, segment.done)
+    };
+
+    let mut position = start + done;
+    let mut request = get(&transfer.client, &transfer.url);
+    let ranged = transfer.resumable && (position > 0 || end.is_some());
+    if ranged {
+        let range = match end {
+            Some(end) => format!("bytes={}-{}", position, end),
+            None => format!("bytes={}-", position),
+        };
+        request = request.header(RANGE, range);
+    }
+
+    let mut response = request.send().await?.error_for_status()?;
+    if ranged && response.status() != StatusCode::PARTIAL_CONTENT {
+        return Err(anyhow::anyhow!("Server ignored the range request"));
+    }
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .open(&transfer.part_file).await
+        .with_context(|| format!("Failed to open {}", transfer.part_file.display()))?;
+    file.seek(SeekFrom::Start(position)).await?;
+
+    while let Some(chunk) = response.chunk().await? {
+        while transfer.control.is_paused() && !transfer.control.is_cancelled() {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+        if transfer.control.is_cancelled() {
+            return Err(anyhow::anyhow!("Download cancelled"));
+        }
+
+        // Never write into the next segment
+        let length = match end {
+            Some(end) => chunk.len().min((end + 1).saturating_sub(position) as usize),
+            None => chunk.len(),
+        };
+        file.write_all(&chunk[..length]).await?;
+        file.flush().await?;
+
+        position += length as u64;
+        transfer.segments.lock().unwrap()[index].done = position - start;
+
+        if let Some(limiter) = &transfer.limiter {
+            limiter.throttle(length).await;
+        }
+        if end.is_some_and(|end| position > end) {
+            return Ok(());
+        }
+    }
+
+    match end {
+        Some(end) if position <= end => Err(anyhow::anyhow!("Connection closed after {} of {} bytes", position - start, end + 1 - start)),
+        _ => Ok(()),
+    }
+}
+
+// Download the job's URL into its output folder and return the final file
+async fn download(job: &QueueJob, control: &DownloadControl, on_event: &EventSink) -> Result<PathBuf> {
+    let client = build_client()?;
+    let file = probe_file(&client, &job.url).await?;
+    if file.is_page() {
+        return Err(anyhow::anyhow!("{} is a web page ({}), not a file", job.url, file.mime_type()));
+    }
+
+    let options = network_options();
+    let config = backend_config();
+    let expected_digest = match &job.kind {
+        JobKind::Direct { checksum: Some(checksum) } => Some(parse_checksum(checksum).map_err(|e| anyhow::anyhow!(e))?),
+        _ => file.digest.clone(),
+    };
+
+    // Direct files keep the server's name, yt-dlp templates need fields a plain file doesn't have
+    let folder = PathBuf::from(&job.output_path);
+    fs::create_dir_all(&folder)
+        .with_context(|| format!("Failed to create {}", folder.display()))?;
+
+    on_event(DownloadEvent::Started);
+    let mut state = ProgressState::for_format(DIRECT_FORMAT_ID);
+
+    // An existing file is only taken as done if it matches, otherwise the download
+    // goes to the next free "name (N).ext" instead of overwriting it
+    let mut file_name = file.file_name.clone();
+    for number in 1.. {
+        let existing = folder.join(&file_name);
+        if !existing.exists() {
+            break;
+        }
+        if is_same_file(&existing, file.size, expected_digest.as_deref()).await? {
+            control.add_destination(&existing);
+            state.phase = DownloadPhase::Finalizing;
+            state.overall_percent = 0.95;
+            state.status_message = "File was already downloaded".to_string();
+            on_event(DownloadEvent::Progress(state.clone()));
+            return finish(existing, state, on_event);
+        }
+        file_name = numbered_file_name(&file.file_name, number);
+    }
+
+    let target = folder.join(&file_name);
+    let part_file = folder.join(format!("{}.part", file_name));
+    let state_file = folder.join(format!("{}.part.state", file_name));
+    control.add_destination(&target);
+
+    // Continue an interrupted download of the same file, otherwise start over
+    let resumed = fs::read_to_string(&state_file).ok()
+        .and_then(|json| serde_json::from_str::<ResumeState>(&json).ok())
+        .filter(|resume| file.resumable && part_file.exists() && resume.size == file.size && resume.url == file.url);
+    let segments = match resumed {
+        Some(resume) => {
+            eprintln!("Resuming {} with {} connections", file_name, resume.segments.len());
+            resume.segments
+        },
+        None => {
+            let part = fs::File::create(&part_file)
+                .with_context(|| format!("Failed to create {}", part_file.display()))?;
+            if let (Some(size), true) = (file.size, file.resumable) {
+                part.set_len(size)?;
+            }
+            plan_segments(file.size, file.resumable, config.http_connections)
+        },
+    };
+
+    let transfer = Arc::new(Transfer {
+        client,
+        url: file.url.clone(),
+        part_file: part_file.clone(),
+        resumable: file.resumable,
+        segments: Mutex::new(segments),
+        control: control.clone(),
+        limiter: job.rate_limit.as_deref().or(options.rate_limit.as_deref())
+            .and_then(rate_limit_bytes)
+            .map(|bytes_per_second| RateLimiter { bytes_per_second, started: Instant::now(), sent: Mutex::new(0) }),
+        retries: options.retries,
+    });
+    transfer.save_state(&state_file, file.size);
+
+    let mut connections = JoinSet::new();
+    let pending: Vec<usize> = transfer.segments.lock().unwrap().iter()
+        .enumerate()
+        .filter(|(_, segment)| !segment.is_complete())
+        .map(|(index, _)| index)
+        .collect();
+    for index in pending {
+        connections.spawn(fetch_segment(Arc::clone(&transfer), index));
+    }
+
+    let mut ticker = tokio::time::interval(PROGRESS_INTERVAL);
+    ticker.tick().await;  // The first tick completes right away
+    let (mut last_bytes, mut last_tick, mut speed) = (transfer.downloaded(), Instant::now(), None::<f64>);
+    let result = loop {
+        tokio::select! {
+            finished = connections.join_next() => match finished {
+                None => break Ok(()),
+                Some(Ok(Ok(()))) => {},
+                Some(Ok(Err(e))) => break Err(e),
+                Some(Err(e)) => break Err(anyhow::anyhow!("Connection task failed: {}", e)),
+            },
+            _ = ticker.tick() => {
+                // A stalled connection would only notice a cancel with its next chunk
+                if control.is_cancelled() {
+                    break Err(anyhow::anyhow!("Download cancelled"));
+                }
+                transfer.save_state(&state_file, file.size);
+                if control.is_paused() {
+                    last_tick = Instant::now();
+                    continue;
+                }
+
+                let downloaded = transfer.downloaded();
+                // A retry without range support starts its segment over, so the count can drop
+                let current = downloaded.saturating_sub(last_bytes) as f64 / last_tick.elapsed().as_secs_f64();
+                let smoothed = speed.map_or(current, |speed| speed * 0.7 + current * 0.3);
+                speed = Some(smoothed);
+                (last_bytes, last_tick) = (downloaded, Instant::now());
+
+                report_progress(&mut state, on_event, DownloadProgress {
+                    status: "downloading".to_string(),
+                    format_id: DIRECT_FORMAT_ID.to_string(),
+                    downloaded_bytes: downloaded,
+                    total_bytes: file.size,
+                    speed: Some(smoothed),
+                    eta: file.size
+                        .filter(|_| smoothed > 0.0)
+                        .map(|size| (size.saturating_sub(downloaded) as f64 / smoothed) as u64),
+                    fragment_index: None,
+                    fragment_count: None,
+                });
+            },
+        }
+    };
+
+    if let Err(e) = result {
+        connections.abort_all();
+        transfer.save_state(&state_file, file.size);
+        return Err(e);
+    }
+
+    report_progress(&mut state, on_event, DownloadProgress {
+        status: "finished".to_string(),
+        format_id: DIRECT_FORMAT_ID.to_string(),
+        downloaded_bytes: transfer.downloaded(),
+        total_bytes: file.size.or(Some(transfer.downloaded())),
+        speed,
+        eta: None,
+        fragment_index: None,
+        fragment_count: None,
+    });
+    let _ = fs::remove_file(&state_file);
+
+    if let Some(expected) = expected_digest {
+        state.phase = DownloadPhase::Finalizing;
+        state.overall_percent = 0.9;
+        state.status_message = "Verifying checksum...".to_string();
+        on_event(DownloadEvent::Stage(state.status_message.clone()));
+        on_event(DownloadEvent::Progress(state.clone()));
+
+        let path = part_file.clone();
+        let actual = tokio::task::spawn_blocking(move || sha256_file(&path)).await??;
+        if actual != expected {
+            // A corrupt file can't be resumed into a good one
+            let _ = fs::remove_file(&part_file);
+            return Err(anyhow::anyhow!("Checksum mismatch: expected SHA-256 {}, got {}", expected, actual));
+        }
+        eprintln!("SHA-256 of {} verified", file_name);
+    }
+
+    fs::rename(&part_file, &target)
+        .with_context(|| format!("Failed to move {} into place", target.display()))?;
+    finish(target, state, on_event)
+}
+
+// Length must match the server's, and the SHA-256 too when one is known
+async fn is_same_file(path: &Path, size: Option<u64>, digest: Option<&str>) -> Result<bool> {
+    let length = fs::metadata(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?
+        .len();
+    if size != Some(length) {
+        return Ok(false);
+    }
+
+    let Some(expected) = digest else {
+        return Ok(true);
+    };
+    let path = path.to_path_buf();
+    let actual = tokio::task::spawn_blocking(move || sha256_file(&path)).await??;
+    Ok(actual == expected)
+}
+
+// "clip.mp4" -> "clip (1).mp4"
+fn numbered_file_name(file_name: &str, number: usize) -> String {
+    match file_name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => format!("{} ({}).{}", stem, number, extension),
+        _ => format!("{} ({})", file_name, number),
+    }
+}
+
+// Same final progress as a finished yt-dlp job
+fn finish(target: PathBuf, mut state: ProgressState, on_event: &EventSink) -> Result<PathBuf> {
+    state.phase = DownloadPhase::Complete;
+    state.overall_percent = 1.0;
+    state.status_message = "Download complete!".to_string();
+    on_event(DownloadEvent::Progress(state));
+    Ok(target)
+}
+
+// Feed the progress through ProgressState like a yt-dlp progress line
+fn report_progress(state: &mut ProgressState, on_event: &EventSink, progress: DownloadProgress) {
+    let previous_phase = state.phase;
+    state.apply(&ProgressEvent::Download(progress));
+    if state.phase != previous_phase {
+        on_event(DownloadEvent::Stage(state.status_message.clone()));
+    }
+    on_event(DownloadEvent::Progress(state.clone()));
+}
+
+fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = fs::File::open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 1024 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
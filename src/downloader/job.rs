@@ -0,0 +1,52 @@
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use super::backend::{backend, backend_for, BackendKind, EventSink};
+use super::control::DownloadControl;
+use super::events::DownloadEvent;
+use super::queue::{QueueJob, JobKind};
+
+// Runs one download through the backend chosen for its URL and holds its control handle.
+// The GUI, the queue and the CLI all drive downloads through this type.
+pub struct DownloadJob {
+    job: QueueJob,
+    control: DownloadControl,
+}
+
+impl DownloadJob {
+    pub fn new(job: QueueJob) -> Self {
+        DownloadJob { job, control: DownloadControl::new() }
+    }
+
+    // Handle to pause, resume or cancel the job while it runs
+    pub fn control(&self) -> &DownloadControl {
+        &self.control
+    }
+
+    // Run on a background thread; on_event is called from worker threads
+    pub fn start<F>(self, on_event: F) -> JoinHandle<DownloadEvent>
+    where
+        F: Fn(DownloadEvent) + Send + Sync + 'static,
+    {
+        thread::spawn(move || self.run(on_event))
+    }
+
+    // Run to completion on the current thread and return the final event
+    pub fn run<F>(&self, on_event: F) -> DownloadEvent
+    where
+        F: Fn(DownloadEvent) + Send + Sync + 'static,
+    {
+        let on_event: EventSink = Arc::new(on_event);
+
+        // Direct file jobs were probed by the HTTP backend, whatever the rules say now
+        let backend = match self.job.kind {
+            JobKind::Direct { .. } => backend(BackendKind::Http),
+            _ => backend_for(&self.job.url),
+        };
+        eprintln!("Downloading {} with {}", self.job.url, backend.kind().label());
+
+        let event = backend.run(&self.job, &self.control, Arc::clone(&on_event));
+        on_event(event.clone());
+        event
+    }
+}
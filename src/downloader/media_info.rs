@@ -0,0 +1,112 @@
+use anyhow::{Result, Context};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::process::Command;
+
+use super::subtitles::{SubtitleTrack, parse_subtitle_tracks};
+
+// Descriptive metadata of a single video from the yt-dlp JSON
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MediaInfo {
+    pub id: String,
+    pub title: String,
+    pub uploader: String,
+    pub thumbnail: Option<String>,     // URL
+    pub duration: Option<u64>,         // Seconds
+    pub upload_date: Option<String>,   // YYYYMMDD
+    pub description: String,
+    pub webpage_url: String,
+    pub subtitles: Vec<SubtitleTrack>,
+    pub artist: Option<String>,        // Music metadata, only some sites provide it
+    pub track: Option<String>,
+    pub album: Option<String>,
+    pub release_year: Option<u32>,
+}
+
+impl MediaInfo {
+    // Upload date as YYYY-MM-DD
+    pub fn formatted_upload_date(&self) -> Option<String> {
+        let date = self.upload_date.as_deref()?;
+        if date.len() != 8 {
+            return Some(date.to_string());
+        }
+        Some(format!("{}-{}-{}", &date[0..4], &date[4..6], &date[6..8]))
+    }
+}
+
+pub(super) fn parse_media_info(json: &Value) -> MediaInfo {
+    MediaInfo {
+        id: json["id"].as_str().unwrap_or("").to_string(),
+        title: json["title"].as_str().unwrap_or("").to_string(),
+        uploader: json["uploader"].as_str()
+            .or_else(|| json["channel"].as_str())
+            .unwrap_or("")
+            .to_string(),
+        thumbnail: select_thumbnail(json),
+        duration: json["duration"].as_f64().map(|d| d as u64),
+        upload_date: json["upload_date"].as_str().map(|d| d.to_string()),
+        description: json["description"].as_str().unwrap_or("").to_string(),
+        webpage_url: json["webpage_url"].as_str().unwrap_or("").to_string(),
+        subtitles: parse_subtitle_tracks(json),
+        artist: json["artist"].as_str()
+            .or_else(|| json["creator"].as_str())
+            .map(|artist| artist.to_string()),
+        track: json["track"].as_str().map(|track| track.to_string()),
+        album: json["album"].as_str().map(|album| album.to_string()),
+        release_year: json["release_year"].as_u64().map(|year| year as u32),
+    }
+}
+
+// Prefer the largest JPEG/PNG thumbnail, since WebP needs an extra gdk-pixbuf loader
+fn select_thumbnail(json: &Value) -> Option<String> {
+    let is_supported = |url: &str| {
+        let path = url.split('?').next().unwrap_or(url).to_lowercase();
+        path.ends_with(".jpg") || path.ends_with(".jpeg") || path.ends_with(".png")
+    };
+
+    json["thumbnails"].as_array()
+        .and_then(|thumbnails| thumbnails.iter()
+            .filter_map(|thumbnail| {
+                let url = thumbnail["url"].as_str()?;
+                let width = thumbnail["width"].as_u64().unwrap_or(0);
+                is_supported(url).then(|| (width, url.to_string()))
+            })
+            .max_by_key(|(width, _)| *width)
+            .map(|(_, url)| url))
+        .or_else(|| json["thumbnail"].as_str().map(|url| url.to_string()))
+}
+
+// Download a thumbnail into the cache dir and return the local file; cached files are reused
+pub fn fetch_thumbnail(url: &str) -> Result<PathBuf> {
+    let cache_dir = dirs::cache_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find cache directory"))?
+        .join("HyprDownloader")
+        .join("thumbnails");
+    fs::create_dir_all(&cache_dir)?;
+
+    let mut hasher = DefaultHasher::new();
+    url.hash(&mut hasher);
+    let path = cache_dir.join(format!("{:016x}", hasher.finish()));
+
+    if path.exists() {
+        return Ok(path);
+    }
+
+    let status = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--location", "--max-time", "30", "--output"])
+        .arg(&path)
+        .arg(url)
+        .status()
+        .context("Failed to execute curl command")?;
+
+    if !status.success() {
+        let _ = fs::remove_file(&path);
+        return Err(anyhow::anyhow!("Failed to download thumbnail: curl exited with {}", status));
+    }
+
+    Ok(path)
+}
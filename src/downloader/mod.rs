@@ -1,6 +1,41 @@
 mod formats;
+mod media_info;
+mod subtitles;
+mod tagging;
+mod template;
 mod download;
+mod control;
+mod queue;
+mod playlist;
+mod progress;
+mod events;
+mod job;
+mod ytdlp;
+mod sites;
+mod auth;
+mod network;
+mod ffmpeg;
+mod backend;
+mod http;
 
-pub use formats::{fetch_available_formats, FormatOption, FormatMessage};
-pub use download::{download_media_with_format, download_audio_only, download_audio_with_format, 
-                   DownloadPhase, ProgressState, update_progress_state}; 
\ No newline at end of file
+pub use formats::{FormatOption, FormatMessage, MediaProbe,
+                  merge_containers_for, build_format_selector, estimate_selector_size, select_video_format, select_audio_format,
+                  codec_compatibility_hint, codec_rank, VIDEO_CODECS,
+                  MERGE_CONTAINERS};
+pub use media_info::{MediaInfo, fetch_thumbnail};
+pub use subtitles::{SubtitleTrack, SubtitleMode, SubtitleOptions};
+pub use tagging::AudioTags;
+pub use template::{OutputTemplate, TEMPLATE_TOKENS, DEFAULT_TEMPLATE, template_values};
+pub use control::DownloadControl;
+pub use events::DownloadEvent;
+pub use job::DownloadJob;
+pub use queue::{DownloadQueue, QueueJob, JobKind, JobStatus, QueueEvent};
+pub use playlist::{Playlist, PlaylistEntry, PlaylistFilter, QualityPreset, parse_index_ranges, parse_filter_date};
+pub use ytdlp::{YtdlpBinary, active_ytdlp, locate_ytdlp, install_ytdlp, ytdlp_version};
+pub use sites::SupportedSites;
+pub use ffmpeg::{FfmpegTools, FFMPEG_HELP, ffmpeg_tools, refresh_ffmpeg_tools, check_ffmpeg};
+pub use network::{NetworkOptions, IpVersion, parse_rate_limit, set_network_options};
+pub use auth::{AuthConfig, CookieSource, SiteLogin, COOKIE_BROWSERS, set_auth_config};
+pub use backend::{BackendKind, BackendRule, BackendConfig, set_backend_config, parse_backend_rules,
+                  format_backend_rules, probe_media, fetch_available_formats, direct_file_for};
+pub use http::{DirectFile, parse_checksum};
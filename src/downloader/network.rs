@@ -0,0 +1,139 @@
+use once_cell::sync::Lazy;
+use serde::{Serialize, Deserialize};
+use std::process::Command;
+use std::sync::RwLock;
+
+// Which IP version yt-dlp connects over
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IpVersion {
+    #[default]
+    Any,
+    Ipv4,    // --force-ipv4
+    Ipv6,    // --force-ipv6
+}
+
+impl IpVersion {
+    pub const ALL: [IpVersion; 3] = [IpVersion::Any, IpVersion::Ipv4, IpVersion::Ipv6];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            IpVersion::Any => "any",
+            IpVersion::Ipv4 => "ipv4",
+            IpVersion::Ipv6 => "ipv6",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            IpVersion::Any => "Automatic",
+            IpVersion::Ipv4 => "IPv4 only",
+            IpVersion::Ipv6 => "IPv6 only",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<IpVersion> {
+        IpVersion::ALL.into_iter().find(|version| version.id() == id)
+    }
+}
+
+// Connection settings applied to every fetch and download
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkOptions {
+    pub proxy: String,                // http://, https:// or socks5:// URL, empty = direct
+    pub rate_limit: Option<String>,   // Bytes per second like "2M" or "500K", None = unlimited
+    pub retries: u32,
+    pub fragment_retries: u32,        // Per fragment of DASH/HLS streams
+    pub socket_timeout: Option<u32>,  // Seconds, None = yt-dlp's default
+    pub ip_version: IpVersion,
+    pub source_address: String,       // Local IP to bind to, empty = any
+}
+
+impl Default for NetworkOptions {
+    fn default() -> Self {
+        // yt-dlp's own defaults
+        NetworkOptions {
+            proxy: String::new(),
+            rate_limit: None,
+            retries: 10,
+            fragment_retries: 10,
+            socket_timeout: None,
+            ip_version: IpVersion::Any,
+            source_address: String::new(),
+        }
+    }
+}
+
+static NETWORK_OPTIONS: Lazy<RwLock<NetworkOptions>> = Lazy::new(|| RwLock::new(NetworkOptions::default()));
+
+pub fn set_network_options(options: NetworkOptions) {
+    *NETWORK_OPTIONS.write().unwrap() = options;
+}
+
+pub(super) fn network_options() -> NetworkOptions {
+    NETWORK_OPTIONS.read().unwrap().clone()
+}
+
+// "2M", "500k", "1.5 MB/s" -> "2M", "500K", "1.5M"; empty means no limit
+pub fn parse_rate_limit(text: &str) -> Result<Option<String>, String> {
+    let text = text.trim().trim_end_matches("/s").trim_end_matches(['B', 'b']).trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+
+    let (number, suffix) = match text.char_indices().last() {
+        Some((index, unit)) if unit.is_ascii_alphabetic() => (text[..index].trim(), unit.to_ascii_uppercase().to_string()),
+        _ => (text, String::new()),
+    };
+
+    let valid_suffix = suffix.is_empty() || ["K", "M", "G"].contains(&suffix.as_str());
+    match number.parse::<f64>() {
+        Ok(value) if value > 0.0 && valid_suffix => Ok(Some(format!("{}{}", number, suffix))),
+        _ => Err(format!("Invalid speed limit '{}', use a value like 500K or 2M", text)),
+    }
+}
+
+// "2M" or "500K" as produced by parse_rate_limit -> bytes per second
+pub(super) fn rate_limit_bytes(rate_limit: &str) -> Option<u64> {
+    let rate_limit = rate_limit.trim();
+    let (number, multiplier) = match rate_limit.chars().last()?.to_ascii_uppercase() {
+        'K' => (&rate_limit[..rate_limit.len() - 1], 1024.0),
+        'M' => (&rate_limit[..rate_limit.len() - 1], 1024.0 * 1024.0),
+        'G' => (&rate_limit[..rate_limit.len() - 1], 1024.0 * 1024.0 * 1024.0),
+        _ => (rate_limit, 1.0),
+    };
+    number.trim().parse::<f64>().ok()
+        .map(|value| (value * multiplier) as u64)
+        .filter(|bytes| *bytes > 0)
+}
+
+// Add the proxy, timeout, retry and rate flags; a job's own limit replaces the global one
+pub(super) fn add_network_args(command: &mut Command, rate_limit: Option<&str>) {
+    let options = NETWORK_OPTIONS.read().unwrap();
+
+    if !options.proxy.trim().is_empty() {
+        command.arg("--proxy").arg(options.proxy.trim());
+    }
+
+    if let Some(rate_limit) = rate_limit.or(options.rate_limit.as_deref()) {
+        command.arg("--limit-rate").arg(rate_limit);
+    }
+
+    command.arg("--retries").arg(options.retries.to_string());
+    command.arg("--fragment-retries").arg(options.fragment_retries.to_string());
+
+    if let Some(timeout) = options.socket_timeout {
+        command.arg("--socket-timeout").arg(timeout.to_string());
+    }
+
+    match options.ip_version {
+        IpVersion::Any => {},
+        IpVersion::Ipv4 => { command.arg("--force-ipv4"); },
+        IpVersion::Ipv6 => { command.arg("--force-ipv6"); },
+    }
+
+    if !options.source_address.trim().is_empty() {
+        command.arg("--source-address").arg(options.source_address.trim());
+    }
+}
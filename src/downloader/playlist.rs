@@ -0,0 +1,187 @@
+use anyhow::{Result, Context};
+use serde_json::Value;
+
+use super::queue::JobKind;
+
+#[derive(Debug, Clone)]
+pub struct PlaylistEntry {
+    pub index: usize,               // 1-based position in the playlist
+    pub title: String,
+    pub url: String,
+    pub duration: Option<u64>,      // Seconds
+    pub upload_date: Option<String>, // YYYYMMDD, often missing in flat listings
+}
+
+#[derive(Debug, Clone)]
+pub struct Playlist {
+    pub title: String,
+    pub uploader: String,
+    pub entries: Vec<PlaylistEntry>,
+}
+
+// Read the entries of a playlist or channel from flat yt-dlp JSON, None for single videos
+pub(super) fn parse_playlist(json: &Value) -> Option<Playlist> {
+    if json["_type"].as_str() != Some("playlist") {
+        return None;
+    }
+
+    let entries: Vec<PlaylistEntry> = json["entries"].as_array()
+        .map(|entries| entries.iter()
+            .enumerate()
+            .filter_map(|(i, entry)| {
+                let id = entry["id"].as_str().unwrap_or("").to_string();
+                // Flat entries carry either a full URL or just the video id
+                let url = entry["url"].as_str()
+                    .or_else(|| entry["webpage_url"].as_str())
+                    .map(|u| u.to_string())
+                    .unwrap_or_else(|| id.clone());
+
+                if url.is_empty() {
+                    eprintln!("Skipping playlist entry {} without URL", i + 1);
+                    return None;
+                }
+
+                Some(PlaylistEntry {
+                    index: entry["playlist_index"].as_u64().map(|n| n as usize).unwrap_or(i + 1),
+                    title: entry["title"].as_str().unwrap_or(&id).to_string(),
+                    url,
+                    duration: entry["duration"].as_f64().map(|d| d as u64),
+                    upload_date: entry["upload_date"].as_str().map(|d| d.to_string()),
+                })
+            })
+            .collect())
+        .unwrap_or_default();
+
+    eprintln!("Found playlist with {} entries", entries.len());
+
+    Some(Playlist {
+        title: json["title"].as_str().unwrap_or("Playlist").to_string(),
+        uploader: json["uploader"].as_str()
+            .or_else(|| json["channel"].as_str())
+            .unwrap_or("")
+            .to_string(),
+        entries,
+    })
+}
+
+// Which entries of a playlist to select
+#[derive(Debug, Clone, Default)]
+pub struct PlaylistFilter {
+    pub first: Option<usize>,           // Only the first N entries
+    pub ranges: Vec<(usize, usize)>,    // Inclusive 1-based index ranges, empty = all
+    pub date_after: Option<String>,     // YYYYMMDD, inclusive
+    pub date_before: Option<String>,    // YYYYMMDD, inclusive
+}
+
+impl PlaylistFilter {
+    pub fn matches(&self, entry: &PlaylistEntry) -> bool {
+        if let Some(first) = self.first {
+            if entry.index > first {
+                return false;
+            }
+        }
+
+        if !self.ranges.is_empty() && !self.ranges.iter().any(|(start, end)| entry.index >= *start && entry.index <= *end) {
+            return false;
+        }
+
+        // Entries without a known date can't be filtered, so they are kept
+        if let Some(date) = &entry.upload_date {
+            if let Some(after) = &self.date_after {
+                if date < after {
+                    return false;
+                }
+            }
+            if let Some(before) = &self.date_before {
+                if date > before {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+}
+
+// Parse index ranges like "1-5,8,10-12"
+pub fn parse_index_ranges(spec: &str) -> Result<Vec<(usize, usize)>> {
+    let mut ranges = Vec::new();
+
+    for part in spec.split(',').map(|p| p.trim()).filter(|p| !p.is_empty()) {
+        let (start, end) = match part.split_once('-') {
+            Some((start, end)) => (start.trim(), end.trim()),
+            None => (part, part),
+        };
+
+        let start: usize = start.parse().with_context(|| format!("Invalid index '{}'", start))?;
+        let end: usize = end.parse().with_context(|| format!("Invalid index '{}'", end))?;
+
+        if start == 0 || end < start {
+            return Err(anyhow::anyhow!("Invalid range '{}'", part));
+        }
+
+        ranges.push((start, end));
+    }
+
+    Ok(ranges)
+}
+
+// Check a YYYYMMDD date as typed in the date filter fields
+pub fn parse_filter_date(text: &str) -> Result<Option<String>> {
+    let text = text.trim().replace('-', "");
+    if text.is_empty() {
+        return Ok(None);
+    }
+    if text.len() != 8 || !text.chars().all(|c| c.is_ascii_digit()) {
+        return Err(anyhow::anyhow!("Dates must be written as YYYYMMDD"));
+    }
+    Ok(Some(text))
+}
+
+// Quality applied to every selected playlist entry
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QualityPreset {
+    BestVideo,
+    MaxHeight(u32),
+    Audio(&'static str),
+}
+
+impl QualityPreset {
+    pub const ALL: [QualityPreset; 9] = [
+        QualityPreset::BestVideo,
+        QualityPreset::MaxHeight(2160),
+        QualityPreset::MaxHeight(1440),
+        QualityPreset::MaxHeight(1080),
+        QualityPreset::MaxHeight(720),
+        QualityPreset::MaxHeight(480),
+        QualityPreset::Audio("mp3"),
+        QualityPreset::Audio("m4a"),
+        QualityPreset::Audio("opus"),
+    ];
+
+    pub fn label(&self) -> String {
+        match self {
+            QualityPreset::BestVideo => "Best video".to_string(),
+            QualityPreset::MaxHeight(height) => format!("Up to {}p", height),
+            QualityPreset::Audio(format) => format!("Audio only ({})", format.to_uppercase()),
+        }
+    }
+
+    // yt-dlp format selector and options for one entry
+    pub fn job_kind(&self) -> JobKind {
+        match self {
+            QualityPreset::BestVideo => JobKind::Video {
+                format_id: "bestvideo+bestaudio/best".to_string(),
+                merge_format: Some("mkv".to_string()),
+            },
+            QualityPreset::MaxHeight(height) => JobKind::Video {
+                format_id: format!("bestvideo[height<={h}]+bestaudio/best[height<={h}]", h = height),
+                merge_format: Some("mkv".to_string()),
+            },
+            QualityPreset::Audio(format) => JobKind::Audio {
+                format_id: "bestaudio/best".to_string(),
+                audio_format: format.to_string(),
+            },
+        }
+    }
+}
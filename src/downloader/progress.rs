@@ -0,0 +1,452 @@
+use serde::Deserialize;
+use std::process::Command;
+
+use crate::app::format_size;
+use super::ffmpeg::is_missing_ffmpeg_message;
+
+// Prefixes of the machine-readable lines requested with --progress-template
+const DOWNLOAD_PREFIX: &str = "[hypr-progress] ";
+const POSTPROCESS_PREFIX: &str = "[hypr-postprocess] ";
+
+// One JSON object per line; missing numbers are printed as null
+const DOWNLOAD_TEMPLATE: &str = concat!(
+    "download:[hypr-progress] {",
+    "\"status\":\"%(progress.status)s\",",
+    "\"format_id\":\"%(info.format_id)s\",",
+    "\"downloaded_bytes\":%(progress.downloaded_bytes|null)s,",
+    "\"total_bytes\":%(progress.total_bytes|null)s,",
+    "\"total_bytes_estimate\":%(progress.total_bytes_estimate|null)s,",
+    "\"speed\":%(progress.speed|null)s,",
+    "\"eta\":%(progress.eta|null)s,",
+    "\"fragment_index\":%(progress.fragment_index|null)s,",
+    "\"fragment_count\":%(progress.fragment_count|null)s}"
+);
+
+const POSTPROCESS_TEMPLATE: &str = concat!(
+    "postprocess:[hypr-postprocess] {",
+    "\"status\":\"%(progress.status)s\",",
+    "\"postprocessor\":\"%(progress.postprocessor)s\"}"
+);
+
+// Ask yt-dlp for one JSON progress line per update instead of the human readable bar
+pub(super) fn add_progress_template(command: &mut Command) {
+    command
+        .arg("--newline")  // Force newlines to ensure consistent output
+        .arg("--progress")
+        .arg("--progress-template")
+        .arg(DOWNLOAD_TEMPLATE)
+        .arg("--progress-template")
+        .arg(POSTPROCESS_TEMPLATE);
+}
+
+// Progress of the stream that is currently being downloaded
+#[derive(Debug, Clone, PartialEq)]
+pub struct DownloadProgress {
+    pub status: String,               // "downloading", "finished" or "error"
+    pub format_id: String,            // Stream of a merged selection that is active
+    pub downloaded_bytes: u64,
+    pub total_bytes: Option<u64>,     // Exact size, or yt-dlp's estimate for fragmented streams
+    pub speed: Option<f64>,           // Bytes per second
+    pub eta: Option<u64>,             // Seconds
+    pub fragment_index: Option<u64>,
+    pub fragment_count: Option<u64>,
+}
+
+impl DownloadProgress {
+    // Fraction of this stream, from bytes or else from fragments
+    pub fn fraction(&self) -> Option<f64> {
+        if self.status == "finished" {
+            return Some(1.0);
+        }
+
+        match (self.total_bytes, self.fragment_index, self.fragment_count) {
+            (Some(total), _, _) if total > 0 => Some((self.downloaded_bytes as f64 / total as f64).min(1.0)),
+            (_, Some(index), Some(count)) if count > 0 => Some((index as f64 / count as f64).min(1.0)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProgressEvent {
+    Download(DownloadProgress),
+    Postprocess { postprocessor: String, status: String },
+}
+
+#[derive(Deserialize)]
+struct RawDownloadProgress {
+    status: String,
+    format_id: String,
+    downloaded_bytes: Option<f64>,
+    total_bytes: Option<f64>,
+    total_bytes_estimate: Option<f64>,
+    speed: Option<f64>,
+    eta: Option<f64>,
+    fragment_index: Option<u64>,
+    fragment_count: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct RawPostprocess {
+    status: String,
+    postprocessor: String,
+}
+
+// Parse a line printed through our progress templates, None for any other output
+pub fn parse_progress_line(line: &str) -> Option<ProgressEvent> {
+    let line = line.trim();
+
+    if let Some(json) = line.strip_prefix(DOWNLOAD_PREFIX) {
+        let raw: RawDownloadProgress = serde_json::from_str(json)
+            .map_err(|e| eprintln!("Invalid progress line '{}': {}", json, e))
+            .ok()?;
+
+        return Some(ProgressEvent::Download(DownloadProgress {
+            status: raw.status,
+            format_id: raw.format_id,
+            downloaded_bytes: raw.downloaded_bytes.unwrap_or(0.0) as u64,
+            total_bytes: raw.total_bytes.or(raw.total_bytes_estimate).map(|t| t as u64),
+            speed: raw.speed,
+            eta: raw.eta.map(|e| e as u64),
+            fragment_index: raw.fragment_index,
+            fragment_count: raw.fragment_count,
+        }));
+    }
+
+    if let Some(json) = line.strip_prefix(POSTPROCESS_PREFIX) {
+        let raw: RawPostprocess = serde_json::from_str(json)
+            .map_err(|e| eprintln!("Invalid postprocess line '{}': {}", json, e))
+            .ok()?;
+
+        return Some(ProgressEvent::Postprocess { postprocessor: raw.postprocessor, status: raw.status });
+    }
+
+    None
+}
+
+// Process phases for tracking download progress
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DownloadPhase {
+    Initializing,   // 0-5%
+    Downloading,    // 5-80%
+    Postprocessing, // 80-95%
+    Finalizing,     // 95-99%
+    Complete        // 100%
+}
+
+// Track the actual percentage and phase
+#[derive(Debug, Clone)]
+pub struct ProgressState {
+    pub phase: DownloadPhase,
+    pub download_percent: f64,
+    pub overall_percent: f64,
+    pub status_message: String,
+    pub downloaded_bytes: u64,
+    pub total_bytes: Option<u64>,
+    pub speed: Option<f64>,              // Bytes per second
+    pub eta: Option<u64>,                // Seconds
+    pub fragment_index: Option<u64>,
+    pub fragment_count: Option<u64>,
+    pub stream_index: usize,             // Active stream of a merged selection, 0-based
+    pub stream_count: usize,
+    streams: Vec<Option<String>>,        // Format ids in download order, None until a generic selector resolves
+}
+
+impl Default for ProgressState {
+    fn default() -> Self {
+        ProgressState {
+            phase: DownloadPhase::Initializing,
+            download_percent: 0.0,
+            overall_percent: 0.0,
+            status_message: "Initializing download...".to_string(),
+            downloaded_bytes: 0,
+            total_bytes: None,
+            speed: None,
+            eta: None,
+            fragment_index: None,
+            fragment_count: None,
+            stream_index: 0,
+            stream_count: 1,
+            streams: Vec::new(),
+        }
+    }
+}
+
+impl ProgressState {
+    // State for a format selector; "137+140" is downloaded as two streams
+    pub fn for_format(format_id: &str) -> Self {
+        let streams: Vec<Option<String>> = format_id.split('+')
+            .filter(|id| !id.is_empty())
+            .map(|id| Some(id.to_string()).filter(|id| !is_generic_selector(id)))
+            .collect();
+
+        ProgressState {
+            stream_count: streams.len().max(1),
+            streams,
+            ..ProgressState::default()
+        }
+    }
+
+    pub fn apply(&mut self, event: &ProgressEvent) {
+        match event {
+            ProgressEvent::Download(progress) => self.apply_download(progress),
+            ProgressEvent::Postprocess { postprocessor, status } => self.apply_postprocess(postprocessor, status),
+        }
+    }
+
+    fn apply_download(&mut self, progress: &DownloadProgress) {
+        // Subtitle files have no format id; they are tiny, so leave the bar where it is
+        if progress.format_id == "NA" {
+            self.status_message = "Downloading subtitles...".to_string();
+            return;
+        }
+        
+        // Streams not named in the selector (e.g. "bestaudio") are counted as they appear
+        let known = self.streams.iter().position(|id| id.as_deref() == Some(progress.format_id.as_str()));
+        let stream_index = match known.or_else(|| self.streams.iter().position(Option::is_none)) {
+            Some(index) => {
+                self.streams[index] = Some(progress.format_id.clone());
+                index
+            },
+            None => self.stream_index,
+        };
+
+        self.phase = DownloadPhase::Downloading;
+        self.stream_index = stream_index;
+        self.downloaded_bytes = progress.downloaded_bytes;
+        self.total_bytes = progress.total_bytes;
+        self.speed = progress.speed;
+        self.eta = progress.eta;
+        self.fragment_index = progress.fragment_index;
+        self.fragment_count = progress.fragment_count;
+
+        let Some(fraction) = progress.fraction() else {
+            self.status_message = format!("Downloading{}: {} received", self.stream_label(), format_size(progress.downloaded_bytes));
+            return;
+        };
+
+        self.download_percent = fraction;
+
+        // Map the combined download of all streams (0-100%) to overall percentage (5-80%),
+        // never moving the bar backwards
+        let combined = (stream_index as f64 + fraction) / self.stream_count as f64;
+        self.overall_percent = self.overall_percent.max(0.05 + combined * 0.75);
+
+        let mut status = format!("Downloading{}: {:.1}%", self.stream_label(), fraction * 100.0);
+        if let Some(total) = progress.total_bytes {
+            status.push_str(&format!(" of {}", format_size(total)));
+        }
+        if let Some(speed) = progress.speed {
+            status.push_str(&format!(" at {}/s", format_size(speed as u64)));
+        }
+        if let Some(eta) = progress.eta {
+            status.push_str(&format!(", ETA {}:{:02}", eta / 60, eta % 60));
+        }
+        self.status_message = status;
+    }
+
+    fn apply_postprocess(&mut self, postprocessor: &str, status: &str) {
+        let (phase, percent, message) = match postprocessor {
+            "Merger" => (DownloadPhase::Postprocessing, 0.85, "Merging formats...".to_string()),
+            "ExtractAudio" => (DownloadPhase::Postprocessing, 0.85, "Extracting audio...".to_string()),
+            "MoveFiles" => (DownloadPhase::Finalizing, 0.95, "Moving files into place...".to_string()),
+            _ => (DownloadPhase::Postprocessing, 0.9, format!("Post-processing ({})...", postprocessor)),
+        };
+
+        if status == "started" || status == "processing" {
+            self.phase = phase;
+            self.overall_percent = self.overall_percent.max(percent);
+            self.status_message = message;
+        }
+    }
+
+    // " stream 2/2" while downloading a merged selection
+    fn stream_label(&self) -> String {
+        if self.stream_count > 1 {
+            format!(" stream {}/{}", self.stream_index + 1, self.stream_count)
+        } else {
+            String::new()
+        }
+    }
+}
+
+// "bestaudio", "bv*[height<=720]" etc. name a choice yt-dlp makes, not a format id
+fn is_generic_selector(id: &str) -> bool {
+    let name = id.split('[').next().unwrap_or(id).trim_end_matches('*');
+    id.contains('[') || matches!(name, "b" | "ba" | "bv" | "w" | "wa" | "wv")
+        || name.starts_with("best") || name.starts_with("worst")
+}
+
+// Update the progress state from a line of yt-dlp output
+pub fn update_progress_state(line: &str, state: &mut ProgressState) -> bool {
+    if let Some(event) = parse_progress_line(line) {
+        state.apply(&event);
+        return true;
+    }
+
+    // Metadata extraction before the first progress line
+    if line.starts_with("[info]") && state.phase == DownloadPhase::Initializing {
+        state.overall_percent = 0.05;
+        state.status_message = "Gathering information...".to_string();
+        return true;
+    }
+
+    // Postprocessing can't run without ffmpeg, the job fails right after
+    if is_missing_ffmpeg_message(line) {
+        state.status_message = "ffmpeg not found, install it to merge and convert downloads".to_string();
+        return true;
+    }
+
+    // Nothing to download, the file is already there
+    if line.contains("has already been downloaded") {
+        state.phase = DownloadPhase::Finalizing;
+        state.overall_percent = 0.95;
+        state.status_message = "File was already downloaded".to_string();
+        return true;
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn download_line(format_id: &str, downloaded: &str, total: &str) -> String {
+        format!("{}{{\"status\":\"downloading\",\"format_id\":\"{}\",\"downloaded_bytes\":{},\"total_bytes\":{},\
+            \"total_bytes_estimate\":null,\"speed\":null,\"eta\":null,\"fragment_index\":null,\"fragment_count\":null}}",
+            DOWNLOAD_PREFIX, format_id, downloaded, total)
+    }
+
+    fn progress(format_id: &str, downloaded: u64, total: u64) -> ProgressEvent {
+        ProgressEvent::Download(DownloadProgress {
+            status: "downloading".to_string(),
+            format_id: format_id.to_string(),
+            downloaded_bytes: downloaded,
+            total_bytes: Some(total),
+            speed: None,
+            eta: None,
+            fragment_index: None,
+            fragment_count: None,
+        })
+    }
+
+    #[test]
+    fn parses_download_line_with_null_fields() {
+        let line = format!("{}{{\"status\":\"downloading\",\"format_id\":\"137\",\"downloaded_bytes\":1024,\"total_bytes\":null,\
+            \"total_bytes_estimate\":4096.7,\"speed\":null,\"eta\":12.0,\"fragment_index\":3,\"fragment_count\":null}}", DOWNLOAD_PREFIX);
+
+        assert_eq!(parse_progress_line(&line), Some(ProgressEvent::Download(DownloadProgress {
+            status: "downloading".to_string(),
+            format_id: "137".to_string(),
+            downloaded_bytes: 1024,
+            total_bytes: Some(4096),
+            speed: None,
+            eta: Some(12),
+            fragment_index: Some(3),
+            fragment_count: None,
+        })));
+    }
+
+    #[test]
+    fn parses_line_without_any_numbers() {
+        let Some(ProgressEvent::Download(progress)) = parse_progress_line(&download_line("140", "null", "null")) else {
+            panic!("not a download line");
+        };
+        assert_eq!(progress.downloaded_bytes, 0);
+        assert_eq!(progress.total_bytes, None);
+        assert_eq!(progress.fraction(), None);
+    }
+
+    #[test]
+    fn parses_postprocess_line() {
+        let line = format!("{}{{\"status\":\"started\",\"postprocessor\":\"Merger\"}}", POSTPROCESS_PREFIX);
+        assert_eq!(parse_progress_line(&line), Some(ProgressEvent::Postprocess {
+            postprocessor: "Merger".to_string(),
+            status: "started".to_string(),
+        }));
+    }
+
+    #[test]
+    fn ignores_other_output() {
+        assert_eq!(parse_progress_line("[download] Destination: video.mp4"), None);
+        assert_eq!(parse_progress_line(&format!("{}{{not json", DOWNLOAD_PREFIX)), None);
+    }
+
+    #[test]
+    fn fraction_falls_back_to_fragments() {
+        let progress = DownloadProgress {
+            status: "downloading".to_string(),
+            format_id: "hls-720".to_string(),
+            downloaded_bytes: 500,
+            total_bytes: None,
+            speed: None,
+            eta: None,
+            fragment_index: Some(5),
+            fragment_count: Some(20),
+        };
+        assert_eq!(progress.fraction(), Some(0.25));
+        assert_eq!(DownloadProgress { status: "finished".to_string(), ..progress }.fraction(), Some(1.0));
+    }
+
+    #[test]
+    fn merged_streams_fill_one_bar() {
+        let mut state = ProgressState::for_format("137+140");
+        assert_eq!(state.stream_count, 2);
+
+        state.apply(&progress("137", 50, 100));
+        assert_eq!(state.stream_index, 0);
+        assert!((state.overall_percent - (0.05 + 0.25 * 0.75)).abs() < 1e-9);
+        assert!(state.status_message.starts_with("Downloading stream 1/2: 50.0%"));
+
+        state.apply(&progress("140", 50, 100));
+        assert_eq!(state.stream_index, 1);
+        assert!((state.overall_percent - (0.05 + 0.75 * 0.75)).abs() < 1e-9);
+        assert!(state.status_message.starts_with("Downloading stream 2/2"));
+    }
+
+    #[test]
+    fn overall_percent_never_goes_back() {
+        let mut state = ProgressState::for_format("137+140");
+        state.apply(&progress("137", 90, 100));
+        let before = state.overall_percent;
+
+        // A restarted stream reports less than before
+        state.apply(&progress("137", 10, 100));
+        assert_eq!(state.overall_percent, before);
+        assert_eq!(state.download_percent, 0.1);
+    }
+
+    #[test]
+    fn unnamed_streams_are_counted_as_they_appear() {
+        let mut state = ProgressState::for_format("bestvideo+bestaudio");
+        state.apply(&progress("248", 100, 100));
+        state.apply(&progress("251", 0, 100));
+        assert_eq!(state.stream_index, 1);
+
+        // A third id can't add a stream beyond the selector
+        state.apply(&progress("999", 0, 100));
+        assert_eq!(state.stream_index, 1);
+    }
+
+    #[test]
+    fn subtitles_leave_the_bar_alone() {
+        let mut state = ProgressState::for_format("22");
+        state.apply(&progress("22", 50, 100));
+        let before = state.overall_percent;
+
+        state.apply(&progress("NA", 10, 10));
+        assert_eq!(state.overall_percent, before);
+        assert_eq!(state.status_message, "Downloading subtitles...");
+    }
+
+    #[test]
+    fn postprocessing_follows_the_download() {
+        let mut state = ProgressState::for_format("137+140");
+        let line = format!("{}{{\"status\":\"started\",\"postprocessor\":\"Merger\"}}", POSTPROCESS_PREFIX);
+        assert!(update_progress_state(&line, &mut state));
+        assert_eq!(state.phase, DownloadPhase::Postprocessing);
+        assert_eq!(state.overall_percent, 0.85);
+        assert_eq!(state.status_message, "Merging formats...");
+    }
+}
@@ -0,0 +1,243 @@
+use serde::{Serialize, Deserialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use super::control::DownloadControl;
+use super::events::DownloadEvent;
+use super::job::DownloadJob;
+use super::progress::ProgressState;
+use super::subtitles::SubtitleOptions;
+use super::tagging::AudioTags;
+use super::template::OutputTemplate;
+
+// What to download for a queued URL
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum JobKind {
+    Video { format_id: String, merge_format: Option<String> },
+    Audio { format_id: String, audio_format: String },
+    Direct { checksum: Option<String> },  // Plain file over HTTP(S), checked against a SHA-256 if given
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueJob {
+    pub url: String,
+    pub output_path: String,
+    pub kind: JobKind,
+    pub label: String,            // Short description shown in the queue row
+    pub title: Option<String>,    // Media title if known before the download starts
+    pub duration: Option<u64>,    // Seconds
+    #[serde(default)]
+    pub subtitles: Option<SubtitleOptions>,  // Video jobs only
+    #[serde(default)]
+    pub tags: Option<AudioTags>,             // Audio jobs only
+    #[serde(default)]
+    pub output_template: Option<OutputTemplate>,  // None = yt-dlp's default file name
+    #[serde(default)]
+    pub rate_limit: Option<String>,               // None = the limit from the network settings
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed(String),
+    Cancelled,
+}
+
+#[derive(Debug, Clone)]
+pub enum QueueEvent {
+    Progress(u64, ProgressState),
+    Status(u64, JobStatus),
+}
+
+// Called from worker threads whenever a job changes
+pub type QueueNotifier = Arc<dyn Fn(QueueEvent) + Send + Sync>;
+
+struct QueueEntry {
+    id: u64,
+    job: QueueJob,
+    status: JobStatus,
+    control: Option<DownloadControl>,
+}
+
+struct QueueState {
+    entries: Vec<QueueEntry>,
+    next_id: u64,
+    max_concurrent: usize,
+}
+
+// Holds queued jobs and runs up to max_concurrent yt-dlp processes at once
+#[derive(Clone)]
+pub struct DownloadQueue {
+    state: Arc<Mutex<QueueState>>,
+    notifier: QueueNotifier,
+}
+
+impl DownloadQueue {
+    pub fn new(max_concurrent: usize, notifier: QueueNotifier) -> Self {
+        DownloadQueue {
+            state: Arc::new(Mutex::new(QueueState {
+                entries: Vec::new(),
+                next_id: 1,
+                max_concurrent: max_concurrent.max(1),
+            })),
+            notifier,
+        }
+    }
+
+    pub fn add(&self, job: QueueJob) -> u64 {
+        let id = {
+            let mut state = self.state.lock().unwrap();
+            let id = state.next_id;
+            state.next_id += 1;
+            state.entries.push(QueueEntry { id, job, status: JobStatus::Queued, control: None });
+            id
+        };
+
+        (self.notifier)(QueueEvent::Status(id, JobStatus::Queued));
+        self.schedule();
+        id
+    }
+
+    // Put a failed or cancelled job back into the queue
+    pub fn retry(&self, id: u64) {
+        let requeued = {
+            let mut state = self.state.lock().unwrap();
+            match state.entries.iter_mut().find(|e| e.id == id) {
+                Some(entry) if matches!(entry.status, JobStatus::Failed(_) | JobStatus::Cancelled) => {
+                    entry.status = JobStatus::Queued;
+                    entry.control = None;
+                    true
+                },
+                _ => false,
+            }
+        };
+
+        if requeued {
+            (self.notifier)(QueueEvent::Status(id, JobStatus::Queued));
+            self.schedule();
+        }
+    }
+
+    pub fn cancel(&self, id: u64) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(entry) = state.entries.iter_mut().find(|e| e.id == id) {
+            match entry.status {
+                JobStatus::Running => {
+                    if let Some(control) = &entry.control {
+                        if let Err(e) = control.cancel() {
+                            eprintln!("Failed to cancel job {}: {}", id, e);
+                        }
+                    }
+                },
+                JobStatus::Queued => {
+                    entry.status = JobStatus::Cancelled;
+                    drop(state);
+                    (self.notifier)(QueueEvent::Status(id, JobStatus::Cancelled));
+                },
+                _ => {}
+            }
+        }
+    }
+
+    // Drop a job from the queue, stopping it first if it is running
+    pub fn remove(&self, id: u64) {
+        self.cancel(id);
+        let mut state = self.state.lock().unwrap();
+        state.entries.retain(|e| e.id != id);
+    }
+
+    // Job and final output file of a finished entry, used to record it in the history
+    pub fn finished_job(&self, id: u64) -> Option<(QueueJob, Option<PathBuf>)> {
+        let state = self.state.lock().unwrap();
+        state.entries.iter()
+            .find(|e| e.id == id)
+            .map(|e| (e.job.clone(), e.control.as_ref().and_then(|c| c.output_file())))
+    }
+
+    // True once no job is waiting or running
+    pub fn is_idle(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        !state.entries.iter().any(|e| matches!(e.status, JobStatus::Queued | JobStatus::Running))
+    }
+
+    pub fn set_max_concurrent(&self, max_concurrent: usize) {
+        self.state.lock().unwrap().max_concurrent = max_concurrent.max(1);
+        self.schedule();
+    }
+
+    // Start queued jobs until the concurrency limit is reached
+    fn schedule(&self) {
+        let mut to_start = Vec::new();
+
+        {
+            let mut state = self.state.lock().unwrap();
+            let max_concurrent = state.max_concurrent;
+            let mut running = state.entries.iter()
+                .filter(|e| e.status == JobStatus::Running)
+                .count();
+
+            for entry in state.entries.iter_mut() {
+                if running >= max_concurrent {
+                    break;
+                }
+                if entry.status == JobStatus::Queued {
+                    let download = DownloadJob::new(entry.job.clone());
+                    entry.status = JobStatus::Running;
+                    entry.control = Some(download.control().clone());
+                    to_start.push((entry.id, entry.job.label.clone(), download));
+                    running += 1;
+                }
+            }
+        }
+
+        for (id, label, download) in to_start {
+            (self.notifier)(QueueEvent::Status(id, JobStatus::Running));
+            eprintln!("Starting queued download: {}", label);
+
+            let queue = self.clone();
+            thread::spawn(move || {
+                let notifier = Arc::clone(&queue.notifier);
+                let event = download.run(move |event| {
+                    if let DownloadEvent::Progress(progress) = event {
+                        notifier(QueueEvent::Progress(id, progress));
+                    }
+                });
+
+                let status = match event {
+                    DownloadEvent::Finished { .. } => JobStatus::Completed,
+                    DownloadEvent::Cancelled => JobStatus::Cancelled,
+                    DownloadEvent::Failed { reason, stderr_tail } => JobStatus::Failed(failure_reason(reason, &stderr_tail)),
+                    _ => JobStatus::Failed("Download ended unexpectedly".to_string()),
+                };
+
+                // A removed job has no row left to update
+                let still_queued = {
+                    let mut state = queue.state.lock().unwrap();
+                    match state.entries.iter_mut().find(|e| e.id == id) {
+                        Some(entry) => {
+                            entry.status = status.clone();
+                            true
+                        },
+                        None => false,
+                    }
+                };
+
+                if still_queued {
+                    (queue.notifier)(QueueEvent::Status(id, status));
+                }
+                queue.schedule();
+            });
+        }
+    }
+}
+
+// Exit status plus yt-dlp's last error line, short enough for a queue row
+fn failure_reason(reason: String, stderr_tail: &[String]) -> String {
+    match stderr_tail.iter().rev().find(|line| line.starts_with("ERROR:")) {
+        Some(error) => format!("{} ({})", error.trim_start_matches("ERROR:").trim(), reason),
+        None => reason,
+    }
+}
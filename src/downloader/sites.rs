@@ -0,0 +1,81 @@
+use anyhow::{Result, Context};
+use std::collections::HashSet;
+
+use super::ytdlp::ytdlp_command;
+
+// Popular sites, used until the extractor list of yt-dlp has been loaded
+const BUILTIN_SITES: [&str; 16] = [
+    "youtube", "vimeo", "dailymotion", "twitch", "soundcloud", "bandcamp", "twitter", "instagram",
+    "tiktok", "facebook", "reddit", "bilibili", "rumble", "odysee", "mixcloud", "peertube",
+];
+
+// Hosts whose name differs from their extractor name
+const HOST_ALIASES: [(&str, &str); 5] = [
+    ("youtu.be", "youtube"),
+    ("x.com", "twitter"),
+    ("redd.it", "reddit"),
+    ("fb.watch", "facebook"),
+    ("b23.tv", "bilibili"),
+];
+
+// Sites yt-dlp has a dedicated extractor for, matched by the host of a URL
+#[derive(Debug, Clone)]
+pub struct SupportedSites {
+    names: HashSet<String>,
+}
+
+impl Default for SupportedSites {
+    fn default() -> Self {
+        SupportedSites { names: BUILTIN_SITES.iter().map(|name| name.to_string()).collect() }
+    }
+}
+
+impl SupportedSites {
+    // Ask yt-dlp for its extractors; names like "youtube:tab" are reduced to "youtube"
+    pub fn load() -> Result<SupportedSites> {
+        let output = ytdlp_command()
+            .arg("--list-extractors")
+            .output()
+            .context("Failed to run yt-dlp --list-extractors")?;
+
+        if !output.status.success() {
+            return Err(anyhow::anyhow!("yt-dlp --list-extractors failed: {}", String::from_utf8_lossy(&output.stderr)));
+        }
+
+        let mut sites = SupportedSites::default();
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let name = line.split(':').next().unwrap_or("").trim().to_lowercase();
+            // The generic extractor would match every page
+            if !name.is_empty() && name != "generic" && !name.contains(' ') {
+                sites.names.insert(name);
+            }
+        }
+        Ok(sites)
+    }
+
+    // True for http(s) links to a site with an extractor, e.g. www.youtube.com -> "youtube"
+    pub fn matches(&self, url: &str) -> bool {
+        let Some(host) = url_host(url) else {
+            return false;
+        };
+
+        if let Some((_, name)) = HOST_ALIASES.iter().find(|(alias, _)| host == *alias || host.ends_with(&format!(".{}", alias))) {
+            return self.names.contains(*name);
+        }
+
+        // Every label but the top-level domain, so both "vimeo.com" and "player.vimeo.com" match
+        let labels: Vec<&str> = host.split('.').collect();
+        labels.len() > 1 && labels[..labels.len() - 1].iter().any(|label| self.names.contains(*label))
+    }
+}
+
+// Lowercase host of an http(s) URL without port and credentials
+pub(super) fn url_host(url: &str) -> Option<String> {
+    let rest = url.strip_prefix("https://").or_else(|| url.strip_prefix("http://"))?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?.split(':').next()?;
+    if host.is_empty() || !host.contains('.') {
+        return None;
+    }
+    Some(host.to_lowercase())
+}
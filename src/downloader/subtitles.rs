@@ -0,0 +1,141 @@
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use std::process::Command;
+
+use super::ffmpeg::ffmpeg_tools;
+
+// A subtitle language offered by the site
+#[derive(Debug, Clone, Serialize)]
+pub struct SubtitleTrack {
+    pub language: String,      // Language code, e.g. "en" or "pt-BR"
+    pub name: String,          // Display name if the site provides one
+    pub automatic: bool,       // Auto-generated captions rather than uploaded subtitles
+    pub formats: Vec<String>,  // Available extensions, e.g. "vtt", "srv3"
+}
+
+impl SubtitleTrack {
+    // "English (en)", with a marker for auto-generated captions
+    pub fn label(&self) -> String {
+        let mut label = if self.name.is_empty() || self.name == self.language {
+            self.language.clone()
+        } else {
+            format!("{} ({})", self.name, self.language)
+        };
+        if self.automatic {
+            label.push_str(" · auto-generated");
+        }
+        label
+    }
+}
+
+// How the selected subtitles end up next to the video
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubtitleMode {
+    #[default]
+    Srt,     // Separate .srt files
+    Vtt,     // Separate .vtt files
+    Embed,   // Muxed into the mp4/mkv/webm output
+}
+
+impl SubtitleMode {
+    pub const ALL: [SubtitleMode; 3] = [SubtitleMode::Srt, SubtitleMode::Vtt, SubtitleMode::Embed];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            SubtitleMode::Srt => "srt",
+            SubtitleMode::Vtt => "vtt",
+            SubtitleMode::Embed => "embed",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SubtitleMode::Srt => "Separate .srt files",
+            SubtitleMode::Vtt => "Separate .vtt files",
+            SubtitleMode::Embed => "Embed into video",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<SubtitleMode> {
+        SubtitleMode::ALL.into_iter().find(|mode| mode.id() == id)
+    }
+}
+
+// Subtitles to fetch along with a video
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubtitleOptions {
+    pub languages: Vec<String>,
+    pub automatic: bool,       // Also accept auto-generated captions
+    pub mode: SubtitleMode,
+}
+
+// Uploaded subtitles first, then auto-generated captions, each sorted by language
+pub(super) fn parse_subtitle_tracks(json: &Value) -> Vec<SubtitleTrack> {
+    let mut tracks = parse_track_map(&json["subtitles"], false);
+    tracks.extend(parse_track_map(&json["automatic_captions"], true));
+    tracks
+}
+
+fn parse_track_map(map: &Value, automatic: bool) -> Vec<SubtitleTrack> {
+    let Some(map) = map.as_object() else {
+        return Vec::new();
+    };
+
+    let mut tracks: Vec<SubtitleTrack> = map.iter()
+        // YouTube lists the live chat replay as a subtitle track
+        .filter(|(language, _)| language.as_str() != "live_chat")
+        .map(|(language, entries)| {
+            let entries = entries.as_array().map(|entries| entries.as_slice()).unwrap_or(&[]);
+            SubtitleTrack {
+                language: language.clone(),
+                name: entries.iter()
+                    .find_map(|entry| entry["name"].as_str())
+                    .unwrap_or("")
+                    .to_string(),
+                automatic,
+                formats: entries.iter()
+                    .filter_map(|entry| entry["ext"].as_str().map(|ext| ext.to_string()))
+                    .collect(),
+            }
+        })
+        .collect();
+
+    tracks.sort_by(|a, b| a.language.cmp(&b.language));
+    tracks
+}
+
+// Add the yt-dlp flags for writing or embedding the selected subtitles
+pub(super) fn add_subtitle_args(command: &mut Command, options: &SubtitleOptions) {
+    if options.languages.is_empty() {
+        return;
+    }
+
+    command
+        .arg("--write-subs")
+        .arg("--sub-langs")
+        .arg(options.languages.join(","));
+
+    if options.automatic {
+        command.arg("--write-auto-subs");
+    }
+
+    match options.mode {
+        // Converting needs ffmpeg; without it the preferred format is taken when offered
+        SubtitleMode::Srt => {
+            command.arg("--sub-format").arg("srt/best");
+            if ffmpeg_tools().ffmpeg.is_some() {
+                command.arg("--convert-subs").arg("srt");
+            }
+        },
+        SubtitleMode::Vtt => {
+            command.arg("--sub-format").arg("vtt/best");
+            if ffmpeg_tools().ffmpeg.is_some() {
+                command.arg("--convert-subs").arg("vtt");
+            }
+        },
+        SubtitleMode::Embed => {
+            command.arg("--embed-subs");
+        },
+    }
+}
@@ -0,0 +1,137 @@
+use serde::{Serialize, Deserialize};
+use std::process::Command;
+
+use super::media_info::MediaInfo;
+
+// Separators between artist and title in video titles like "Artist - Title"
+const ARTIST_SEPARATORS: [&str; 3] = [" - ", " – ", " — "];
+
+// Bracketed parts of video titles that don't belong in a song title
+const TITLE_NOISE: [&str; 9] = ["official", "video", "audio", "lyric", "visualizer", "visualiser", "hd", "4k", "mv"];
+
+// Tags written into extracted audio files; empty fields keep what yt-dlp derives itself
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AudioTags {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub date: String,          // Year or YYYY-MM-DD
+    pub embed_metadata: bool,  // Write title/artist/album/date tags
+    pub embed_cover: bool,     // Embed the thumbnail as cover art
+}
+
+impl AudioTags {
+    // Tags from the fetched metadata; music sites provide artist and track, otherwise
+    // "Artist - Title" is parsed from the video title with the uploader as fallback artist
+    pub fn from_media_info(info: &MediaInfo) -> AudioTags {
+        let (parsed_artist, parsed_title) = split_artist_title(&info.title)
+            .unwrap_or_else(|| (String::new(), clean_title(&info.title)));
+
+        let artist = info.artist.clone()
+            .or_else(|| Some(parsed_artist).filter(|artist| !artist.is_empty()))
+            .unwrap_or_else(|| uploader_as_artist(&info.uploader));
+
+        AudioTags {
+            title: info.track.clone().unwrap_or(parsed_title),
+            artist,
+            album: info.album.clone().unwrap_or_default(),
+            date: info.release_year.map(|year| year.to_string())
+                .or_else(|| info.formatted_upload_date())
+                .unwrap_or_default(),
+            embed_metadata: true,
+            embed_cover: true,
+        }
+    }
+
+    // Tags from a playlist entry title, the playlist name serves as album
+    pub fn from_title(title: &str, uploader: &str, album: &str) -> AudioTags {
+        let (artist, title) = split_artist_title(title)
+            .unwrap_or_else(|| (uploader_as_artist(uploader), clean_title(title)));
+
+        AudioTags {
+            title,
+            artist,
+            album: album.to_string(),
+            date: String::new(),
+            embed_metadata: true,
+            embed_cover: true,
+        }
+    }
+}
+
+// "Artist - Title (Official Video)" -> ("Artist", "Title")
+pub fn split_artist_title(title: &str) -> Option<(String, String)> {
+    let (artist, song) = ARTIST_SEPARATORS.iter()
+        .find_map(|separator| title.split_once(separator))?;
+
+    let artist = artist.trim();
+    let song = clean_title(song);
+    if artist.is_empty() || song.is_empty() {
+        return None;
+    }
+
+    Some((artist.to_string(), song))
+}
+
+// Drop "(Official Video)", "[Lyrics]" and similar parts and surrounding quotes
+fn clean_title(title: &str) -> String {
+    let mut cleaned = String::new();
+    let mut rest = title;
+
+    while let Some(start) = rest.find(['(', '[']) {
+        let close = if rest[start..].starts_with('(') { ')' } else { ']' };
+        let Some(length) = rest[start..].find(close) else {
+            break;
+        };
+
+        let inner = rest[start + 1..start + length].to_lowercase();
+        let is_noise = inner.split(|c: char| !c.is_alphanumeric())
+            .any(|word| TITLE_NOISE.iter().any(|noise| word.starts_with(noise)));
+
+        cleaned.push_str(&rest[..start]);
+        if !is_noise {
+            cleaned.push_str(&rest[start..=start + length]);
+        }
+        rest = &rest[start + length + 1..];
+    }
+    cleaned.push_str(rest);
+
+    cleaned.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_matches(|c| c == '"' || c == '\'' || c == '“' || c == '”')
+        .to_string()
+}
+
+// Auto-generated YouTube Music channels are called "Artist - Topic"
+fn uploader_as_artist(uploader: &str) -> String {
+    uploader.trim_end_matches(" - Topic").to_string()
+}
+
+// Add the yt-dlp flags for cover art and tags
+pub(super) fn add_tag_args(command: &mut Command, tags: &AudioTags) {
+    if tags.embed_cover {
+        // Cover art must be JPEG or PNG for most players
+        command.arg("--embed-thumbnail").arg("--convert-thumbnails").arg("jpg");
+    }
+
+    if !tags.embed_metadata {
+        return;
+    }
+
+    command.arg("--embed-metadata");
+
+    // meta_* fields override what --embed-metadata writes
+    for (value, field) in [(&tags.title, "title"), (&tags.artist, "artist"), (&tags.album, "album"), (&tags.date, "date")] {
+        if !value.trim().is_empty() {
+            command.arg("--parse-metadata").arg(constant_metadata(value.trim(), field));
+        }
+    }
+}
+
+// --parse-metadata FROM:TO that sets a field to a fixed value. The leading space keeps a
+// single word from being read as a field name and is matched away again by the regex.
+fn constant_metadata(value: &str, field: &str) -> String {
+    let template = value.replace('%', "%%").replace(':', "\\:");
+    format!(" {}: (?P<meta_{}>.+)", template, field)
+}
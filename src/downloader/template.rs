@@ -0,0 +1,175 @@
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::process::Command;
+
+use super::media_info::MediaInfo;
+
+// yt-dlp's own default, used when no template is configured
+pub const DEFAULT_TEMPLATE: &str = "%(title)s [%(id)s].%(ext)s";
+
+// Tokens offered by the template picker: (label, token)
+pub const TEMPLATE_TOKENS: [(&str, &str); 8] = [
+    ("Title", "%(title)s"),
+    ("Uploader", "%(uploader)s"),
+    ("Upload date", "%(upload_date)s"),
+    ("Resolution", "%(resolution)s"),
+    ("Playlist index", "%(playlist_index)03d"),
+    ("Playlist title", "%(playlist_title)s"),
+    ("Video ID", "%(id)s"),
+    ("Extension", "%(ext)s"),
+];
+
+// File name template and sanitization passed to yt-dlp as -o
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputTemplate {
+    pub template: String,
+    pub restrict_filenames: bool,  // ASCII only, no spaces or shell characters
+}
+
+impl OutputTemplate {
+    pub fn new(template: &str, restrict_filenames: bool) -> Self {
+        let template = if template.trim().is_empty() { DEFAULT_TEMPLATE } else { template.trim() };
+        OutputTemplate { template: template.to_string(), restrict_filenames }
+    }
+
+    // Playlist entries are downloaded one URL at a time, so yt-dlp doesn't know their
+    // playlist fields; fill them in before handing the template over
+    pub fn for_playlist_entry(&self, index: usize, playlist_title: &str) -> Self {
+        let index = index.to_string();
+        let template = replace_fields(&self.template, |key, spec| {
+            let value = match key {
+                "playlist_index" | "playlist_autonumber" => &index,
+                "playlist_title" | "playlist" => playlist_title,
+                _ => return None,
+            };
+            // Sanitize now, yt-dlp would treat the inserted text as a literal
+            Some(sanitize_filename(&apply_spec(value, spec), self.restrict_filenames).replace('%', "%%"))
+        });
+        OutputTemplate { template, restrict_filenames: self.restrict_filenames }
+    }
+
+    // File name yt-dlp will produce for the given field values, "NA" for unknown fields
+    pub fn preview(&self, values: &HashMap<&str, String>) -> String {
+        let name = replace_fields(&self.template, |key, spec| {
+            // "a,b|default": first field with a value, else the default
+            let (fields, default) = key.split_once('|').unwrap_or((key, "NA"));
+            let value = fields.split(',')
+                .find_map(|field| values.get(field.trim()).filter(|value| !value.is_empty()))
+                .map(|value| value.as_str())
+                .unwrap_or(default);
+            Some(sanitize_filename(&apply_spec(value, spec), self.restrict_filenames))
+        });
+        name.replace("%%", "%")
+    }
+
+    pub(super) fn add_args(&self, command: &mut Command) {
+        command.arg("-o").arg(&self.template);
+        if self.restrict_filenames {
+            command.arg("--restrict-filenames");
+        }
+    }
+}
+
+// Field values of fetched media for previews; the extension and resolution depend on the selection
+pub fn template_values<'a>(info: &MediaInfo, ext: &str, resolution: &str) -> HashMap<&'a str, String> {
+    let mut values = HashMap::new();
+    values.insert("title", info.title.clone());
+    values.insert("id", info.id.clone());
+    values.insert("uploader", info.uploader.clone());
+    values.insert("channel", info.uploader.clone());
+    values.insert("upload_date", info.upload_date.clone().unwrap_or_default());
+    values.insert("ext", ext.to_string());
+    values.insert("resolution", resolution.to_string());
+    if let Some(duration) = info.duration {
+        values.insert("duration", duration.to_string());
+    }
+    values
+}
+
+// Replace each %(key)spec field for which replace returns a value, keeping the others
+fn replace_fields<F>(template: &str, replace: F) -> String
+where
+    F: Fn(&str, &str) -> Option<String>,
+{
+    let mut result = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find('%') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+
+        // "%%" is a literal percent sign
+        if let Some(remaining) = after.strip_prefix('%') {
+            result.push_str("%%");
+            rest = remaining;
+            continue;
+        }
+
+        let field = after.strip_prefix('(')
+            .and_then(|inner| inner.find(')').map(|end| (&inner[..end], &inner[end + 1..])))
+            .and_then(|(key, tail)| {
+                // Conversion spec like "s" or "03d" ends at the first letter
+                let spec_len = tail.find(|c: char| c.is_ascii_alphabetic())? + 1;
+                Some((key, &tail[..spec_len], &tail[spec_len..]))
+            });
+
+        match field {
+            Some((key, spec, remaining)) => {
+                match replace(key, spec) {
+                    Some(value) => result.push_str(&value),
+                    None => result.push_str(&rest[start..rest.len() - remaining.len()]),
+                }
+                rest = remaining;
+            },
+            None => {
+                result.push('%');
+                rest = after;
+            },
+        }
+    }
+
+    result.push_str(rest);
+    result
+}
+
+// Zero padding for numeric specs like "03d"
+fn apply_spec(value: &str, spec: &str) -> String {
+    let Some(width) = spec.strip_suffix('d').and_then(|width| width.parse::<usize>().ok()) else {
+        return value.to_string();
+    };
+    match value.parse::<u64>() {
+        Ok(number) if spec.starts_with('0') => format!("{:0width$}", number, width = width),
+        Ok(number) => format!("{:width$}", number, width = width),
+        Err(_) => value.to_string(),
+    }
+}
+
+// Same replacements yt-dlp applies to field values: full-width look-alikes for characters
+// that are invalid in file names, or underscores in restricted mode
+fn sanitize_filename(value: &str, restricted: bool) -> String {
+    let mut sanitized = String::new();
+    for c in value.chars() {
+        match c {
+            _ if (c as u32) < 32 || c as u32 == 127 => {},
+            '/' if !restricted => sanitized.push('⧸'),
+            '\\' if !restricted => sanitized.push('⧹'),
+            '"' | '*' | ':' | '<' | '>' | '?' | '|' if !restricted => {
+                sanitized.push(char::from_u32(c as u32 + 0xfee0).unwrap_or('_'));
+            },
+            '?' | '"' => {},
+            ':' => sanitized.push_str("_-"),
+            '/' | '\\' | '|' | '*' | '<' | '>' => sanitized.push('_'),
+            _ if restricted && (c.is_whitespace() || !c.is_ascii() || "!&'()[]{}$;`^,#".contains(c)) => sanitized.push('_'),
+            _ => sanitized.push(c),
+        }
+    }
+
+    if restricted {
+        // Collapse runs of underscores like yt-dlp does
+        while sanitized.contains("__") {
+            sanitized = sanitized.replace("__", "_");
+        }
+        sanitized = sanitized.trim_matches('_').to_string();
+    }
+    sanitized
+}
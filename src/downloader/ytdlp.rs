@@ -0,0 +1,178 @@
+use anyhow::{Result, Context};
+use once_cell::sync::Lazy;
+use std::env;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Releases older than this are likely broken by site changes
+const OUTDATED_AFTER_DAYS: i64 = 180;
+
+// yt-dlp executable used for every fetch and download, "yt-dlp" means PATH lookup
+static YTDLP_PATH: Lazy<RwLock<String>> = Lazy::new(|| RwLock::new("yt-dlp".to_string()));
+
+// The executable found by the last locate_ytdlp call
+static ACTIVE_YTDLP: Lazy<RwLock<Option<YtdlpBinary>>> = Lazy::new(|| RwLock::new(None));
+
+fn use_ytdlp_path(path: &str) {
+    *YTDLP_PATH.write().unwrap() = path.to_string();
+}
+
+pub fn active_ytdlp() -> Option<YtdlpBinary> {
+    ACTIVE_YTDLP.read().unwrap().clone()
+}
+
+// Start a yt-dlp command using the configured executable
+pub fn ytdlp_command() -> Command {
+    Command::new(YTDLP_PATH.read().unwrap().as_str())
+}
+
+// Where a working yt-dlp was found
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum YtdlpSource {
+    Custom,   // Executable set in the preferences
+    AppData,  // Private copy in the XDG data dir
+    Path,     // First yt-dlp in PATH
+}
+
+impl YtdlpSource {
+    pub fn label(&self) -> &'static str {
+        match self {
+            YtdlpSource::Custom => "custom path",
+            YtdlpSource::AppData => "app copy",
+            YtdlpSource::Path => "PATH",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct YtdlpBinary {
+    pub path: PathBuf,
+    pub source: YtdlpSource,
+    pub version: String,  // Release date like "2024.08.06"
+}
+
+impl YtdlpBinary {
+    // "yt-dlp 2024.08.06 (PATH: /usr/bin/yt-dlp)"
+    pub fn description(&self) -> String {
+        format!("yt-dlp {} ({}: {})", self.version, self.source.label(), self.path.display())
+    }
+
+    // Versions are release dates, so the age tells whether an update is due
+    pub fn is_outdated(&self) -> bool {
+        let mut parts = self.version.split(['.', '-']).map(|part| part.parse::<i64>().ok());
+        let (Some(Some(year)), Some(Some(month)), Some(Some(day))) = (parts.next(), parts.next(), parts.next()) else {
+            return false;
+        };
+
+        let today = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64 / 86400).unwrap_or(0);
+        today - days_from_civil(year, month, day) > OUTDATED_AFTER_DAYS
+    }
+}
+
+// Days since 1970-01-01 for a proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+// Private copy that can be replaced from the preferences, ~/.local/share/HyprDownloader/bin/yt-dlp
+pub fn app_data_ytdlp() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("HyprDownloader").join("bin").join("yt-dlp"))
+}
+
+fn find_in_path(name: &str) -> Option<PathBuf> {
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+// Run `yt-dlp --version` to make sure the executable actually works
+pub fn ytdlp_version(path: &Path) -> Result<String> {
+    let output = Command::new(path)
+        .arg("--version")
+        .output()
+        .with_context(|| format!("Failed to run {}", path.display()))?;
+
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !output.status.success() || version.is_empty() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.lines().last().unwrap_or("no version reported").trim().to_string();
+        return Err(anyhow::anyhow!("{} --version failed: {}", path.display(), reason));
+    }
+    Ok(version)
+}
+
+// Pick the first working yt-dlp: the custom path, then the app copy, then PATH.
+// The app copy comes before PATH so an outdated pip or distro install can't shadow it.
+pub fn locate_ytdlp(custom_path: &str) -> Result<YtdlpBinary> {
+    let custom_path = custom_path.trim();
+    let mut candidates = Vec::new();
+    if !custom_path.is_empty() && custom_path != "yt-dlp" {
+        let path = if custom_path.contains('/') { Some(PathBuf::from(custom_path)) } else { find_in_path(custom_path) };
+        candidates.push((YtdlpSource::Custom, path));
+    }
+    candidates.push((YtdlpSource::AppData, app_data_ytdlp().filter(|path| path.is_file())));
+    candidates.push((YtdlpSource::Path, find_in_path("yt-dlp")));
+
+    let mut problems = Vec::new();
+    for (source, path) in candidates {
+        let Some(path) = path else {
+            problems.push(format!("{}: not found", source.label()));
+            continue;
+        };
+
+        match ytdlp_version(&path) {
+            Ok(version) => {
+                if !problems.is_empty() {
+                    eprintln!("Skipped unusable yt-dlp ({})", problems.join("; "));
+                }
+                use_ytdlp_path(&path.to_string_lossy());
+                let binary = YtdlpBinary { path, source, version };
+                *ACTIVE_YTDLP.write().unwrap() = Some(binary.clone());
+                return Ok(binary);
+            },
+            Err(e) => problems.push(format!("{}: {:#}", source.label(), e)),
+        }
+    }
+
+    // Keep the old behaviour so the error of the first command names the executable
+    use_ytdlp_path(if custom_path.is_empty() { "yt-dlp" } else { custom_path });
+    *ACTIVE_YTDLP.write().unwrap() = None;
+    Err(anyhow::anyhow!("No working yt-dlp found\n{}", problems.join("\n")))
+}
+
+// Copy a downloaded yt-dlp release into the app data dir, replacing the previous copy
+pub fn install_ytdlp(file: &Path) -> Result<YtdlpBinary> {
+    let target = app_data_ytdlp()
+        .ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?;
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+
+    // Check the new file next to the old one before swapping, a broken download keeps the old copy
+    let staged = target.with_extension("new");
+    fs::copy(file, &staged)
+        .with_context(|| format!("Failed to copy {}", file.display()))?;
+    fs::set_permissions(&staged, fs::Permissions::from_mode(0o755))?;
+
+    let version = match ytdlp_version(&staged) {
+        Ok(version) => version,
+        Err(e) => {
+            let _ = fs::remove_file(&staged);
+            return Err(e.context(format!("{} is not a working yt-dlp executable", file.display())));
+        }
+    };
+
+    fs::rename(&staged, &target)
+        .with_context(|| format!("Failed to replace {}", target.display()))?;
+    Ok(YtdlpBinary { path: target, source: YtdlpSource::AppData, version })
+}
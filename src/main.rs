@@ -1,18 +1,30 @@
 mod ui;
 mod app;
 mod downloader;
+mod cli;
 
 use anyhow::Result;
-use gtk4::{prelude::*, Application};
+use gtk4::{prelude::*, gio, Application};
 
 const APP_ID: &str = "com.github.mediadownloader";
 
 fn main() -> Result<()> {
+    // Subcommands run headless, without touching GTK or a display
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(command) = cli::parse_args(&args) {
+        return cli::run(command?);
+    }
+
+    // URLs arrive as arguments, through "Open With" or from a second launch over D-Bus
     let app = Application::builder()
         .application_id(APP_ID)
+        .flags(gio::ApplicationFlags::HANDLES_OPEN | gio::ApplicationFlags::HANDLES_COMMAND_LINE)
         .build();
 
-    app.connect_activate(app::build_ui);
+    app::add_main_options(&app);
+    app.connect_activate(app::activate);
+    app.connect_open(app::open_files);
+    app.connect_command_line(app::handle_command_line);
     app.run();
 
     Ok(())
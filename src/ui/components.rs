@@ -1,5 +1,5 @@
-use gtk4::{prelude::*, Box as GtkBox, Label, Entry, Button, ComboBoxText, Orientation, Spinner, Image};
-use gtk4::{Align, MessageDialog, DialogFlags, MessageType, ButtonsType, Window};
+use gtk4::{prelude::*, Box as GtkBox, Label, Entry, Button, ComboBoxText, Orientation, Spinner, Image, Picture};
+use gtk4::{Align, MessageDialog, DialogFlags, MessageType, ButtonsType, Window, ProgressBar};
 
 pub fn create_title_with_subtitle(title: &str, subtitle: &str) -> GtkBox {
     let container = GtkBox::builder()
@@ -171,6 +171,163 @@ pub fn create_spinner_with_label() -> (GtkBox, Spinner, Label) {
     (container, spinner, label)
 }
 
+// Preview of the fetched media: thumbnail next to title, channel and length
+#[derive(Clone)]
+pub struct MediaPreview {
+    pub container: GtkBox,
+    pub thumbnail: Picture,
+    pub title_label: Label,
+    pub details_label: Label,
+    pub checksum_box: GtkBox,     // Only shown for direct file links
+    pub checksum_entry: Entry,
+}
+
+impl MediaPreview {
+    pub fn show(&self, title: &str, details: &str) {
+        self.title_label.set_text(title);
+        self.details_label.set_text(details);
+        self.thumbnail.set_filename(None::<&std::path::Path>);
+        self.checksum_box.set_visible(false);
+        self.checksum_entry.set_text("");
+        self.container.set_visible(true);
+    }
+    
+    // Direct file link, with the checksum the server announced if any
+    pub fn show_file(&self, title: &str, details: &str, digest: Option<&str>) {
+        self.show(title, details);
+        self.checksum_entry.set_text(digest.unwrap_or(""));
+        self.checksum_box.set_visible(true);
+    }
+    
+    pub fn hide(&self) {
+        self.container.set_visible(false);
+    }
+}
+
+pub fn create_media_preview() -> MediaPreview {
+    let container = GtkBox::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(10)
+        .margin_top(4)
+        .visible(false)
+        .build();
+    
+    container.add_css_class("media-preview");
+    
+    let thumbnail = Picture::builder()
+        .width_request(160)
+        .height_request(90)
+        .can_shrink(true)
+        .keep_aspect_ratio(true)
+        .build();
+    
+    thumbnail.add_css_class("media-thumbnail");
+    
+    let text_box = GtkBox::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(4)
+        .valign(Align::Center)
+        .hexpand(true)
+        .build();
+    
+    let title_label = Label::builder()
+        .halign(Align::Start)
+        .xalign(0.0)
+        .wrap(true)
+        .lines(2)
+        .ellipsize(gtk4::pango::EllipsizeMode::End)
+        .build();
+    
+    title_label.add_css_class("info-title");
+    
+    let details_label = create_status_label("");
+    
+    let (checksum_box, checksum_entry) = create_labeled_entry("SHA-256:", "Optional, checked after the download", None);
+    checksum_entry.set_tooltip_text(Some("Filled in when the server announces a checksum"));
+    checksum_box.set_visible(false);
+    
+    text_box.append(&title_label);
+    text_box.append(&details_label);
+    text_box.append(&checksum_box);
+    
+    container.append(&thumbnail);
+    container.append(&text_box);
+    
+    MediaPreview {
+        container,
+        thumbnail,
+        title_label,
+        details_label,
+        checksum_box,
+        checksum_entry,
+    }
+}
+
+// Widgets of a single row in the download queue list
+#[derive(Clone)]
+pub struct QueueRow {
+    pub container: GtkBox,
+    pub progress_bar: ProgressBar,
+    pub status_label: Label,
+    pub retry_button: Button,
+    pub remove_button: Button,
+}
+
+pub fn create_queue_row(title: &str) -> QueueRow {
+    let container = GtkBox::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(4)
+        .build();
+    
+    container.add_css_class("queue-row");
+    
+    let header = GtkBox::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(6)
+        .build();
+    
+    let title_label = Label::builder()
+        .label(title)
+        .halign(Align::Start)
+        .hexpand(true)
+        .ellipsize(gtk4::pango::EllipsizeMode::Middle)
+        .xalign(0.0)
+        .build();
+    
+    title_label.add_css_class("input-label");
+    
+    let retry_button = create_button("Retry", "queue-button");
+    retry_button.set_visible(false);
+    
+    let remove_button = create_button("Remove", "queue-button");
+    
+    header.append(&title_label);
+    header.append(&retry_button);
+    header.append(&remove_button);
+    
+    let progress_bar = ProgressBar::builder()
+        .show_text(true)
+        .text("0%")
+        .fraction(0.0)
+        .build();
+    
+    progress_bar.add_css_class("download-progress");
+    
+    let status_label = create_status_label("Waiting in queue");
+    
+    container.append(&header);
+    container.append(&progress_bar);
+    container.append(&status_label);
+    
+    QueueRow {
+        container,
+        progress_bar,
+        status_label,
+        retry_button,
+        remove_button,
+    }
+}
+
 pub fn show_error_dialog(parent: &impl IsA<Window>, title: &str, message: &str) {
     let dialog = MessageDialog::builder()
         .transient_for(parent)
@@ -200,5 +357,19 @@ pub fn create_folder_chooser_dialog(parent: &impl IsA<Window>, title: &str) -> g
     dialog.add_button("Cancel", gtk4::ResponseType::Cancel.into());
     dialog.add_button("Select", gtk4::ResponseType::Accept.into());
     
+    dialog
+}
+
+pub fn create_file_chooser_dialog(parent: &impl IsA<Window>, title: &str) -> gtk4::FileChooserDialog {
+    let dialog = gtk4::FileChooserDialog::builder()
+        .title(title)
+        .transient_for(parent)
+        .modal(true)
+        .action(gtk4::FileChooserAction::Open)
+        .build();
+    
+    dialog.add_button("Cancel", gtk4::ResponseType::Cancel);
+    dialog.add_button("Open", gtk4::ResponseType::Accept);
+    
     dialog
 } 
\ No newline at end of file
@@ -113,6 +113,15 @@ pub fn load_css() {
             color: @theme_selected_fg_color;
         }
         
+        .cancel-button {
+            font-weight: 500;
+            min-height: 34px;
+            min-width: 70px;
+            border-radius: 5px;
+            background-color: @error_color;
+            color: @theme_selected_fg_color;
+        }
+        
         .open-folder-button {
             font-weight: 500;
             min-height: 34px;
@@ -120,6 +129,18 @@ pub fn load_css() {
             border-radius: 5px;
         }
         
+        .queue-row {
+            padding: 8px;
+            border-bottom: 1px solid alpha(@theme_fg_color, 0.1);
+        }
+        
+        .queue-button {
+            min-height: 24px;
+            padding: 0 8px;
+            border-radius: 4px;
+            font-size: 12px;
+        }
+        
         .download-progress {
             min-height: 6px;
             border-radius: 3px;
@@ -149,6 +170,22 @@ pub fn load_css() {
             font-size: 14px;
         }
         
+        .media-preview {
+            border-top: 1px solid alpha(@theme_fg_color, 0.1);
+            padding-top: 8px;
+        }
+        
+        .media-thumbnail {
+            border-radius: 4px;
+            background-color: alpha(@theme_fg_color, 0.05);
+        }
+        
+        .clipboard-banner {
+            padding: 6px 10px;
+            border-radius: 6px;
+            background-color: alpha(@theme_selected_bg_color, 0.15);
+        }
+        
         /* Video and Audio option boxes */
         .info-box .section-title {
             font-size: 14px;